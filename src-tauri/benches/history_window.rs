@@ -0,0 +1,125 @@
+//! Peak memory and time of loading a 5000-entry history from the JSON store
+//! and from the low-memory index, in full and one page at a time. Run with
+//! `cargo bench --bench history_window`
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use app_lib::{read_index, read_index_window, write_index_at, ClipboardEntry, ClipboardHistory};
+use chrono::Utc;
+
+const ENTRIES: usize = 5000;
+const PAGE: usize = 50;
+const ROUNDS: u32 = 20;
+
+/// Counts live heap bytes so peak memory can be reported
+struct CountingAlloc;
+
+static HEAP_LIVE: AtomicUsize = AtomicUsize::new(0);
+static HEAP_PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let live = HEAP_LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            HEAP_PEAK.fetch_max(live, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        HEAP_LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+/// Result of `f` and how far the heap grew above its starting size while it ran
+fn peak_heap<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let start = HEAP_LIVE.load(Ordering::Relaxed);
+    HEAP_PEAK.store(start, Ordering::Relaxed);
+    let result = f();
+    (result, HEAP_PEAK.load(Ordering::Relaxed).saturating_sub(start))
+}
+
+/// Average time of `ROUNDS` runs of `f`, in microseconds
+fn time_us(mut f: impl FnMut()) -> u128 {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed().as_micros() / ROUNDS as u128
+}
+
+/// `n` entries with the optional fields a real capture tends to fill in
+fn sample_history(n: usize) -> ClipboardHistory {
+    let mut history = ClipboardHistory::new(n);
+    for i in 0..n {
+        let content_type = if i.is_multiple_of(5) { "image/png" } else { "text" };
+        history.entries.push_back(ClipboardEntry {
+            source_app: Some("org.gnome.TextEditor".to_string()),
+            mimes: vec!["text/plain;charset=utf-8".to_string(), "UTF8_STRING".to_string()],
+            last_used: Some(Utc::now()),
+            ..ClipboardEntry::new(uuid::Uuid::new_v4().to_string(), content_type.to_string(), 64 + i)
+        });
+    }
+    history
+}
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("claw-bench-window-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let json_path = dir.join("claw_history.json");
+    let index_path = dir.join("claw_history.index.jsonl");
+    let history = sample_history(ENTRIES);
+    fs::write(&json_path, serde_json::to_vec(&history).unwrap()).unwrap();
+    write_index_at(&index_path, &history).unwrap();
+    drop(history);
+
+    // What the JSON store does: the plugin parses the file into a value tree
+    // (and keeps it), then `load_history` deserializes the entries from it
+    let json_load = || {
+        let value: serde_json::Value = serde_json::from_slice(&fs::read(&json_path).unwrap()).unwrap();
+        let history = serde_json::from_value::<ClipboardHistory>(value.clone()).unwrap();
+        (value, history)
+    };
+    let index_load = || read_index(fs::File::open(&index_path).unwrap()).unwrap();
+    let window = |offset: usize| read_index_window(fs::File::open(&index_path).unwrap(), offset, PAGE).unwrap();
+
+    let ((cached, full), json_peak) = peak_heap(json_load);
+    assert_eq!(full.entries.len(), ENTRIES);
+    drop((cached, full));
+    let (full, index_peak) = peak_heap(index_load);
+    assert_eq!(full.entries.len(), ENTRIES);
+    drop(full);
+    let (first, first_peak) = peak_heap(|| window(0));
+    assert_eq!(first.len(), PAGE);
+    let (last, last_peak) = peak_heap(|| window(ENTRIES - PAGE));
+    assert_eq!(last.len(), PAGE);
+
+    let json_us = time_us(|| drop(json_load()));
+    let index_us = time_us(|| drop(index_load()));
+    let first_us = time_us(|| drop(window(0)));
+    let last_us = time_us(|| drop(window(ENTRIES - PAGE)));
+
+    println!(
+        "JSON store, full load:  peak {} KiB, {}µs ({} bytes)",
+        json_peak / 1024,
+        json_us,
+        fs::metadata(&json_path).unwrap().len()
+    );
+    println!(
+        "index, full load:       peak {} KiB, {}µs ({} bytes)",
+        index_peak / 1024,
+        index_us,
+        fs::metadata(&index_path).unwrap().len()
+    );
+    println!("index, first {} entries: peak {} KiB, {}µs", PAGE, first_peak / 1024, first_us);
+    println!("index, last {} entries:  peak {} KiB, {}µs", PAGE, last_peak / 1024, last_us);
+    let _ = fs::remove_dir_all(&dir);
+}
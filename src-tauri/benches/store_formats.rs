@@ -0,0 +1,68 @@
+//! Load and save cost of a 1000-entry history in both store formats. Run with
+//! `cargo bench --bench store_formats`
+
+use std::fs;
+use std::time::Instant;
+
+use app_lib::{decode_binary_store, encode_binary_store, ClipboardEntry, ClipboardHistory};
+use chrono::Utc;
+
+const ENTRIES: usize = 1000;
+const ROUNDS: u32 = 50;
+
+/// Average time of `ROUNDS` runs of `f`, in microseconds
+fn time_us(mut f: impl FnMut()) -> u128 {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed().as_micros() / ROUNDS as u128
+}
+
+/// `n` entries with the optional fields a real capture tends to fill in
+fn sample_history(n: usize) -> ClipboardHistory {
+    let mut history = ClipboardHistory::new(n);
+    for i in 0..n {
+        let content_type = if i.is_multiple_of(5) { "image/png" } else { "text" };
+        history.entries.push_back(ClipboardEntry {
+            source_app: Some("org.gnome.TextEditor".to_string()),
+            mimes: vec!["text/plain;charset=utf-8".to_string(), "UTF8_STRING".to_string()],
+            last_used: Some(Utc::now()),
+            ..ClipboardEntry::new(uuid::Uuid::new_v4().to_string(), content_type.to_string(), 64 + i)
+        });
+    }
+    history
+}
+
+fn main() {
+    let history = sample_history(ENTRIES);
+    let dir = std::env::temp_dir().join(format!("claw-bench-store-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let bin_path = dir.join("claw_history.bin");
+    let json_path = dir.join("claw_history.json");
+
+    let bin_save = time_us(|| fs::write(&bin_path, encode_binary_store(&history).unwrap()).unwrap());
+    let bin_load = time_us(|| {
+        let loaded = decode_binary_store(&fs::read(&bin_path).unwrap()).unwrap();
+        assert_eq!(loaded.entries.len(), ENTRIES);
+    });
+    let json_save = time_us(|| fs::write(&json_path, serde_json::to_vec(&history).unwrap()).unwrap());
+    let json_load = time_us(|| {
+        let loaded: ClipboardHistory = serde_json::from_slice(&fs::read(&json_path).unwrap()).unwrap();
+        assert_eq!(loaded.entries.len(), ENTRIES);
+    });
+
+    println!(
+        "bincode: save {}µs, load {}µs, {} bytes",
+        bin_save,
+        bin_load,
+        fs::metadata(&bin_path).unwrap().len()
+    );
+    println!(
+        "json:    save {}µs, load {}µs, {} bytes",
+        json_save,
+        json_load,
+        fs::metadata(&json_path).unwrap().len()
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -3,18 +3,156 @@
 
 use crate::detect::DesktopEnv;
 use once_cell::sync::Lazy;
-use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
 use std::sync::Mutex;
-use wl_clipboard_rs::copy::{MimeType, Source};
-use wl_clipboard_rs::paste::{get_contents, ClipboardType, MimeType as PasteMimeType, Seat};
+use wl_clipboard_rs::copy::{MimeSource, MimeType, Source};
+use wl_clipboard_rs::paste::{
+    get_contents, get_mime_types, ClipboardType, Error as PasteError, MimeType as PasteMimeType, Seat,
+};
 use x11_clipboard::Clipboard as X11Clipboard;
 
 use crate::LAST_WRITTEN_CLIPBOARD;
-use crate::utils::{detect_content_type, normalize_clipboard_bytes};
+use crate::utils::{clipboard_hash, detect_content_type, lock_recover, normalize_clipboard_bytes, transcode_to_utf8};
+
+/// Upper bound for a single clipboard read (`clipboard.max_read_bytes`)
+static MAX_READ_BYTES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_READ_BYTES);
+pub const DEFAULT_MAX_READ_BYTES: usize = 64 * 1024 * 1024;
+
+pub fn set_max_read_bytes(limit: usize) {
+    MAX_READ_BYTES.store(limit, Ordering::Relaxed);
+}
+
+fn max_read_bytes() -> usize {
+    MAX_READ_BYTES.load(Ordering::Relaxed)
+}
+
+/// Leading bytes hashed to recognise an offer skipped for exceeding the read cap
+const SKIP_FINGERPRINT_BYTES: usize = 4096;
+
+/// Fingerprint of the last offer skipped for exceeding the read cap, so each
+/// oversized copy is logged once rather than on every poll. Cleared by the
+/// next successful read.
+static SKIPPED_OVERSIZE: Mutex<Option<u64>> = Mutex::new(None);
+
+fn skip_fingerprint(bytes: &[u8]) -> u64 {
+    clipboard_hash(&bytes[..bytes.len().min(SKIP_FINGERPRINT_BYTES)])
+}
+
+/// Whether these leading bytes belong to the offer skipped last. An offer
+/// whose first bytes match is taken to be the same copy; once something else
+/// has been read, a later copy starting the same way is read again.
+fn is_skipped_oversize(prefix: &[u8]) -> bool {
+    *lock_recover(&SKIPPED_OVERSIZE) == Some(skip_fingerprint(prefix))
+}
+
+/// Forget the skipped offer once a read has succeeded, so the fingerprint
+/// can't match a later, different copy
+fn clear_oversize_skip() {
+    *lock_recover(&SKIPPED_OVERSIZE) = None;
+}
+
+/// Remember an oversized offer, logging it the first time it is seen
+fn note_oversize_skip(bytes: &[u8]) {
+    let fingerprint = skip_fingerprint(bytes);
+    let mut skipped = lock_recover(&SKIPPED_OVERSIZE);
+    if *skipped != Some(fingerprint) {
+        eprintln!("Skipping clipboard content over {} bytes", max_read_bytes());
+        *skipped = Some(fingerprint);
+    }
+}
+
+/// MIME type prefixes never read from the clipboard (`clipboard.ignore_mimes`)
+static IGNORE_MIMES: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+pub fn set_ignore_mimes(mimes: Vec<String>) {
+    *lock_recover(&IGNORE_MIMES) = mimes;
+}
+
+fn is_ignored_mime(mime: &str) -> bool {
+    let mime = mime.to_ascii_lowercase();
+    lock_recover(&IGNORE_MIMES).iter().any(|prefix| mime.starts_with(prefix.as_str()))
+}
+
+/// True when the clipboard offers types but every one of them is ignored
+pub fn only_ignored_mimes_offered() -> bool {
+    if lock_recover(&IGNORE_MIMES).is_empty() {
+        return false;
+    }
+    match list_clipboard_mimes() {
+        Ok(mimes) => !mimes.is_empty() && mimes.iter().all(|m| is_ignored_mime(m)),
+        Err(_) => false,
+    }
+}
+
+/// Set once both backends have failed to connect repeatedly on an `Unknown` session.
+/// Reads and writes then go through `PERSISTENT_CLIPBOARD_DATA` only.
+static MEMORY_ONLY: AtomicBool = AtomicBool::new(false);
+static BACKEND_CONNECT_FAILURES: AtomicU32 = AtomicU32::new(0);
+const MEMORY_ONLY_AFTER_FAILURES: u32 = 5;
+
+pub fn is_memory_only() -> bool {
+    MEMORY_ONLY.load(Ordering::Relaxed)
+}
+
+/// Whether either backend can be constructed at all (not whether it holds data)
+fn backends_reachable() -> bool {
+    let wayland_ok = !matches!(
+        get_mime_types(ClipboardType::Regular, Seat::Unspecified),
+        Err(PasteError::WaylandConnection(_))
+    );
+    wayland_ok || X11Clipboard::new().is_ok()
+}
+
+/// Probe the backends on an `Unknown` session, switching to memory-only mode
+/// after several consecutive connection failures
+fn check_unknown_env_backends() -> bool {
+    if is_memory_only() {
+        return false;
+    }
+
+    if backends_reachable() {
+        BACKEND_CONNECT_FAILURES.store(0, Ordering::Relaxed);
+        return true;
+    }
+
+    let failures = BACKEND_CONNECT_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+    if failures >= MEMORY_ONLY_AFTER_FAILURES {
+        MEMORY_ONLY.store(true, Ordering::Relaxed);
+        eprintln!(
+            "No Wayland or X11 clipboard reachable after {} attempts; running in memory-only mode (in-app copies only)",
+            failures
+        );
+    }
+    false
+}
+
+fn persistent_bytes() -> Vec<u8> {
+    lock_recover(&PERSISTENT_CLIPBOARD_DATA).clone().unwrap_or_default()
+}
 
 pub static PERSISTENT_CLIPBOARD_DATA: Lazy<Mutex<Option<Vec<u8>>>> = Lazy::new(|| Mutex::new(None));
 
+/// Original encoding of the last clipboard read, when it had to be transcoded to UTF-8
+static LAST_READ_ENCODING: Lazy<Mutex<Option<&'static str>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn last_read_encoding() -> Option<&'static str> {
+    *lock_recover(&LAST_READ_ENCODING)
+}
+
+/// Transcode non-UTF-8 text to UTF-8, remembering the encoding it came from
+fn transcode_text(bytes: &[u8]) -> Option<Vec<u8>> {
+    let (utf8, encoding) = transcode_to_utf8(bytes)?;
+    *lock_recover(&LAST_READ_ENCODING) = Some(encoding);
+    Some(utf8)
+}
+
+/// Clear poisoning left by a panicked holder of the clipboard caches
+pub fn clear_poisoned_locks() {
+    PERSISTENT_CLIPBOARD_DATA.clear_poison();
+    LAST_WRITTEN_CLIPBOARD.clear_poison();
+}
+
 fn is_text_like_type(t: &str) -> bool {
     t == "text" || t == "text/uri-list" || t == "x-special/gnome-copied-files"
 }
@@ -24,7 +162,7 @@ pub fn set_wayland_clipboard_bytes(data: &[u8]) -> Result<(), String> {
     let content_type = detect_content_type(data);
 
     // Store BEFORE setting to avoid race condition
-    *PERSISTENT_CLIPBOARD_DATA.lock().unwrap() = Some(data.to_vec());
+    *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(data.to_vec());
 
     if content_type.starts_with("image/") {
         // Images
@@ -62,17 +200,22 @@ pub fn set_wayland_clipboard_bytes(data: &[u8]) -> Result<(), String> {
 
 /// Check if bytes should be ignored
 pub fn should_ignore_bytes(bytes: &[u8]) -> bool {
+    ignore_reason(bytes).is_some()
+}
+
+/// Why `should_ignore_bytes` rejects these bytes, or None if it doesn't
+pub fn ignore_reason(bytes: &[u8]) -> Option<&'static str> {
     if bytes.is_empty() {
-        return true;
+        return Some("clipboard is empty");
     }
 
     // Allow shebang scripts
     if bytes.starts_with(b"#!") {
-        return false;
+        return None;
     }
 
     if bytes == b"0,0" {
-        return true;
+        return Some("placeholder \"0,0\" content");
     }
 
     // Allow file:// URIs (file copies), but reject junk
@@ -82,21 +225,21 @@ pub fn should_ignore_bytes(bytes: &[u8]) -> bool {
             .cloned()
             .filter(|&b| b != 0 && !b.is_ascii_whitespace())
             .collect();
-        return clean.is_empty() || clean == b"file://";
+        return (clean.is_empty() || clean == b"file://").then_some("file:// URI without a path");
     }
 
     // GNOME copied-files format: "copy\nfile://...\n..."
     if bytes.starts_with(b"copy\n") || bytes.starts_with(b"cut\n") {
         // If it doesn't contain any file://, it's probably junk
         if !bytes.windows(7).any(|w| w == b"file://") {
-            return true;
+            return Some("copied-files list without any file:// URI");
         }
-        return false;
+        return None;
     }
 
     // Keep old guard for <meta tags (tiny icons)
     if bytes.starts_with(b"<meta") {
-        return true;
+        return Some("bare <meta> tag");
     }
 
     // Remove NULs and whitespace for other checks
@@ -108,15 +251,15 @@ pub fn should_ignore_bytes(bytes: &[u8]) -> bool {
         .collect::<Vec<u8>>();
 
     if clean.is_empty() || clean == b"0,0" {
-        return true;
+        return Some("only whitespace, NUL bytes or \"0,0\"");
     }
 
     // Ignore tiny images
     if detect_content_type(&clean).starts_with("image/") && clean.len() < 100 {
-        return true;
+        return Some("image under 100 bytes");
     }
 
-    false
+    None
 }
 
 /// Get Wayland clipboard - reads from system
@@ -136,19 +279,51 @@ pub fn get_wayland_clipboard_bytes() -> Result<Vec<u8>, String> {
     let mut candidate_image: Option<Vec<u8>> = None;
 
     for mime in &mimes {
-        if let Ok((mut pipe, _)) = get_contents(ClipboardType::Regular, Seat::Unspecified, *mime) {
+        let name = match mime {
+            PasteMimeType::Specific(s) => *s,
+            _ => "text/plain",
+        };
+        if is_ignored_mime(name) {
+            continue;
+        }
+
+        if let Ok((pipe, _)) = get_contents(ClipboardType::Regular, Seat::Unspecified, *mime) {
+            // Read at most one byte past the cap so oversized offers are detected
+            // without buffering the whole payload
+            let cap = max_read_bytes();
             let mut bytes = Vec::with_capacity(1024);
-            if pipe.read_to_end(&mut bytes).is_ok() && !bytes.is_empty() {
+            let mut pipe = pipe.take(cap as u64 + 1);
+
+            // The first bytes are enough to tell an offer already skipped as
+            // oversized, so it isn't read in full again on every poll
+            let prefix_read = pipe
+                .by_ref()
+                .take(SKIP_FINGERPRINT_BYTES as u64)
+                .read_to_end(&mut bytes)
+                .is_ok();
+            if prefix_read && is_skipped_oversize(&bytes) {
+                continue;
+            }
+
+            if prefix_read && pipe.read_to_end(&mut bytes).is_ok() && !bytes.is_empty() {
                 drop(pipe);
 
+                if bytes.len() > cap {
+                    note_oversize_skip(&bytes);
+                    continue;
+                }
+
                 let is_textish = matches!(mime, PasteMimeType::Text)
                     || matches!(mime, PasteMimeType::Specific(s) if *s == "text/uri-list")
                     || matches!(mime, PasteMimeType::Specific(s) if *s == "x-special/gnome-copied-files");
 
                 if is_textish {
+                    // Transcode before NUL filtering, which would mangle UTF-16
+                    let bytes = transcode_text(&bytes).unwrap_or(bytes);
                     let clean = bytes.iter().cloned().filter(|&b| b != 0).collect::<Vec<u8>>();
                     if !should_ignore_bytes(&clean) && String::from_utf8(clean.clone()).is_ok() {
-                        *PERSISTENT_CLIPBOARD_DATA.lock().unwrap() = Some(clean.clone());
+                        clear_oversize_skip();
+                        *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(clean.clone());
                         return Ok(clean);
                     }
                     continue;
@@ -164,11 +339,12 @@ pub fn get_wayland_clipboard_bytes() -> Result<Vec<u8>, String> {
     }
 
     if let Some(img) = candidate_image {
-        *PERSISTENT_CLIPBOARD_DATA.lock().unwrap() = Some(img.clone());
+        clear_oversize_skip();
+        *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(img.clone());
         return Ok(img);
     }
 
-    if let Some(data) = PERSISTENT_CLIPBOARD_DATA.lock().unwrap().as_ref() {
+    if let Some(data) = lock_recover(&PERSISTENT_CLIPBOARD_DATA).as_ref() {
         return Ok(data.clone());
     }
 
@@ -177,7 +353,7 @@ pub fn get_wayland_clipboard_bytes() -> Result<Vec<u8>, String> {
 
 /// Set X11 clipboard
 pub fn set_x11_clipboard(data: &[u8]) -> Result<(), String> {
-    *PERSISTENT_CLIPBOARD_DATA.lock().unwrap() = Some(data.to_vec());
+    *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(data.to_vec());
 
     let clipboard = X11Clipboard::new().map_err(|e| format!("Failed to create X11 clipboard: {}", e))?;
     clipboard
@@ -186,10 +362,116 @@ pub fn set_x11_clipboard(data: &[u8]) -> Result<(), String> {
     Ok(())
 }
 
-/// Get X11 clipboard - reads from system
-pub fn get_x11_clipboard_bytes() -> Result<Vec<u8>, String> {
+/// Last content claw put in the primary selection. Kept apart from
+/// `PERSISTENT_CLIPBOARD_DATA` so the two targets never overwrite each other.
+pub static PERSISTENT_PRIMARY_DATA: Lazy<Mutex<Option<Vec<u8>>>> = Lazy::new(|| Mutex::new(None));
+
+fn set_wayland_primary(data: &[u8]) -> Result<(), String> {
+    let content_type = detect_content_type(data);
+    let mime_type = if content_type.starts_with("image/") {
+        MimeType::Specific(content_type)
+    } else {
+        MimeType::Text
+    };
+
+    let mut options = wl_clipboard_rs::copy::Options::new();
+    options.clipboard(wl_clipboard_rs::copy::ClipboardType::Primary);
+    options
+        .copy(Source::Bytes(data.to_vec().into_boxed_slice()), mime_type)
+        .map_err(|e| match e {
+            wl_clipboard_rs::copy::Error::PrimarySelectionUnsupported => {
+                "The compositor does not support the primary selection".to_string()
+            }
+            e => e.to_string(),
+        })
+}
+
+fn set_x11_primary(data: &[u8]) -> Result<(), String> {
     let clipboard = X11Clipboard::new().map_err(|e| format!("Failed to create X11 clipboard: {}", e))?;
+    clipboard
+        .store(clipboard.setter.atoms.primary, clipboard.setter.atoms.utf8_string, data)
+        .map_err(|e| format!("Failed to set X11 primary selection: {}", e))
+}
+
+/// Put data in the primary selection (middle-click paste) only; the regular
+/// clipboard and its hash tracking are left alone
+pub fn set_primary_selection(data: &[u8]) -> Result<(), String> {
+    let result = match crate::detect::current_desktop_env() {
+        DesktopEnv::Wayland => set_wayland_primary(data),
+        DesktopEnv::X11 => set_x11_primary(data),
+        DesktopEnv::Unknown if is_memory_only() => Err("No primary selection available".to_string()),
+        DesktopEnv::Unknown => set_wayland_primary(data).or_else(|_| set_x11_primary(data)),
+    };
+
+    if result.is_ok() {
+        *lock_recover(&PERSISTENT_PRIMARY_DATA) = Some(data.to_vec());
+    }
+    result
+}
 
+/// Clear the X11 PRIMARY selection
+fn clear_x11_primary() -> Result<(), String> {
+    let clipboard = X11Clipboard::new().map_err(|e| format!("Failed to create X11 clipboard: {}", e))?;
+    clipboard
+        .store(clipboard.setter.atoms.primary, clipboard.setter.atoms.utf8_string, b"".as_slice())
+        .map_err(|e| format!("Failed to clear X11 primary selection: {}", e))
+}
+
+/// Offer an empty string on Wayland. Unlike `set_wayland_clipboard_bytes`
+/// no trailing newline is appended, so pasting really yields nothing
+fn set_wayland_empty_text(target: wl_clipboard_rs::copy::ClipboardType) -> Result<(), String> {
+    let mut options = wl_clipboard_rs::copy::Options::new();
+    options.clipboard(target);
+    options
+        .copy(Source::Bytes(Vec::new().into_boxed_slice()), MimeType::Text)
+        .map_err(|e| e.to_string())
+}
+
+/// Clear the system clipboard, and the primary selection too when asked.
+/// Errors are ignored: an already-empty target is not a failure.
+/// `empty_string` leaves an empty string as the selection rather than none
+/// (`clipboard.clear_sets_empty_string`)
+pub fn clear_system_clipboard(include_primary: bool, empty_string: bool) {
+    let wayland_target = if include_primary {
+        wl_clipboard_rs::copy::ClipboardType::Both
+    } else {
+        wl_clipboard_rs::copy::ClipboardType::Regular
+    };
+
+    match crate::detect::current_desktop_env() {
+        DesktopEnv::Wayland | DesktopEnv::Unknown if empty_string => {
+            *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(Vec::new());
+            // `Both` fails outright when the compositor has no primary selection
+            let offered = set_wayland_empty_text(wayland_target)
+                .or_else(|_| set_wayland_empty_text(wl_clipboard_rs::copy::ClipboardType::Regular));
+            if offered.is_err() {
+                let _ = set_clipboard_no_hash(b"");
+            }
+        }
+        DesktopEnv::Wayland | DesktopEnv::Unknown => {
+            let _ = wl_clipboard_rs::copy::clear(wayland_target, wl_clipboard_rs::copy::Seat::All);
+        }
+        DesktopEnv::X11 => {
+            let _ = set_clipboard_no_hash(b"");
+            if include_primary {
+                let _ = clear_x11_primary();
+            }
+        }
+    }
+}
+
+/// `clipboard.x11_fallback_to_primary`: read PRIMARY when CLIPBOARD is empty
+static X11_FALLBACK_TO_PRIMARY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_x11_fallback_to_primary(enabled: bool) {
+    X11_FALLBACK_TO_PRIMARY.store(enabled, Ordering::Relaxed);
+}
+
+/// Text held by an X11 selection, or None if it is empty, unowned or over the read cap
+fn load_x11_selection(
+    clipboard: &X11Clipboard,
+    selection: x11rb::protocol::xproto::Atom,
+) -> Option<Vec<u8>> {
     // BUGFIX: requesting `incr` as the *target* is wrong. Request UTF8 text.
     // This dramatically improves reads and is required for many providers.
     let try_targets = [
@@ -198,24 +480,353 @@ pub fn get_x11_clipboard_bytes() -> Result<Vec<u8>, String> {
     ];
 
     for target in try_targets {
-        match clipboard.load(
+        match load_x11_capped(clipboard, selection, target, max_read_bytes()) {
+            Ok(CappedRead::Oversize(prefix)) => {
+                note_oversize_skip(&prefix);
+                return None;
+            }
+            Ok(CappedRead::Data(contents)) if !contents.is_empty() => {
+                clear_oversize_skip();
+                return Some(contents);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Outcome of `load_x11_capped`
+enum CappedRead {
+    Data(Vec<u8>),
+    /// Over the cap; holds the first bytes read, enough to recognise the offer
+    Oversize(Vec<u8>),
+}
+
+// How long an X11 selection owner gets to hand over its data
+const X11_READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+fn x11_read_error(e: impl std::fmt::Display) -> String {
+    format!("Failed to read X11 selection: {}", e)
+}
+
+/// Read a selection like `X11Clipboard::load`, but stop as soon as it is
+/// known to exceed `cap` bytes: a plain transfer is sized before it is
+/// fetched, and an INCR transfer is abandoned once its size hint or the
+/// chunks received so far pass the cap
+fn load_x11_capped(
+    clipboard: &X11Clipboard,
+    selection: x11rb::protocol::xproto::Atom,
+    target: x11rb::protocol::xproto::Atom,
+    cap: usize,
+) -> Result<CappedRead, String> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, Property};
+    use x11rb::protocol::Event;
+
+    let conn = &clipboard.getter.connection;
+    let window = clipboard.getter.window;
+    let property = clipboard.getter.atoms.property;
+    // get_property counts in 32-bit units
+    let prefix_words = (SKIP_FINGERPRINT_BYTES / 4) as u32;
+
+    conn.convert_selection(window, selection, target, property, x11rb::CURRENT_TIME)
+        .map_err(x11_read_error)?
+        .check()
+        .map_err(x11_read_error)?;
+
+    let deadline = std::time::Instant::now() + X11_READ_TIMEOUT;
+    // Size announced by an INCR transfer, once one has started
+    let mut incr_size: Option<usize> = None;
+    let mut buff = Vec::new();
+
+    loop {
+        if std::time::Instant::now() >= deadline {
+            return Err("Timed out reading X11 selection".to_string());
+        }
+        let Some(event) = conn.poll_for_event().map_err(x11_read_error)? else {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            continue;
+        };
+
+        match event {
+            Event::SelectionNotify(event) if event.selection == selection && incr_size.is_none() => {
+                if event.property == u32::from(AtomEnum::NONE) {
+                    return Ok(CappedRead::Data(Vec::new()));
+                }
+
+                // Fetch only the first bytes; bytes_after gives the rest of the size
+                let reply = conn
+                    .get_property(false, window, property, AtomEnum::ANY, 0, prefix_words)
+                    .map_err(x11_read_error)?
+                    .reply()
+                    .map_err(x11_read_error)?;
+
+                if reply.type_ == clipboard.getter.atoms.incr {
+                    let size = reply.value32().and_then(|mut v| v.next()).unwrap_or(0);
+                    incr_size = Some(size as usize);
+                    // Deleting the property asks the owner for the first chunk
+                    conn.delete_property(window, property)
+                        .map_err(x11_read_error)?
+                        .check()
+                        .map_err(x11_read_error)?;
+                    continue;
+                } else if reply.type_ != target {
+                    return Err(format!("Unexpected X11 selection type {}", reply.type_));
+                }
+
+                if reply.value.len() + reply.bytes_after as usize > cap {
+                    return Ok(CappedRead::Oversize(reply.value));
+                }
+
+                let mut contents = reply.value;
+                if reply.bytes_after > 0 {
+                    let rest = conn
+                        .get_property(false, window, property, AtomEnum::ANY, prefix_words, u32::MAX)
+                        .map_err(x11_read_error)?
+                        .reply()
+                        .map_err(x11_read_error)?;
+                    contents.extend_from_slice(&rest.value);
+                }
+                return Ok(CappedRead::Data(contents));
+            }
+            Event::PropertyNotify(event)
+                if event.atom == property && event.state == Property::NEW_VALUE =>
+            {
+                let Some(size) = incr_size else {
+                    continue;
+                };
+
+                let reply = conn
+                    .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX)
+                    .map_err(x11_read_error)?
+                    .reply()
+                    .map_err(x11_read_error)?;
+                if reply.type_ != target {
+                    continue;
+                }
+                // A zero-length chunk ends the transfer
+                if reply.value.is_empty() {
+                    return Ok(CappedRead::Data(buff));
+                }
+
+                buff.extend_from_slice(&reply.value);
+                if size > cap || buff.len() > cap {
+                    buff.truncate(SKIP_FINGERPRINT_BYTES);
+                    return Ok(CappedRead::Oversize(buff));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Selection a clipboard read came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    Clipboard,
+    /// X11 PRIMARY, read under `x11_fallback_to_primary`
+    Primary,
+}
+
+impl Selection {
+    /// Only CLIPBOARD content is cached and kept alive; doing either for
+    /// PRIMARY would copy a text selection into CLIPBOARD and record it twice
+    pub fn keeps_alive(self) -> bool {
+        self == Selection::Clipboard
+    }
+}
+
+/// CLIPBOARD content if there is any, else PRIMARY from `primary`
+fn pick_x11_selection(
+    clipboard: Option<Vec<u8>>,
+    primary: impl FnOnce() -> Option<Vec<u8>>,
+) -> Option<(Vec<u8>, Selection)> {
+    match clipboard {
+        Some(contents) => Some((contents, Selection::Clipboard)),
+        None => primary().map(|contents| (contents, Selection::Primary)),
+    }
+}
+
+/// Get X11 clipboard - reads from system
+pub fn get_x11_clipboard_bytes() -> Result<(Vec<u8>, Selection), String> {
+    let clipboard = X11Clipboard::new().map_err(|e| format!("Failed to create X11 clipboard: {}", e))?;
+
+    let read = pick_x11_selection(load_x11_selection(&clipboard, clipboard.getter.atoms.clipboard), || {
+        X11_FALLBACK_TO_PRIMARY
+            .load(Ordering::Relaxed)
+            .then(|| load_x11_selection(&clipboard, clipboard.getter.atoms.primary))
+            .flatten()
+    });
+    match read {
+        Some((contents, selection)) => {
+            if selection.keeps_alive() {
+                *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(contents.clone());
+            }
+            Ok((contents, selection))
+        }
+        None => Ok((persistent_bytes(), Selection::Clipboard)),
+    }
+}
+
+/// Read `pipe` up to the read cap, failing if the offer is larger
+fn read_capped(pipe: impl Read) -> Result<Vec<u8>, String> {
+    let cap = max_read_bytes();
+    let mut bytes = Vec::new();
+    pipe.take(cap as u64 + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read clipboard: {}", e))?;
+    if bytes.len() > cap {
+        return Err(format!("Clipboard content is over {} bytes", cap));
+    }
+    Ok(bytes)
+}
+
+fn list_wayland_mimes() -> Result<Vec<String>, String> {
+    match get_mime_types(ClipboardType::Regular, Seat::Unspecified) {
+        Ok(mimes) => Ok(mimes.into_iter().collect()),
+        Err(PasteError::ClipboardEmpty) | Err(PasteError::NoSeats) => Ok(vec![]),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn get_wayland_mime(mime: &str) -> Result<Vec<u8>, String> {
+    match get_contents(ClipboardType::Regular, Seat::Unspecified, PasteMimeType::Specific(mime)) {
+        Ok((pipe, _)) => read_capped(pipe),
+        Err(PasteError::ClipboardEmpty) | Err(PasteError::NoSeats) | Err(PasteError::NoMimeType) => Ok(vec![]),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn list_x11_targets() -> Result<Vec<String>, String> {
+    use x11rb::protocol::xproto::ConnectionExt;
+
+    let clipboard = X11Clipboard::new().map_err(|e| format!("Failed to create X11 clipboard: {}", e))?;
+    let raw = match clipboard.load(
+        clipboard.getter.atoms.clipboard,
+        clipboard.getter.atoms.targets,
+        clipboard.getter.atoms.property,
+        std::time::Duration::from_secs(3),
+    ) {
+        Ok(raw) => raw,
+        // No owner: nothing offered
+        Err(_) => return Ok(vec![]),
+    };
+
+    // TARGETS is a list of 32-bit atoms
+    let names = raw
+        .chunks_exact(4)
+        .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+        .filter_map(|atom| clipboard.getter.connection.get_atom_name(atom).ok()?.reply().ok())
+        .map(|reply| String::from_utf8_lossy(&reply.name).into_owned())
+        .collect();
+    Ok(names)
+}
+
+fn get_x11_mime(mime: &str) -> Result<Vec<u8>, String> {
+    if !list_x11_targets()?.iter().any(|t| t == mime) {
+        return Ok(vec![]);
+    }
+
+    let clipboard = X11Clipboard::new().map_err(|e| format!("Failed to create X11 clipboard: {}", e))?;
+    let target = clipboard
+        .getter
+        .get_atom(mime)
+        .map_err(|e| format!("Failed to intern {}: {}", mime, e))?;
+    let contents = clipboard
+        .load(
             clipboard.getter.atoms.clipboard,
             target,
             clipboard.getter.atoms.property,
             std::time::Duration::from_secs(3),
-        ) {
-            Ok(contents) if !contents.is_empty() => {
-                *PERSISTENT_CLIPBOARD_DATA.lock().unwrap() = Some(contents.clone());
-                return Ok(contents);
-            }
-            _ => {}
-        }
+        )
+        .map_err(|e| format!("Failed to read {}: {}", mime, e))?;
+    if contents.len() > max_read_bytes() {
+        return Err(format!("Clipboard content is over {} bytes", max_read_bytes()));
     }
+    Ok(contents)
+}
 
-    if let Some(data) = PERSISTENT_CLIPBOARD_DATA.lock().unwrap().as_ref() {
-        Ok(data.clone())
-    } else {
-        Ok(vec![])
+/// MIME types (X11 targets) the current clipboard owner offers, sorted
+pub fn list_clipboard_mimes() -> Result<Vec<String>, String> {
+    let mut mimes = match crate::detect::current_desktop_env() {
+        DesktopEnv::Wayland => list_wayland_mimes(),
+        DesktopEnv::X11 => list_x11_targets(),
+        DesktopEnv::Unknown if is_memory_only() => Ok(vec![]),
+        DesktopEnv::Unknown => list_wayland_mimes().or_else(|_| list_x11_targets()),
+    }?;
+    mimes.sort();
+    Ok(mimes)
+}
+
+/// MIME types offered for the content just read, kept with its history entry.
+/// Only Wayland lists offers reliably; elsewhere this is empty and entries
+/// are copied back as a single type.
+pub fn offered_mimes() -> Vec<String> {
+    match crate::detect::current_desktop_env() {
+        DesktopEnv::Wayland => list_wayland_mimes()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|m| !is_ignored_mime(m))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Of the MIME types an entry was offered under, those its stored bytes can
+/// serve: plain-text flavours for text, the image's own type for images.
+/// Other formats (e.g. HTML next to text) weren't stored and can't be offered.
+fn servable_mimes(content_type: &str, mimes: &[String]) -> Vec<String> {
+    const TEXT_TARGETS: [&str; 3] = ["UTF8_STRING", "STRING", "TEXT"];
+
+    mimes
+        .iter()
+        .filter(|m| match content_type {
+            "text" => m.starts_with("text/plain") || TEXT_TARGETS.contains(&m.as_str()),
+            other => m.as_str() == other,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Set the clipboard like `set_clipboard`, offering the content under every
+/// recorded MIME type it can serve. On Wayland only; anywhere else, or with
+/// fewer than two usable types, it is a plain `set_clipboard`.
+pub fn set_clipboard_with_mimes(data: &[u8], mimes: &[String]) -> Result<(), String> {
+    let content_type = detect_content_type(data);
+    let servable = servable_mimes(&content_type, mimes);
+    if servable.len() < 2 || !matches!(crate::detect::current_desktop_env(), DesktopEnv::Wayland) {
+        return set_clipboard(data);
+    }
+
+    if is_text_like_type(&content_type) {
+        *lock_recover(&LAST_WRITTEN_CLIPBOARD) = Some(clipboard_hash(data));
+    }
+    *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(data.to_vec());
+
+    let mut bytes = data.to_vec();
+    if content_type == "text" && !bytes.ends_with(b"\n") {
+        bytes.push(b'\n'); // trailing newline helps GTK/Qt apps
+    }
+    let sources = servable
+        .into_iter()
+        .map(|mime| MimeSource {
+            source: Source::Bytes(bytes.clone().into_boxed_slice()),
+            mime_type: MimeType::Specific(mime),
+        })
+        .collect();
+    wl_clipboard_rs::copy::Options::new()
+        .copy_multi(sources)
+        .map_err(|e| e.to_string())
+}
+
+/// Raw bytes for one specific MIME type, bypassing claw's text/image list.
+/// Empty when the type isn't offered.
+pub fn get_clipboard_mime(mime: &str) -> Result<Vec<u8>, String> {
+    match crate::detect::current_desktop_env() {
+        DesktopEnv::Wayland => get_wayland_mime(mime),
+        DesktopEnv::X11 => get_x11_mime(mime),
+        DesktopEnv::Unknown if is_memory_only() => Ok(vec![]),
+        DesktopEnv::Unknown => get_wayland_mime(mime).or_else(|_| get_x11_mime(mime)),
     }
 }
 
@@ -225,15 +836,17 @@ fn set_clipboard_inner(data: &[u8], update_last_written: bool) -> Result<(), Str
 
     // Hash-update should include uri-lists too (prevents loops when reinjecting file copies)
     if update_last_written && is_text_like_type(&content_type) {
-        let normalized = normalize_clipboard_bytes(data);
-        let mut hasher = DefaultHasher::new();
-        normalized.hash(&mut hasher);
-        *LAST_WRITTEN_CLIPBOARD.lock().unwrap() = Some(hasher.finish());
+        *lock_recover(&LAST_WRITTEN_CLIPBOARD) = Some(clipboard_hash(data));
     }
 
     match crate::detect::current_desktop_env() {
         DesktopEnv::Wayland => set_wayland_clipboard_bytes(data),
         DesktopEnv::X11 => set_x11_clipboard(data),
+        DesktopEnv::Unknown if !check_unknown_env_backends() => {
+            // Memory-only: keep the data so in-app paste and history still work
+            *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(data.to_vec());
+            Ok(())
+        }
         DesktopEnv::Unknown => set_wayland_clipboard_bytes(data).or_else(|_| set_x11_clipboard(data)),
     }
 }
@@ -248,50 +861,65 @@ pub fn set_clipboard_no_hash(data: &[u8]) -> Result<(), String> {
     set_clipboard_inner(data, false)
 }
 
-/// Get clipboard based on current environment
-pub fn get_clipboard() -> Result<Vec<u8>, String> {
-    let bytes = match crate::detect::current_desktop_env() {
-        DesktopEnv::Wayland => get_wayland_clipboard_bytes(),
+/// Get clipboard based on current environment, with the selection it came from
+pub fn get_clipboard() -> Result<(Vec<u8>, Selection), String> {
+    *lock_recover(&LAST_READ_ENCODING) = None;
+
+    let wayland = || get_wayland_clipboard_bytes().map(|bytes| (bytes, Selection::Clipboard));
+    let (bytes, selection) = match crate::detect::current_desktop_env() {
+        DesktopEnv::Wayland => wayland(),
         DesktopEnv::X11 => get_x11_clipboard_bytes(),
-        DesktopEnv::Unknown => get_wayland_clipboard_bytes().or_else(|_| get_x11_clipboard_bytes()),
+        DesktopEnv::Unknown if !check_unknown_env_backends() => {
+            return Ok((persistent_bytes(), Selection::Clipboard));
+        }
+        DesktopEnv::Unknown => wayland().or_else(|_| get_x11_clipboard_bytes()),
     }?;
 
     if bytes.is_empty() {
-        if let Some(data) = PERSISTENT_CLIPBOARD_DATA.lock().unwrap().as_ref() {
-            return Ok(data.clone());
+        if let Some(data) = lock_recover(&PERSISTENT_CLIPBOARD_DATA).as_ref() {
+            return Ok((data.clone(), Selection::Clipboard));
         }
     }
 
     if should_ignore_bytes(&bytes) {
-        if let Some(data) = PERSISTENT_CLIPBOARD_DATA.lock().unwrap().as_ref() {
+        if let Some(data) = lock_recover(&PERSISTENT_CLIPBOARD_DATA).as_ref() {
             if !should_ignore_bytes(data) {
-                return Ok(data.clone());
+                return Ok((data.clone(), Selection::Clipboard));
             }
         }
-        return Ok(vec![]);
+        return Ok((vec![], selection));
     }
 
     let content_type = detect_content_type(&bytes);
 
     if content_type.starts_with("image/") {
-        return Ok(bytes);
+        return Ok((bytes, selection));
     }
 
     // For any UTF-8-ish text formats (including uri-lists), normalize safely
     if is_text_like_type(&content_type) {
         if String::from_utf8(bytes.clone()).is_ok() {
-            return Ok(normalize_clipboard_bytes(&bytes));
+            return Ok((normalize_clipboard_bytes(&bytes), selection));
         } else {
-            return Ok(vec![]);
+            return Ok((vec![], selection));
         }
     }
 
-    Ok(bytes)
+    // Latin-1 / Windows-1252 / UTF-16 text from other apps fails the UTF-8 check above
+    if let Some(utf8) = transcode_text(&bytes) {
+        let utf8 = normalize_clipboard_bytes(&utf8);
+        if selection.keeps_alive() {
+            *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(utf8.clone());
+        }
+        return Ok((utf8, selection));
+    }
+
+    Ok((bytes, selection))
 }
 
 /// Get clipboard for frontend - ALWAYS returns from persistent memory
 pub fn get_clipboard_for_paste() -> Result<Vec<u8>, String> {
-    if let Some(data) = PERSISTENT_CLIPBOARD_DATA.lock().unwrap().as_ref() {
+    if let Some(data) = lock_recover(&PERSISTENT_CLIPBOARD_DATA).as_ref() {
         if should_ignore_bytes(data) {
             return Ok(vec![]);
         }
@@ -318,12 +946,79 @@ pub fn get_clipboard_for_paste() -> Result<Vec<u8>, String> {
 
 /// Get the most recent clipboard item from persistent memory
 pub fn get_persistent_clipboard() -> Option<Vec<u8>> {
-    PERSISTENT_CLIPBOARD_DATA.lock().unwrap().clone()
+    lock_recover(&PERSISTENT_CLIPBOARD_DATA).clone()
+}
+
+/// Put the cached content back on the system clipboard right away, as the
+/// watcher's keep-alive does. Returns false when nothing is cached.
+pub fn reinject_clipboard() -> Result<bool, String> {
+    match get_persistent_clipboard() {
+        Some(data) if !should_ignore_bytes(&data) => {
+            set_clipboard_no_hash(&data)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
 }
 
 /// Store clipboard data in persistent memory without setting system clipboard
 pub fn cache_clipboard_data(data: &[u8]) {
     if !data.is_empty() && !should_ignore_bytes(data) {
-        *PERSISTENT_CLIPBOARD_DATA.lock().unwrap() = Some(data.to_vec());
+        *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(data.to_vec());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Panic while holding `mutex`, leaving it poisoned
+    fn poison<T: Send>(mutex: &'static Mutex<T>) {
+        let result = std::thread::spawn(move || {
+            let _held = mutex.lock().unwrap();
+            panic!("poisoning the lock on purpose");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(mutex.is_poisoned());
+    }
+
+    #[test]
+    fn clear_poisoned_locks_restores_clipboard_caches() {
+        *lock_recover(&PERSISTENT_CLIPBOARD_DATA) = Some(b"cached".to_vec());
+        poison(&PERSISTENT_CLIPBOARD_DATA);
+        poison(&LAST_WRITTEN_CLIPBOARD);
+
+        assert_eq!(persistent_bytes(), b"cached".to_vec());
+
+        clear_poisoned_locks();
+        assert!(!PERSISTENT_CLIPBOARD_DATA.is_poisoned());
+        assert!(!LAST_WRITTEN_CLIPBOARD.is_poisoned());
+        assert_eq!(PERSISTENT_CLIPBOARD_DATA.lock().unwrap().as_deref(), Some(b"cached".as_slice()));
+    }
+
+    #[test]
+    fn primary_fallback_is_neither_cached_nor_kept_alive() {
+        let (_, selection) = pick_x11_selection(Some(b"copied".to_vec()), || Some(b"selected".to_vec())).unwrap();
+        assert_eq!(selection, Selection::Clipboard);
+        assert!(selection.keeps_alive());
+
+        let (contents, selection) = pick_x11_selection(None, || Some(b"selected".to_vec())).unwrap();
+        assert_eq!(contents, b"selected".to_vec());
+        assert_eq!(selection, Selection::Primary);
+        assert!(!selection.keeps_alive());
+
+        assert!(pick_x11_selection(None, || None).is_none());
+    }
+
+    #[test]
+    fn oversize_skip_is_forgotten_after_a_successful_read() {
+        let mut offer = vec![b'x'; SKIP_FINGERPRINT_BYTES];
+        offer.extend_from_slice(b"tail of a huge copy");
+        note_oversize_skip(&offer);
+        assert!(is_skipped_oversize(&offer[..SKIP_FINGERPRINT_BYTES]));
+
+        clear_oversize_skip();
+        assert!(!is_skipped_oversize(&offer[..SKIP_FINGERPRINT_BYTES]));
     }
 }
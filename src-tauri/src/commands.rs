@@ -2,10 +2,31 @@ use std::sync::Arc;
 use tauri::{command, AppHandle, Emitter, State};
 use tokio::sync::RwLock;
 use crate::clipboard::{get_clipboard_for_paste, set_clipboard, cache_clipboard_data};
-use crate::config::ClipboardConfig;
-use crate::history::{load_history, save_history, ClipboardEntry};
+use crate::config::{
+    config_file_for, find_config, gather_results, raw_config_value, write_config_value, ClipboardConfig, GatherResult,
+};
+use crate::history::{
+    load_history, save_history, AddOutcome, ClipboardEntry, ClipboardHistory, EntrySource, GrowthEstimate,
+    HistoryHealth,
+};
+use crate::preview::{recent_previews, EntryPreview};
 use crate::theme::Theme;
-use crate::utils::detect_content_type;
+use crate::utils::{
+    apply_text_transform, detect_content_type, extract_first_url, file_path_from_source, is_file_copy,
+    open_with_default, record_error, reveal_path, TEXT_TRANSFORMS,
+};
+
+// Source app recorded for copies made from inside claw
+const CLAW_APP_NAME: &str = "claw";
+
+/// Error for commands that would change history while `lock_history` is on
+fn ensure_history_unlocked() -> Result<(), String> {
+    if crate::history::history_locked() {
+        Err("History is locked (clipboard.lock_history)".to_string())
+    } else {
+        Ok(())
+    }
+}
 
 #[command]
 pub async fn set_system_clipboard(
@@ -19,7 +40,16 @@ pub async fn set_system_clipboard(
     cache_clipboard_data(&content);
     set_clipboard(&content)?;
 
-    let max_entries = config.read().await.0.history_limit as usize;
+    let settings = config.read().await.0.clone();
+    let max_entries = settings.max_entries();
+
+    if !settings.record_files && is_file_copy(&content) {
+        return Ok(());
+    }
+    // Locked history: the clipboard is set, just not recorded
+    if crate::history::history_locked() {
+        return Ok(());
+    }
 
     let source_path = if content.starts_with(b"file://") {
         Some(String::from_utf8_lossy(&content[7..]).to_string())
@@ -32,13 +62,128 @@ pub async fn set_system_clipboard(
         &content,
         content_type,
         max_entries,
-        source_path,
+        EntrySource {
+            path: source_path,
+            app: Some(CLAW_APP_NAME.to_string()),
+            ..Default::default()
+        },
+        settings.dedup_ignore_whitespace,
+        settings.dedup_images(),
+    )?;
+
+    let _ = app_handle.emit("history-updated", "");
+    Ok(())
+}
+
+/// Record content in history without putting it on the clipboard, e.g. for
+/// importing entries from elsewhere. The content type is detected when not given.
+#[command]
+pub async fn add_history_entry(
+    app_handle: AppHandle,
+    content: Vec<u8>,
+    content_type: Option<String>,
+    source_path: Option<String>,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    ensure_history_unlocked()?;
+    if let Some(reason) = crate::clipboard::ignore_reason(&content) {
+        return Err(format!("Content is ignored: {}", reason));
+    }
+
+    let content_type = content_type.unwrap_or_else(|| detect_content_type(&content));
+    let settings = config.read().await.0.clone();
+    crate::history::add_to_history(
+        &app_handle,
+        &content,
+        content_type,
+        settings.max_entries(),
+        EntrySource {
+            path: source_path,
+            ..Default::default()
+        },
+        settings.dedup_ignore_whitespace,
+        settings.dedup_images(),
+    )?;
+
+    let _ = app_handle.emit("history-updated", "");
+    Ok(())
+}
+
+/// Put an image file on the clipboard and record it in history with the file
+/// as its source, as if it had been copied from an image viewer
+#[command]
+pub async fn import_image_file(
+    app_handle: AppHandle,
+    path: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let path = std::path::PathBuf::from(&path);
+    let size = std::fs::metadata(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if size > crate::history::MAX_ENTRY_SIZE as u64 {
+        return Err(format!(
+            "{} is larger than the {} byte entry limit",
+            path.display(),
+            crate::history::MAX_ENTRY_SIZE
+        ));
+    }
+
+    let content = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let content_type = detect_content_type(&content);
+    if !content_type.starts_with("image/") {
+        return Err(format!("{} is not an image (detected {})", path.display(), content_type));
+    }
+
+    cache_clipboard_data(&content);
+    set_clipboard(&content)?;
+
+    // Locked history: the clipboard is set, just not recorded
+    if crate::history::history_locked() {
+        return Ok(());
+    }
+
+    let source_path = url::Url::from_file_path(std::fs::canonicalize(&path).unwrap_or(path))
+        .map(|url| url.to_string())
+        .ok();
+    let settings = config.read().await.0.clone();
+    crate::history::add_to_history(
+        &app_handle,
+        &content,
+        content_type,
+        settings.max_entries(),
+        EntrySource {
+            path: source_path,
+            app: Some(CLAW_APP_NAME.to_string()),
+            ..Default::default()
+        },
+        settings.dedup_ignore_whitespace,
+        settings.dedup_images(),
     )?;
 
     let _ = app_handle.emit("history-updated", "");
     Ok(())
 }
 
+#[command]
+pub async fn capture_now(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let settings = config.read().await.0.clone();
+    crate::watchers::capture_now(&app_handle, &settings)
+}
+
+#[command]
+pub fn list_clipboard_mimes() -> Result<Vec<String>, String> {
+    crate::clipboard::list_clipboard_mimes()
+}
+
+#[command]
+pub fn get_clipboard_mime(mime: String) -> Result<Vec<u8>, String> {
+    crate::clipboard::get_clipboard_mime(&mime)
+}
+
 #[command]
 pub async fn get_system_clipboard(
     _app_handle: AppHandle,
@@ -67,57 +212,163 @@ pub async fn get_clipboard_history(
     limit: Option<usize>,
     config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
 ) -> Result<Vec<ClipboardEntry>, String> {
-    let max_entries = config.read().await.0.history_limit as usize;
+    // Low-memory mode reads just the requested page from the index
+    if let Some(limit) = limit {
+        if let Some(entries) = crate::history::load_history_window(&app_handle, 0, limit)? {
+            return Ok(entries);
+        }
+    }
+
+    let max_entries = config.read().await.0.max_entries();
     let history = load_history(&app_handle, max_entries)?;
     
     Ok(history.get_entries(limit))
 }
 
+/// Filtered, sorted and paged history in one call
+#[command]
+pub async fn query_history(
+    app_handle: AppHandle,
+    params: crate::history::QueryParams,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<crate::history::QueryResult, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+    history.query(&params)
+}
+
+/// Entry above or below `entry_id` in the list as `get_clipboard_history` orders it
+#[command]
+pub async fn get_adjacent_entry(
+    app_handle: AppHandle,
+    entry_id: String,
+    direction: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Option<ClipboardEntry>, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+    history.adjacent_entry(&entry_id, &direction)
+}
+
+#[command]
+pub async fn get_entries_in_range(
+    app_handle: AppHandle,
+    from: chrono::DateTime<chrono::Utc>,
+    to: chrono::DateTime<chrono::Utc>,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Vec<ClipboardEntry>, String> {
+    if from > to {
+        return Err("Range start must not be after its end".to_string());
+    }
+
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    // Entries are stored newest first, so filtering keeps that order
+    Ok(history
+        .entries
+        .iter()
+        .filter(|e| e.timestamp >= from && e.timestamp <= to)
+        .cloned()
+        .collect())
+}
+
+/// Entries whose stored size is within the bounds (either may be left open),
+/// biggest first, for finding what to clean up with `remove_clipboard_entries`
+#[command]
+pub async fn get_entries_by_size(
+    app_handle: AppHandle,
+    min_bytes: Option<usize>,
+    max_bytes: Option<usize>,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Vec<ClipboardEntry>, String> {
+    if let (Some(min), Some(max)) = (min_bytes, max_bytes) {
+        if min > max {
+            return Err("Minimum size must not be above the maximum".to_string());
+        }
+    }
+
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    let min = min_bytes.unwrap_or(0);
+    let max = max_bytes.unwrap_or(usize::MAX);
+    let mut entries: Vec<ClipboardEntry> = history
+        .entries
+        .iter()
+        .filter(|e| e.content_size >= min && e.content_size <= max)
+        .cloned()
+        .collect();
+    entries.sort_by(|a, b| b.content_size.cmp(&a.content_size));
+    Ok(entries)
+}
+
+/// What was on the clipboard at `ts`: the latest entry captured at or before it.
+/// Fetch its content separately with `get_clipboard_entry_content`.
+#[command]
+pub async fn get_entry_at_time(
+    app_handle: AppHandle,
+    ts: chrono::DateTime<chrono::Utc>,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Option<ClipboardEntry>, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    Ok(history
+        .entries
+        .iter()
+        .filter(|e| e.timestamp <= ts)
+        .max_by_key(|e| e.timestamp)
+        .cloned())
+}
+
 #[command]
 pub async fn get_clipboard_entry_content(
     app_handle: AppHandle,
     entry_id: String,
     config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
 ) -> Result<Vec<u8>, String> {
-    let max_entries = config.read().await.0.history_limit as usize;
+    let max_entries = config.read().await.0.max_entries();
     let history = load_history(&app_handle, max_entries)?;
     
     history.get_entry_content(&entry_id)
         .ok_or_else(|| "Entry not found".to_string())
 }
 
+#[command]
+pub async fn get_recent_previews(
+    app_handle: AppHandle,
+    n: usize,
+    preview_bytes: usize,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Vec<EntryPreview>, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    Ok(recent_previews(&history, n, preview_bytes))
+}
+
 #[command]
 pub async fn clear_clipboard_history(
     app_handle: AppHandle,
     config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
 ) -> Result<(), String> {
-    let max_entries = config.read().await.0.history_limit as usize;
+    // Locked history: refuse before touching even the system clipboard
+    ensure_history_unlocked()?;
+    let (max_entries, primary_selection, clear_sets_empty_string) = {
+        let cfg = config.read().await;
+        (cfg.0.max_entries(), cfg.0.primary_selection, cfg.0.clear_sets_empty_string)
+    };
 
     {
-        let mut last = crate::LAST_WRITTEN_CLIPBOARD.lock().unwrap();
+        let mut last = crate::utils::lock_recover(&crate::LAST_WRITTEN_CLIPBOARD);
         *last = None;
     }
     
-    *crate::clipboard::PERSISTENT_CLIPBOARD_DATA.lock().unwrap() = None;
+    *crate::utils::lock_recover(&crate::clipboard::PERSISTENT_CLIPBOARD_DATA) = None;
     
-    // Clear the system clipboard
-    match crate::detect::current_desktop_env() {
-        crate::detect::DesktopEnv::Wayland => {
-            let _ = wl_clipboard_rs::copy::clear(
-                wl_clipboard_rs::copy::ClipboardType::Regular,
-                wl_clipboard_rs::copy::Seat::All
-            );
-        }
-        crate::detect::DesktopEnv::X11 => {
-            let _ = crate::clipboard::set_clipboard_no_hash(b"");
-        }
-        crate::detect::DesktopEnv::Unknown => {
-            let _ = wl_clipboard_rs::copy::clear(
-                wl_clipboard_rs::copy::ClipboardType::Regular,
-                wl_clipboard_rs::copy::Seat::All
-            );
-        }
-    }
+    // Clear the system clipboard (and primary selection if enabled)
+    crate::clipboard::clear_system_clipboard(primary_selection, clear_sets_empty_string);
 
     let mut history = load_history(&app_handle, max_entries)?;
     history.clear();
@@ -132,82 +383,1459 @@ pub async fn clear_clipboard_history(
 }
 
 #[command]
-pub async fn remove_clipboard_entry(
+pub async fn update_entry_content(
     app_handle: AppHandle,
     entry_id: String,
+    content: Vec<u8>,
     config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
-) -> Result<bool, String> {
-    let max_entries = config.read().await.0.history_limit as usize;
+) -> Result<(), String> {
+    ensure_history_unlocked()?;
+    let max_entries = config.read().await.0.max_entries();
+    let mut history = load_history(&app_handle, max_entries)?;
+    history.replace_content(&entry_id, content)?;
+    save_history(&app_handle, &history)?;
+
+    let _ = app_handle.emit("history-updated", "");
+    Ok(())
+}
+
+#[command]
+pub async fn clear_unpinned_history(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<usize, String> {
+    ensure_history_unlocked()?;
+    let max_entries = config.read().await.0.max_entries();
     let mut history = load_history(&app_handle, max_entries)?;
-    let removed = history.remove_entry(&entry_id);
+    let removed = history.clear_unpinned();
     save_history(&app_handle, &history)?;
 
     let _ = app_handle.emit("history-updated", "");
     Ok(removed)
 }
 
+// Upper bound for pin_matching so a broad query can't pin everything
+const MAX_BULK_PIN: usize = 50;
+
 #[command]
-pub async fn set_clipboard_from_history(
+pub async fn pin_matching(
+    app_handle: AppHandle,
+    query: String,
+    fuzzy: bool,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<usize, String> {
+    ensure_history_unlocked()?;
+    let max_entries = config.read().await.0.max_entries();
+    let mut history = load_history(&app_handle, max_entries)?;
+
+    let mut pinned = 0;
+    for id in history.search(&query, fuzzy).into_iter().take(MAX_BULK_PIN) {
+        let already = history.entries.iter().any(|e| e.id == id && e.pinned);
+        if !already && history.set_pinned(&id, true) {
+            pinned += 1;
+        }
+    }
+
+    if pinned > 0 {
+        save_history(&app_handle, &history)?;
+        let _ = app_handle.emit("history-updated", "");
+    }
+    Ok(pinned)
+}
+
+#[command]
+pub async fn set_entry_expiry(
     app_handle: AppHandle,
     entry_id: String,
+    ttl_secs: Option<u64>,
     config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
-) -> Result<(), String> {
-    let max_entries = config.read().await.0.history_limit as usize;
-    let history = load_history(&app_handle, max_entries)?;
+) -> Result<bool, String> {
+    ensure_history_unlocked()?;
+    let max_entries = config.read().await.0.max_entries();
+    let mut history = load_history(&app_handle, max_entries)?;
+
+    let expires_at = ttl_secs.map(|secs| chrono::Utc::now() + chrono::Duration::seconds(secs as i64));
+    let updated = history.set_expiry(&entry_id, expires_at);
+    save_history(&app_handle, &history)?;
+
+    let _ = app_handle.emit("history-updated", "");
+    Ok(updated)
+}
+
+/// Correct a mislabelled entry (e.g. SVG detected as text) so it renders and
+/// pastes with the right type
+#[command]
+pub async fn set_entry_content_type(
+    app_handle: AppHandle,
+    entry_id: String,
+    content_type: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<bool, String> {
+    ensure_history_unlocked()?;
+    let max_entries = config.read().await.0.max_entries();
+    let mut history = load_history(&app_handle, max_entries)?;
 
-    if let Some(content) = history.get_entry_content(&entry_id) {
-        cache_clipboard_data(&content);
-        set_clipboard(&content)?;
-        drop(content);
+    let updated = history.set_content_type(&entry_id, content_type.trim())?;
+    if updated {
+        save_history(&app_handle, &history)?;
         let _ = app_handle.emit("history-updated", "");
-        Ok(())
+    }
+    Ok(updated)
+}
+
+#[command]
+pub async fn dedupe_history(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<usize, String> {
+    ensure_history_unlocked()?;
+    let (max_entries, dedup_images) = {
+        let cfg = config.read().await;
+        (cfg.0.max_entries(), cfg.0.dedup_images())
+    };
+    let removed = crate::history::dedupe_history(&app_handle, max_entries, dedup_images)?;
+
+    if removed > 0 {
+        let _ = app_handle.emit("history-updated", "");
+    }
+    Ok(removed)
+}
+
+#[command]
+pub async fn set_entry_pinned(
+    app_handle: AppHandle,
+    entry_id: String,
+    pinned: bool,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<bool, String> {
+    ensure_history_unlocked()?;
+    let max_entries = config.read().await.0.max_entries();
+    let mut history = load_history(&app_handle, max_entries)?;
+    let updated = history.set_pinned(&entry_id, pinned);
+    save_history(&app_handle, &history)?;
+
+    let _ = app_handle.emit("history-updated", "");
+    Ok(updated)
+}
+
+#[command]
+pub async fn remove_clipboard_entry(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<bool, String> {
+    ensure_history_unlocked()?;
+    let (max_entries, soft_delete) = {
+        let cfg = config.read().await;
+        (cfg.0.max_entries(), cfg.0.soft_delete)
+    };
+    let mut history = load_history(&app_handle, max_entries)?;
+    let removed = if soft_delete {
+        history.trash_entry(&entry_id)
     } else {
-        Err("Entry not found".to_string())
+        history.remove_entry(&entry_id)
+    };
+    save_history(&app_handle, &history)?;
+    if crate::history::last_selected(&app_handle, &history).is_none() {
+        crate::history::set_last_selected(&app_handle, None)?;
     }
+
+    let _ = app_handle.emit("history-updated", "");
+    Ok(removed)
 }
 
+/// Remove several entries at once (e.g. acting on `find_duplicates`); honours
+/// `soft_delete` like single removal. Returns how many were removed.
 #[command]
-pub async fn get_history_stats(
+pub async fn remove_clipboard_entries(
     app_handle: AppHandle,
+    entry_ids: Vec<String>,
     config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
-) -> Result<HistoryStats, String> {
-    let max_entries = config.read().await.0.history_limit as usize;
-    let history = load_history(&app_handle, max_entries)?;
-    
-    let stats = HistoryStats {
-        total_entries: history.entries.len(),
-        max_entries: history.max_entries,
-        total_size_bytes: history.entries.iter().map(|e| e.content_size).sum(),
+) -> Result<usize, String> {
+    ensure_history_unlocked()?;
+    let (max_entries, soft_delete) = {
+        let cfg = config.read().await;
+        (cfg.0.max_entries(), cfg.0.soft_delete)
     };
-    
-    Ok(stats)
+    let mut history = load_history(&app_handle, max_entries)?;
+    let mut removed = 0;
+    for id in &entry_ids {
+        let gone = if soft_delete {
+            history.trash_entry(id)
+        } else {
+            history.remove_entry(id)
+        };
+        if gone {
+            removed += 1;
+        }
+    }
+    if removed == 0 {
+        return Ok(0);
+    }
+    save_history(&app_handle, &history)?;
+    if crate::history::last_selected(&app_handle, &history).is_none() {
+        crate::history::set_last_selected(&app_handle, None)?;
+    }
+
+    let _ = app_handle.emit("history-updated", "");
+    Ok(removed)
 }
 
-#[derive(serde::Serialize)]
-pub struct HistoryStats {
-    pub total_entries: usize,
-    pub max_entries: usize,
-    pub total_size_bytes: usize,
+/// Groups of entry ids with identical content, newest first in each group
+#[command]
+pub async fn find_duplicates(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Vec<Vec<String>>, String> {
+    let (max_entries, dedup_images) = {
+        let cfg = config.read().await;
+        (cfg.0.max_entries(), cfg.0.dedup_images())
+    };
+    let mut history = load_history(&app_handle, max_entries)?;
+    history.dedup_images = dedup_images;
+    Ok(history.find_duplicates())
 }
 
-#[derive(serde::Serialize)]
-pub struct ClipboardData {
-    pub content: Vec<u8>,
-    pub content_type: String,
+/// Hex SHA-256 of an entry's content as stored on disk, for comparing entries
+/// across machines or spotting corruption
+#[command]
+pub async fn get_entry_checksum(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<String, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+    history.entry_checksum(&entry_id)
 }
 
+/// Size and format of an image entry without sending or decoding its pixels
 #[command]
-pub async fn get_theme(
-    claw_config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
-) -> Result<Theme, String> {
-    let theme = claw_config.read().await.1.clone();
-    Ok(theme)
+pub async fn get_image_info(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<crate::history::ImageInfo, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let mut history = load_history(&app_handle, max_entries)?;
+    let (info, computed) = history.image_info(&entry_id)?;
+
+    // Caching is best-effort; a locked history just recomputes next time
+    if computed && !crate::history::history_locked() {
+        save_history(&app_handle, &history)?;
+    }
+    Ok(info)
 }
 
+/// Write history as a readable `txt` or `md` file for reviewing or sharing.
+/// Returns how many entries were written.
 #[command]
-pub async fn get_claw_config(
-    claw_config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
-) -> Result<ClipboardConfig, String> {
-    let cfg = claw_config.read().await;
-    Ok(cfg.0.clone())
+pub async fn export_history_text(
+    app_handle: AppHandle,
+    dest_path: String,
+    format: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<usize, String> {
+    let markdown = match format.to_lowercase().as_str() {
+        "md" | "markdown" => true,
+        "txt" | "text" => false,
+        other => return Err(format!("Unknown export format '{}' (expected txt or md)", other)),
+    };
+
+    let settings = config.read().await.0.clone();
+    let history = load_history(&app_handle, settings.max_entries())?;
+    let (document, written) = history.export_text(markdown, &settings.timestamp_format, &settings.timezone);
+
+    std::fs::write(&dest_path, document).map_err(|e| format!("Failed to write {}: {}", dest_path, e))?;
+    Ok(written)
+}
+
+/// Recently removed entries (metadata only, newest first) and why each went
+#[command]
+pub fn get_deletion_log(app_handle: AppHandle) -> Result<Vec<crate::history::DeletionRecord>, String> {
+    crate::history::deletion_log(&app_handle)
+}
+
+/// Bring back an entry deleted with `soft_delete` on, within the grace period
+#[command]
+pub async fn restore_entry(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    ensure_history_unlocked()?;
+    let max_entries = config.read().await.0.max_entries();
+    let mut history = load_history(&app_handle, max_entries)?;
+    history.restore_entry(&entry_id)?;
+    save_history(&app_handle, &history)?;
+
+    let _ = app_handle.emit("history-updated", "");
+    Ok(())
+}
+
+/// Put a history entry on the clipboard, then auto-paste it if configured
+async fn apply_history_entry(
+    app_handle: &AppHandle,
+    history: &mut ClipboardHistory,
+    entry_id: &str,
+    auto_paste: bool,
+) -> Result<(), String> {
+    let content = history
+        .get_entry_content(entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+
+    apply_entry_content(app_handle, history, entry_id, content, auto_paste).await
+}
+
+/// Put `content` (an entry's, possibly rewritten) on the clipboard and count it
+/// as a use of `entry_id`
+async fn apply_entry_content(
+    app_handle: &AppHandle,
+    history: &mut ClipboardHistory,
+    entry_id: &str,
+    content: Vec<u8>,
+    auto_paste: bool,
+) -> Result<(), String> {
+    let mimes = history
+        .entries
+        .iter()
+        .find(|e| e.id == entry_id)
+        .map(|e| e.mimes.clone())
+        .unwrap_or_default();
+    cache_clipboard_data(&content);
+    crate::clipboard::set_clipboard_with_mimes(&content, &mimes)?;
+    drop(content);
+    if history.record_use(entry_id) && !crate::history::history_locked() {
+        save_history(app_handle, history)?;
+    }
+    crate::history::set_last_selected(app_handle, Some(entry_id))?;
+    let _ = app_handle.emit("history-updated", "");
+
+    if auto_paste {
+        // Hide first so focus returns to the window we're pasting into
+        crate::window::hide_main_window(app_handle);
+        tokio::time::sleep(tokio::time::Duration::from_millis(150)).await;
+        if let Err(e) = crate::paste::synthesize_paste() {
+            eprintln!("Auto-paste unavailable, copied only: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+pub async fn set_clipboard_from_history(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let (max_entries, auto_paste, close_on_select) = {
+        let cfg = config.read().await;
+        (cfg.0.max_entries(), cfg.0.auto_paste, cfg.0.close_on_select)
+    };
+    let mut history = load_history(&app_handle, max_entries)?;
+
+    apply_history_entry(&app_handle, &mut history, &entry_id, auto_paste).await?;
+    close_after_select(&app_handle, auto_paste, close_on_select);
+    Ok(())
+}
+
+/// `close_on_select`: hide the picker once an entry is applied. Auto-paste
+/// has already hidden it (before pasting) so there is nothing left to do then.
+fn close_after_select(app_handle: &AppHandle, auto_paste: bool, close_on_select: bool) {
+    if close_on_select && !auto_paste {
+        crate::window::hide_main_window(app_handle);
+    }
+}
+
+/// Save what is on the clipboard now as a labelled snippet, without it having
+/// to be captured into history first. Returns the snippet's entry id.
+#[command]
+pub async fn snippet_from_clipboard(
+    app_handle: AppHandle,
+    label: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<String, String> {
+    ensure_history_unlocked()?;
+    let label = label.trim();
+    if label.is_empty() {
+        return Err("Snippet label can't be empty".to_string());
+    }
+    let content = crate::clipboard::get_clipboard_for_paste()?;
+    if content.is_empty() {
+        return Err("Clipboard is empty; nothing to save as a snippet".to_string());
+    }
+
+    let max_entries = config.read().await.0.max_entries();
+    let mut history = load_history(&app_handle, max_entries)?;
+    let content_type = crate::history::refine_content_type(&content, detect_content_type(&content));
+    let id = history.save_snippet(content, content_type, label)?;
+    save_history(&app_handle, &history)?;
+
+    let _ = app_handle.emit("history-updated", "");
+    Ok(id)
+}
+
+/// Copy a text entry with its template placeholders filled in (see
+/// `utils::TEMPLATE_VARIABLES`); `{{clipboard}}` is what was copied before
+#[command]
+pub async fn apply_snippet(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let (max_entries, auto_paste) = {
+        let cfg = config.read().await;
+        (cfg.0.max_entries(), cfg.0.auto_paste)
+    };
+    let mut history = load_history(&app_handle, max_entries)?;
+
+    let is_text = history
+        .entries
+        .iter()
+        .find(|e| e.id == entry_id)
+        .is_some_and(|e| crate::utils::is_text_content_type(&e.content_type));
+    if !is_text {
+        return Err("Only text entries can be used as snippets".to_string());
+    }
+    let content = history
+        .get_entry_content(&entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+
+    let previous = crate::utils::lock_recover(&crate::clipboard::PERSISTENT_CLIPBOARD_DATA).clone();
+    let previous = String::from_utf8_lossy(previous.as_deref().unwrap_or_default()).to_string();
+    let expanded = crate::utils::expand_template(&String::from_utf8_lossy(&content), &previous);
+
+    apply_entry_content(&app_handle, &mut history, &entry_id, expanded.into_bytes(), auto_paste).await
+}
+
+#[derive(serde::Serialize)]
+pub struct TemplateVariable {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Placeholders `apply_snippet` fills in, for the snippet editor to list
+#[command]
+pub fn get_template_variables() -> Vec<TemplateVariable> {
+    crate::utils::TEMPLATE_VARIABLES
+        .iter()
+        .map(|&(name, description)| TemplateVariable { name, description })
+        .collect()
+}
+
+/// History entry holding what is on the system clipboard right now, if any
+#[command]
+pub async fn get_current_entry_id(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Option<String>, String> {
+    let current = crate::utils::lock_recover(&crate::clipboard::PERSISTENT_CLIPBOARD_DATA).clone();
+    let Some(current) = current.filter(|data| !data.is_empty()) else {
+        return Ok(None);
+    };
+
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+    Ok(history.find_by_content(&current).map(|entry| entry.id.clone()))
+}
+
+#[command]
+pub async fn rebuild_index_from_disk(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<usize, String> {
+    ensure_history_unlocked()?;
+    let max_entries = config.read().await.0.max_entries();
+    let recovered = crate::history::rebuild_index_from_disk(&app_handle, max_entries)?;
+    if recovered > 0 {
+        let _ = app_handle.emit("history-updated", "");
+    }
+    Ok(recovered)
+}
+
+#[derive(serde::Serialize)]
+pub struct SchemaVersion {
+    /// Version found in the store before this call (None for a new or pre-versioning store)
+    pub stored: Option<u32>,
+    pub current: u32,
+    /// The store was older and has been migrated to `current`
+    pub migrated: bool,
+}
+
+/// Report the history store schema version, migrating the store if it is out of date
+#[command]
+pub async fn get_schema_version(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<SchemaVersion, String> {
+    let current = crate::history::HISTORY_SCHEMA_VERSION;
+    let stored = crate::history::stored_schema_version(&app_handle)?;
+
+    let migrated = stored != Some(current);
+    if migrated {
+        let max_entries = config.read().await.0.max_entries();
+        load_history(&app_handle, max_entries)?;
+        let _ = app_handle.emit("history-updated", "");
+    }
+
+    Ok(SchemaVersion { stored, current, migrated })
+}
+
+/// Write an entry to a temp file (with a matching extension) for dragging
+/// out of the window. Returns the file path.
+#[command]
+pub async fn materialize_entry(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<String, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    let entry = history
+        .entries
+        .iter()
+        .find(|e| e.id == entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+    let content = history
+        .get_entry_content(&entry_id)
+        .ok_or_else(|| "Entry content could not be read".to_string())?;
+
+    let path = crate::utils::materialize_entry_file(&entry.id, &entry.content_type, &content)
+        .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Entry last applied from the picker, so it can reopen on it
+#[command]
+pub async fn get_last_selected(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Option<String>, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+    Ok(crate::history::last_selected(&app_handle, &history))
+}
+
+/// Entry ids grouped by the app they were copied from
+#[command]
+pub async fn get_history_by_app(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<std::collections::HashMap<String, Vec<String>>, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+    Ok(crate::history::group_by_app(&history))
+}
+
+/// Entry content as text for display, with invalid UTF-8 replaced rather than dropped
+#[command]
+pub async fn get_entry_text_lossy(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<String, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    let entry = history
+        .entries
+        .iter()
+        .find(|e| e.id == entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+    if entry.content_type.starts_with("image/") {
+        return Err("Image entries have no text".to_string());
+    }
+
+    let content = history
+        .get_entry_content(&entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+    Ok(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// What `transform_entries` did with one of the requested entries
+#[derive(serde::Serialize)]
+pub struct TransformResult {
+    pub id: String,
+    /// Entry holding the transformed text. None without an `error` means the
+    /// result repeats the latest entry, so nothing was added.
+    pub new_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Apply `ops` in order to each text entry in `ids`, storing every result as a
+/// new entry (originals are kept). Returns one result per id, in order.
+#[command]
+pub async fn transform_entries(
+    app_handle: AppHandle,
+    ids: Vec<String>,
+    ops: Vec<String>,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Vec<TransformResult>, String> {
+    ensure_history_unlocked()?;
+    if let Some(op) = ops.iter().find(|op| !TEXT_TRANSFORMS.contains(&op.as_str())) {
+        return Err(format!("Unknown transform \"{}\"", op));
+    }
+
+    let settings = config.read().await.0.clone();
+    let mut history = load_history(&app_handle, settings.max_entries())?;
+    history.dedup_ignore_whitespace = settings.dedup_ignore_whitespace;
+
+    let mut results = Vec::with_capacity(ids.len());
+    for id in ids {
+        let text = match history.entries.iter().find(|e| e.id == id) {
+            None => Err("entry not found".to_string()),
+            Some(entry) if entry.content_type.starts_with("image/") => Err("not a text entry".to_string()),
+            Some(_) => history
+                .get_entry_content(&id)
+                .and_then(|content| String::from_utf8(content).ok())
+                .ok_or_else(|| "not a text entry".to_string()),
+        };
+        let transformed = text.and_then(|text| {
+            ops.iter()
+                .try_fold(text, |text, op| apply_text_transform(&text, op))
+        });
+
+        let added = transformed.and_then(|text| {
+            let content = text.into_bytes();
+            let content_type = crate::history::refine_content_type(&content, detect_content_type(&content));
+            match history.add_entry(content, content_type, None) {
+                AddOutcome::Inserted(new_id) => {
+                    if let Some(entry) = history.entries.iter_mut().find(|e| e.id == new_id) {
+                        entry.source_app = Some(CLAW_APP_NAME.to_string());
+                    }
+                    Ok(Some(new_id))
+                }
+                AddOutcome::Duplicate => Ok(None),
+                AddOutcome::Rejected => Err("failed to store the result".to_string()),
+            }
+        });
+
+        results.push(match added {
+            Ok(new_id) => TransformResult { id, new_id, error: None },
+            Err(e) => TransformResult { id, new_id: None, error: Some(e) },
+        });
+    }
+
+    save_history(&app_handle, &history)?;
+    let _ = app_handle.emit("history-updated", "");
+    Ok(results)
+}
+
+/// Dominant color of an image entry as `#rrggbb`, for tinting its card
+#[command]
+pub async fn get_entry_dominant_color(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<String, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    let entry = history
+        .entries
+        .iter()
+        .find(|e| e.id == entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+    if !entry.content_type.starts_with("image/") {
+        return Err("Only image entries have a dominant color".to_string());
+    }
+
+    Ok(history.dominant_color(&entry_id))
+}
+
+/// Most text a peek will return, whatever the caller asks for
+const PEEK_MAX_BYTES: usize = 1024 * 1024;
+
+#[derive(serde::Serialize)]
+pub struct PeekText {
+    pub text: String,
+    /// The entry is longer than what was returned
+    pub truncated: bool,
+}
+
+/// Full text of an entry for the peek popup, cut to `max_bytes`
+/// (capped at 1MB) on a character boundary
+#[command]
+pub async fn get_entry_full_text(
+    app_handle: AppHandle,
+    entry_id: String,
+    max_bytes: usize,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<PeekText, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    let entry = history
+        .entries
+        .iter()
+        .find(|e| e.id == entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+    if entry.content_type.starts_with("image/") {
+        return Err("Image entries have no text".to_string());
+    }
+
+    let content = history
+        .get_entry_content(&entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+    let text = String::from_utf8_lossy(&content);
+
+    let limit = max_bytes.min(PEEK_MAX_BYTES);
+    if text.len() <= limit {
+        return Ok(PeekText { text: text.into_owned(), truncated: false });
+    }
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Ok(PeekText { text: text[..end].to_string(), truncated: true })
+}
+
+/// Set the manual order of pinned entries (e.g. after drag-to-reorder)
+#[command]
+pub async fn reorder_pins(
+    app_handle: AppHandle,
+    ordered_ids: Vec<String>,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    ensure_history_unlocked()?;
+    let max_entries = config.read().await.0.max_entries();
+    let mut history = load_history(&app_handle, max_entries)?;
+    history.reorder_pins(&ordered_ids)?;
+    save_history(&app_handle, &history)?;
+
+    let _ = app_handle.emit("history-updated", "");
+    Ok(())
+}
+
+#[command]
+pub fn get_active_profile() -> String {
+    crate::history::active_profile()
+}
+
+/// Switch history to another profile for this session; the picker, tray and
+/// watcher all follow. Use `clipboard.profile` to make it stick.
+#[command]
+pub fn set_active_profile(app_handle: AppHandle, name: String) -> Result<(), String> {
+    crate::history::set_active_profile(&name)?;
+    let _ = crate::tray::update_tray_menu(&app_handle, crate::tray::TRAY_ID);
+    let _ = app_handle.emit("history-updated", "");
+    Ok(())
+}
+
+#[command]
+pub fn list_profiles(app_handle: AppHandle) -> Vec<String> {
+    crate::history::list_profiles(&app_handle)
+}
+
+#[command]
+pub fn create_profile(name: String) -> Result<(), String> {
+    crate::history::create_profile(&name)
+}
+
+#[command]
+pub fn delete_profile(app_handle: AppHandle, name: String) -> Result<(), String> {
+    ensure_history_unlocked()?;
+    crate::history::delete_profile(&app_handle, &name)
+}
+
+#[command]
+pub async fn get_top_pinned(
+    app_handle: AppHandle,
+    limit: usize,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Vec<ClipboardEntry>, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+    Ok(history.top_pinned(limit).into_iter().cloned().collect())
+}
+
+#[command]
+pub async fn reset_usage_stats(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    ensure_history_unlocked()?;
+    let max_entries = config.read().await.0.max_entries();
+    let mut history = load_history(&app_handle, max_entries)?;
+    history.reset_usage_stats();
+    save_history(&app_handle, &history)?;
+    let _ = app_handle.emit("history-updated", "");
+    Ok(())
+}
+
+/// Put an image entry on the clipboard re-encoded as `target_format`.
+/// The stored entry is left as it is.
+#[command]
+pub async fn set_clipboard_from_history_as(
+    app_handle: AppHandle,
+    entry_id: String,
+    target_format: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    let entry = history
+        .entries
+        .iter()
+        .find(|e| e.id == entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+    if !entry.content_type.starts_with("image/") {
+        return Err("Only image entries can be converted".to_string());
+    }
+
+    let content = history
+        .get_entry_content(&entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+    let converted = crate::utils::convert_image(&content, &target_format)?;
+
+    cache_clipboard_data(&converted);
+    set_clipboard(&converted)
+}
+
+/// Load a history entry into the primary selection without touching the clipboard
+#[command]
+pub async fn set_primary_from_history(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+    let content = history
+        .get_entry_content(&entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+
+    crate::clipboard::set_primary_selection(&content)
+}
+
+/// Copy (and auto-paste, if enabled) the Nth most recent entry, resolved
+/// against the history as it is right now rather than the UI's last copy of it
+#[command]
+pub async fn quick_paste(
+    app_handle: AppHandle,
+    index: usize,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<String, String> {
+    let (max_entries, auto_paste, close_on_select) = {
+        let cfg = config.read().await;
+        (cfg.0.max_entries(), cfg.0.auto_paste, cfg.0.close_on_select)
+    };
+    let mut history = load_history(&app_handle, max_entries)?;
+
+    // Same order the picker shows, so the Nth key matches the Nth row
+    let entry_id = history
+        .get_entries(Some(index + 1))
+        .get(index)
+        .map(|e| e.id.clone())
+        .ok_or_else(|| format!("No entry at position {}", index + 1))?;
+
+    apply_history_entry(&app_handle, &mut history, &entry_id, auto_paste).await?;
+    close_after_select(&app_handle, auto_paste, close_on_select);
+    Ok(entry_id)
+}
+
+#[command]
+pub async fn concat_entries(
+    app_handle: AppHandle,
+    ids: Vec<String>,
+    separator: String,
+    record: bool,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<String, String> {
+    let settings = config.read().await.0.clone();
+    let max_entries = settings.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    let mut parts = Vec::with_capacity(ids.len());
+    let mut rejected = Vec::new();
+
+    for id in &ids {
+        let is_text = history
+            .entries
+            .iter()
+            .find(|e| &e.id == id)
+            .map(|e| !e.content_type.starts_with("image/") && e.content_type != "binary")
+            .unwrap_or(false);
+
+        match history.get_entry_content(id).map(String::from_utf8) {
+            Some(Ok(text)) if is_text => parts.push(text),
+            _ => rejected.push(id.clone()),
+        }
+    }
+
+    if !rejected.is_empty() {
+        return Err(format!("Cannot concatenate non-text or missing entries: {}", rejected.join(", ")));
+    }
+
+    let combined = parts.join(&separator);
+    set_combined_text(&app_handle, &settings, &combined, record)?;
+    Ok(combined)
+}
+
+/// Append a text entry to what is on the clipboard now, joined by `separator`,
+/// to build up one combined paste. An empty clipboard just takes the entry.
+#[command]
+pub async fn append_to_clipboard(
+    app_handle: AppHandle,
+    entry_id: String,
+    separator: String,
+    record: bool,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<String, String> {
+    let settings = config.read().await.0.clone();
+    let history = load_history(&app_handle, settings.max_entries())?;
+
+    let entry = history
+        .entries
+        .iter()
+        .find(|e| e.id == entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+    if entry.content_type.starts_with("image/") || entry.content_type == "binary" {
+        return Err("Only text entries can be appended".to_string());
+    }
+    let addition = history
+        .get_entry_content(&entry_id)
+        .map(String::from_utf8)
+        .ok_or_else(|| "Entry content could not be read".to_string())?
+        .map_err(|_| "Entry is not valid UTF-8 text".to_string())?;
+
+    let current = get_clipboard_for_paste()?;
+    if detect_content_type(&current).starts_with("image/") {
+        return Err("The clipboard holds an image; only text can be appended to".to_string());
+    }
+    let current = String::from_utf8(current).map_err(|_| "The clipboard is not valid UTF-8 text".to_string())?;
+
+    let combined = if current.is_empty() {
+        addition
+    } else {
+        format!("{}{}{}", current, separator, addition)
+    };
+    set_combined_text(&app_handle, &settings, &combined, record)?;
+    Ok(combined)
+}
+
+/// Put text built from entries on the clipboard, recording it if asked to
+fn set_combined_text(
+    app_handle: &AppHandle,
+    settings: &ClipboardConfig,
+    text: &str,
+    record: bool,
+) -> Result<(), String> {
+    let content = text.as_bytes();
+    cache_clipboard_data(content);
+    set_clipboard(content)?;
+
+    if record && !crate::history::history_locked() {
+        crate::history::add_to_history(
+            app_handle,
+            content,
+            detect_content_type(content),
+            settings.max_entries(),
+            EntrySource {
+                app: Some(CLAW_APP_NAME.to_string()),
+                ..Default::default()
+            },
+            settings.dedup_ignore_whitespace,
+            settings.dedup_images(),
+        )?;
+        let _ = app_handle.emit("history-updated", "");
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn open_entry_url(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    let content = history
+        .get_entry_content(&entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+    let text = String::from_utf8(content).map_err(|_| "Entry is not text".to_string())?;
+
+    let url = extract_first_url(&text).ok_or_else(|| "Entry does not contain a URL".to_string())?;
+
+    open_with_default(&url)
+}
+
+#[command]
+pub async fn reveal_in_file_manager(
+    app_handle: AppHandle,
+    entry_id: String,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+
+    let entry = history
+        .entries
+        .iter()
+        .find(|e| e.id == entry_id)
+        .ok_or_else(|| "Entry not found".to_string())?;
+
+    // Prefer the recorded source, then a file list in the content itself
+    let path = match &entry.source_path {
+        Some(src) if src.starts_with("file://") => file_path_from_source(src),
+        _ => history
+            .get_entry_content(&entry.id)
+            .filter(|content| is_file_copy(content))
+            .and_then(|content| file_path_from_source(&String::from_utf8_lossy(&content))),
+    }
+    .ok_or_else(|| "Entry is not a file".to_string())?;
+
+    if !path.exists() {
+        return Err(format!("File no longer exists: {}", path.display()));
+    }
+
+    reveal_path(&path)
+}
+
+#[command]
+pub async fn get_history_stats(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<HistoryStats, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+    
+    let stats = HistoryStats {
+        total_entries: history.entries.len(),
+        max_entries: history.max_entries,
+        total_size_bytes: history.entries.iter().map(|e| e.content_size).sum(),
+        image_entries: history
+            .entries
+            .iter()
+            .filter(|e| e.content_type.starts_with("image/"))
+            .count(),
+    };
+    
+    Ok(stats)
+}
+
+/// How fast history is growing and when it will hit `history_max_length`
+#[command]
+pub async fn get_growth_estimate(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<GrowthEstimate, String> {
+    let max_entries = config.read().await.0.max_entries();
+    let history = load_history(&app_handle, max_entries)?;
+    Ok(history.growth_estimate())
+}
+
+#[command]
+pub fn history_health_check(app_handle: AppHandle) -> HistoryHealth {
+    crate::history::health_check(&app_handle)
+}
+
+#[derive(serde::Serialize)]
+pub struct HistoryStats {
+    pub total_entries: usize,
+    pub max_entries: usize,
+    pub total_size_bytes: usize,
+    pub image_entries: usize,
+}
+
+#[derive(serde::Serialize)]
+pub struct ClipboardData {
+    pub content: Vec<u8>,
+    pub content_type: String,
+}
+
+/// Raise the history limit until claw exits, without touching the config file.
+/// A limit at or below `history_max_length` has no effect; 0 clears the override.
+#[command]
+pub async fn set_session_history_limit(
+    limit: u64,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    config.write().await.0.session_history_limit = (limit > 0).then_some(limit);
+    Ok(())
+}
+
+#[command]
+pub async fn set_tray_history_enabled(
+    app_handle: AppHandle,
+    enabled: bool,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let config_path = config_file_for("clipboard.tray_history").map_err(|e| e.to_string())?;
+    write_config_value(&config_path, "clipboard.tray_history", &enabled.to_string())
+        .map_err(|e| e.to_string())?;
+
+    config.write().await.0.tray_history = enabled;
+
+    crate::tray::update_tray_menu(&app_handle, crate::tray::TRAY_ID).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn set_persist_history(
+    app_handle: AppHandle,
+    enabled: bool,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let config_path = config_file_for("clipboard.persist_history").map_err(|e| e.to_string())?;
+    write_config_value(&config_path, "clipboard.persist_history", &enabled.to_string())
+        .map_err(|e| e.to_string())?;
+
+    let (max_entries, update) = {
+        let mut cfg = config.write().await;
+        cfg.0.persist_history = enabled;
+        (
+            cfg.0.max_entries(),
+            crate::ConfigUpdate {
+                enable_titlebar: cfg.0.enable_titlebar,
+                force_dark_mode: cfg.0.force_dark_mode,
+                theme: cfg.1.clone(),
+            },
+        )
+    };
+
+    // Enabling: make sure what we have right now is flushed to disk.
+    // Disabling: history stays usable for this session and is wiped on exit.
+    if enabled {
+        let history = load_history(&app_handle, max_entries)?;
+        save_history(&app_handle, &history)?;
+    }
+
+    let _ = app_handle.emit("config-reloaded", update);
+    Ok(())
+}
+
+/// Toggle `clipboard.force_dark_mode`, saving it to the config and applying it
+/// to the window right away
+#[command]
+pub async fn set_force_dark_mode(
+    app_handle: AppHandle,
+    enabled: bool,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    let config_path = config_file_for("clipboard.force_dark_mode")
+        .map_err(|e| e.to_string())
+        .inspect_err(|e| record_error(e.clone()))?;
+    write_config_value(&config_path, "clipboard.force_dark_mode", &enabled.to_string())
+        .map_err(|e| format!("Failed to save force_dark_mode: {}", e))
+        .inspect_err(|e| record_error(e.clone()))?;
+
+    let update = {
+        let mut cfg = config.write().await;
+        cfg.0.force_dark_mode = enabled;
+        crate::ConfigUpdate {
+            enable_titlebar: cfg.0.enable_titlebar,
+            force_dark_mode: enabled,
+            theme: cfg.1.clone(),
+        }
+    };
+
+    if let Err(e) = crate::window::apply_dark_mode(&app_handle, enabled) {
+        record_error(format!("Failed to set window theme: {}", e));
+    }
+
+    let _ = app_handle.emit("config-reloaded", update);
+    Ok(())
+}
+
+/// Keep the picker above other windows (or stop) for this session, or save the
+/// choice as `clipboard.always_on_top` with `persist`. Returns the new state.
+#[command]
+pub async fn set_always_on_top(
+    app_handle: AppHandle,
+    enabled: bool,
+    persist: bool,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<bool, String> {
+    if persist {
+        let config_path = config_file_for("clipboard.always_on_top").map_err(|e| e.to_string())?;
+        write_config_value(&config_path, "clipboard.always_on_top", &enabled.to_string())
+            .map_err(|e| e.to_string())?;
+        config.write().await.0.always_on_top = enabled;
+    }
+
+    let configured = config.read().await.0.always_on_top;
+    crate::window::set_always_on_top_override(&app_handle, enabled, configured).map_err(|e| e.to_string())?;
+    Ok(crate::window::is_always_on_top())
+}
+
+#[command]
+pub fn get_always_on_top() -> bool {
+    crate::window::is_always_on_top()
+}
+
+/// Name, bounds and DPI scale of the monitor the picker window is on
+#[command]
+pub fn get_window_monitor(app_handle: AppHandle) -> Result<crate::window::WindowMonitor, String> {
+    crate::window::window_monitor(&app_handle)
+}
+
+#[command]
+pub async fn get_theme(
+    claw_config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Theme, String> {
+    let theme = claw_config.read().await.1.clone();
+    Ok(theme)
+}
+
+#[command]
+pub async fn get_claw_config(
+    claw_config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<ClipboardConfig, String> {
+    let cfg = claw_config.read().await;
+    Ok(cfg.0.clone())
+}
+
+/// Active keybinds with where each came from ("config" or "default")
+#[command]
+pub async fn get_effective_keybinds(
+    claw_config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Vec<crate::config::EffectiveKeybind>, String> {
+    let cfg = claw_config.read().await;
+    Ok(cfg.0.keybinds.effective())
+}
+
+#[command]
+pub fn get_config_value(key: String) -> Option<String> {
+    raw_config_value(&key)
+}
+
+#[command]
+pub fn set_config_value(key: String, value: String) -> Result<(), String> {
+    crate::config::set_config_value(&key, &value).map_err(|e| e.to_string())
+}
+
+/// Swap the active theme to one from a gathered document, optionally saving
+/// the choice as `clipboard.theme`
+#[command]
+pub async fn set_active_theme_alias(
+    app_handle: AppHandle,
+    alias: String,
+    persist: bool,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Theme, String> {
+    let path = find_config().ok_or_else(|| "No claw.rune config found".to_string())?;
+    let rune = crate::config::load_rune_file(&path).map_err(|e| e.to_string())?;
+    let theme = crate::config::theme_for_alias(&rune, &alias)
+        .ok_or_else(|| format!("Gathered document '{}' has no theme block", alias))?;
+
+    let update = {
+        let mut cfg = config.write().await;
+        cfg.1 = theme.clone();
+        crate::ConfigUpdate {
+            enable_titlebar: cfg.0.enable_titlebar,
+            force_dark_mode: cfg.0.force_dark_mode,
+            theme: theme.clone(),
+        }
+    };
+    let _ = app_handle.emit("config-reloaded", update);
+
+    if persist {
+        crate::config::set_config_value("clipboard.theme", &alias).map_err(|e| e.to_string())?;
+    }
+
+    Ok(theme)
+}
+
+// Longest a UI edit session may hold config reloads off
+const CONFIG_WATCH_PAUSE_SECS: u64 = 30;
+
+#[command]
+pub fn pause_config_watch() {
+    crate::watchers::pause_config_watch(std::time::Duration::from_secs(CONFIG_WATCH_PAUSE_SECS));
+}
+
+#[command]
+pub async fn resume_config_watch(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<(), String> {
+    crate::watchers::resume_config_watch();
+    // Pick up everything written while paused in one reload
+    crate::watchers::reload_config(&app_handle, &config).await
+}
+
+#[command]
+pub fn get_last_error() -> Option<String> {
+    crate::utils::last_error()
+}
+
+#[command]
+pub fn clear_last_error() {
+    crate::utils::clear_last_error();
+}
+
+/// Config and gather files the config watcher reloads on
+#[command]
+pub fn get_watched_paths() -> Vec<String> {
+    crate::watchers::watched_paths()
+}
+
+/// Why the watcher would or wouldn't record what is on the clipboard now
+#[command]
+pub async fn explain_current_clipboard(
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<crate::watchers::ClipboardExplain, String> {
+    let settings = config.read().await.0.clone();
+    crate::watchers::explain_clipboard(&settings)
+}
+
+/// Reload the config and pick up gather files added or removed since the
+/// watcher started, without waiting for the next edit. Returns the watched files.
+#[command]
+pub async fn rescan_gather_files(
+    app_handle: AppHandle,
+    config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<Vec<String>, String> {
+    crate::watchers::reload_config(&app_handle, &config).await?;
+    crate::watchers::rescan_watched_paths()
+}
+
+#[command]
+pub fn get_config_path() -> Option<String> {
+    find_config().map(|path| path.to_string_lossy().to_string())
+}
+
+#[command]
+pub fn open_config_file() -> Result<(), String> {
+    let path = find_config().ok_or_else(|| "No claw.rune config found".to_string())?;
+    open_with_default(&path.to_string_lossy())
+}
+
+#[command]
+pub fn get_gather_results() -> Vec<GatherResult> {
+    find_config()
+        .map(|path| gather_results(&path))
+        .unwrap_or_default()
+}
+
+#[derive(serde::Serialize)]
+pub struct AppInfo {
+    pub version: String,
+    pub tauri_version: String,
+    pub desktop_env: String,
+    pub backend: String,
+}
+
+fn backend_name(env: crate::detect::DesktopEnv) -> &'static str {
+    match env {
+        crate::detect::DesktopEnv::Wayland => "wl-clipboard-rs",
+        crate::detect::DesktopEnv::X11 => "x11-clipboard",
+        crate::detect::DesktopEnv::Unknown => "wl-clipboard-rs (x11-clipboard fallback)",
+    }
+}
+
+#[command]
+pub fn get_app_info() -> AppInfo {
+    let env = crate::detect::current_desktop_env();
+
+    AppInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+        desktop_env: format!("{:?}", env),
+        backend: backend_name(env).to_string(),
+    }
+}
+
+/// The resolved config, theme and some diagnostics as one JSON document, for
+/// backups and support bundles (history is not included)
+#[command]
+pub async fn export_settings(
+    claw_config: State<'_, Arc<RwLock<(ClipboardConfig, Theme)>>>,
+) -> Result<String, String> {
+    let (config, theme) = claw_config.read().await.clone();
+    let env = crate::detect::current_desktop_env();
+
+    let settings = serde_json::json!({
+        "config": config,
+        "theme_name": raw_config_value("clipboard.theme"),
+        "theme": theme,
+        "diagnostics": {
+            "version": env!("CARGO_PKG_VERSION"),
+            "desktop_env": format!("{:?}", env),
+            "backend": backend_name(env),
+            "config_path": find_config(),
+            "local_config_path": crate::config::find_local_config(),
+        },
+    });
+    serde_json::to_string_pretty(&settings).map_err(|e| format!("Failed to serialize settings: {}", e))
+}
+
+/// Write settings from `export_settings` back into claw.rune; the config
+/// watcher then reloads them. Returns how many keys were written.
+#[command]
+pub fn import_settings(json: String) -> Result<usize, String> {
+    let settings: serde_json::Value =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid settings JSON: {}", e))?;
+    crate::config::import_settings(&settings)
+        .map_err(|e| format!("Failed to import settings: {}", e))
+        .inspect_err(|e| record_error(e.clone()))
+}
+
+#[derive(serde::Serialize)]
+pub struct BackendStatus {
+    pub detected_env: String,
+    /// Backend reads and writes go through; "memory" once claw has fallen back
+    /// to its in-process copy after both backends kept failing
+    pub active_backend: String,
+    /// Environment variable that selected the backend, if any
+    pub override_source: Option<String>,
+    pub last_read_ok: bool,
+    pub last_read_error: Option<String>,
+}
+
+/// Everything needed to diagnose clipboard access in one call
+#[command]
+pub fn get_backend_status() -> BackendStatus {
+    let env = crate::detect::current_desktop_env();
+    let active_backend = if crate::clipboard::is_memory_only() {
+        "memory"
+    } else {
+        backend_name(env)
+    };
+    let (last_read_ok, last_read_error) = crate::watchers::last_read_status();
+
+    BackendStatus {
+        detected_env: format!("{:?}", env),
+        active_backend: active_backend.to_string(),
+        override_source: crate::detect::desktop_env_source().map(str::to_string),
+        last_read_ok,
+        last_read_error,
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct WatcherDiagnostics {
+    pub restarts: u64,
+    pub memory_only: bool,
+}
+
+#[command]
+pub fn get_watcher_diagnostics() -> WatcherDiagnostics {
+    WatcherDiagnostics {
+        restarts: crate::watchers::watcher_restart_count(),
+        memory_only: crate::clipboard::is_memory_only(),
+    }
+}
+
+#[command]
+pub fn get_watcher_metrics() -> crate::watchers::WatcherMetrics {
+    crate::watchers::watcher_metrics()
+}
+
+/// Re-set the system clipboard from claw's cached copy immediately, for
+/// compositors that drop the selection between keep-alive ticks
+#[command]
+pub fn reinject_clipboard() -> Result<bool, String> {
+    crate::clipboard::reinject_clipboard()
+}
+
+/// Copies recorded since claw started, without scanning history
+#[command]
+pub fn get_session_stats() -> crate::watchers::SessionStats {
+    crate::watchers::session_stats()
 }
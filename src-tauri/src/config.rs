@@ -1,9 +1,11 @@
 use eyre::{eyre, Result};
+use once_cell::sync::Lazy;
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Mutex;
 
 use crate::theme::{find_theme_file, Theme};
-use rune_cfg::{RuneConfig, RuneError};
+use rune_cfg::{RuneConfig, RuneError, Value};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,6 +15,44 @@ pub struct Keybinds {
     pub delete: String,
     pub delete_all: String,
     pub select: String,
+    pub peek: String,
+    /// Actions that weren't set in the config and fell back to their default
+    #[serde(skip)]
+    pub defaulted: Vec<String>,
+}
+
+/// One active binding and whether it came from the config or the defaults
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveKeybind {
+    pub action: String,
+    pub key: String,
+    /// "config" or "default"
+    pub source: &'static str,
+}
+
+impl Keybinds {
+    /// Every binding in effect, keyed by its `clipboard.keybinds.*` name
+    pub fn effective(&self) -> Vec<EffectiveKeybind> {
+        [
+            ("up", &self.up),
+            ("down", &self.down),
+            ("delete", &self.delete),
+            ("delete_all", &self.delete_all),
+            ("select", &self.select),
+            ("peek", &self.peek),
+        ]
+        .into_iter()
+        .map(|(action, key)| EffectiveKeybind {
+            action: action.to_string(),
+            key: key.clone(),
+            source: if self.defaulted.iter().any(|a| a == action) {
+                "default"
+            } else {
+                "config"
+            },
+        })
+        .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,8 +62,92 @@ pub struct ClipboardConfig {
     pub force_dark_mode: bool,
     pub keybinds: Keybinds,
     pub persist_history: bool,
+    pub min_text_length: usize,
+    pub record_files: bool,
+    pub record_images: bool,
+    pub window_width: u32,
+    pub window_height: u32,
+    pub always_on_top: bool,
+    pub dedupe_on_startup: bool,
+    pub max_image_dimension: u32,
+    pub timestamp_format: String,
+    pub timezone: String,
+    pub auto_paste: bool,
+    pub tray_content_types: Vec<String>,
+    pub primary_selection: bool,
+    pub dedup_ignore_whitespace: bool,
+    pub max_read_bytes: usize,
+    pub tray_history: bool,
+    pub tray_unique: bool,
+    pub trim_blank_lines: bool,
+    pub rebuild_index_on_startup: bool,
+    pub second_instance_action: String,
+    pub capture_mode: String,
+    pub strip_ansi: bool,
+    pub tray_empty_text: String,
+    pub tray_show_stats: bool,
+    /// Session-only raise of `history_limit` (never written to config)
+    #[serde(default)]
+    pub session_history_limit: Option<u64>,
+    pub hide_on_blur: bool,
+    pub hide_on_blur_delay_ms: u64,
+    pub ignore_mimes: Vec<String>,
+    pub image_dedup: String,
+    pub soft_delete: bool,
+    pub store_format: String,
+    pub profile: String,
+    pub redact_patterns: Vec<String>,
+    pub max_text_entries: usize,
+    pub max_image_entries: usize,
+    pub lock_history: bool,
+    pub notify_on_capture: bool,
+    pub idle_after_secs: u64,
+    pub idle_poll_max_ms: u64,
+    pub idle_backoff_percent: u64,
+    pub clear_sets_empty_string: bool,
+    pub trim_trailing_newline: bool,
+    pub x11_fallback_to_primary: bool,
+    pub only_record_patterns: Vec<String>,
+    pub only_record_excludes_images: bool,
+    pub auto_pin_recent: usize,
+    pub recapture_cooldown_secs: u64,
+    pub on_oversize: String,
+    pub close_on_select: bool,
+    pub low_memory: bool,
 }
 
+impl ClipboardConfig {
+    /// History size to keep: the configured limit, or the session limit if larger
+    pub fn max_entries(&self) -> usize {
+        self.history_limit.max(self.session_history_limit.unwrap_or(0)) as usize
+    }
+
+    /// Whether an image identical to the previous one is dropped (`image_dedup = "exact"`)
+    pub fn dedup_images(&self) -> bool {
+        self.image_dedup != "none"
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "message")]
+pub enum GatherStatus {
+    Loaded,
+    Missing,
+    ParseError(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GatherResult {
+    pub path: String,
+    pub alias: Option<String>,
+    #[serde(flatten)]
+    pub status: GatherStatus,
+}
+
+/// Parsed main config and local overlay from the last successful load,
+/// kept for `raw_config_value` so lookups don't re-read the files
+static RAW_CONFIG: Lazy<Mutex<Option<(RuneConfig, Option<RuneConfig>)>>> = Lazy::new(|| Mutex::new(None));
+
 /// rune_cfg 0.4.0 `from_file_with_base` requires both args share the same type `P`,
 /// so we pass PathBuf for both.
 fn rune_from_file_with_base(path: PathBuf, base_dir: PathBuf) -> Result<RuneConfig> {
@@ -31,6 +155,68 @@ fn rune_from_file_with_base(path: PathBuf, base_dir: PathBuf) -> Result<RuneConf
         .map_err(|e: RuneError| eyre!("Failed to load config: {}", e))
 }
 
+/// Main config with an optional machine-local overlay on top.
+/// Keys set in the overlay win; everything else falls through to the main config.
+struct LayeredConfig<'a> {
+    base: &'a RuneConfig,
+    local: Option<&'a RuneConfig>,
+}
+
+impl LayeredConfig<'_> {
+    fn get<T: TryFrom<Value, Error = RuneError>>(&self, key: &str) -> Result<T> {
+        if let Some(local) = self.local {
+            if let Ok(value) = local.get::<T>(key) {
+                return Ok(value);
+            }
+        }
+        self.base
+            .get::<T>(key)
+            .map_err(|e: RuneError| eyre!("Failed to read {}: {}", key, e))
+    }
+
+    fn get_or<T: TryFrom<Value, Error = RuneError>>(&self, key: &str, default: T) -> T {
+        self.get(key).unwrap_or(default)
+    }
+
+    /// Like `get_or`, also telling whether the key was set (false: defaulted)
+    fn get_or_tracked<T: TryFrom<Value, Error = RuneError>>(&self, key: &str, default: T) -> (T, bool) {
+        match self.get(key) {
+            Ok(value) => (value, true),
+            Err(_) => (default, false),
+        }
+    }
+}
+
+/// Load the optional `claw.local.rune` overlay, skipping it if absent or broken
+fn load_local_config() -> Option<RuneConfig> {
+    let path = find_local_config()?;
+    let base_dir = path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+
+    match rune_from_file_with_base(path.clone(), base_dir) {
+        Ok(cfg) => Some(cfg),
+        Err(err) => {
+            eprintln!("Ignoring {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// Theme from a gathered document, if that document carries a theme block
+pub fn theme_for_alias(config: &RuneConfig, alias: &str) -> Option<Theme> {
+    let has_theme = config.get::<String>(&format!("{alias}.theme.light.background")).is_ok()
+        || config.get::<String>(&format!("{alias}.light.background")).is_ok();
+    has_theme.then(|| Theme::from_config(config, Some(alias)))
+}
+
+/// Parse a config file with gathers resolved relative to its directory
+pub fn load_rune_file(path: &Path) -> Result<RuneConfig> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
+    rune_from_file_with_base(path.to_path_buf(), base_dir)
+}
+
 // --- Load Config ---
 pub fn load_config(path: &str) -> Result<(ClipboardConfig, Theme)> {
     let path_buf = PathBuf::from(path);
@@ -41,17 +227,31 @@ pub fn load_config(path: &str) -> Result<(ClipboardConfig, Theme)> {
 
     // IMPORTANT: load from file so rune_cfg can resolve gathers/imports
     let config = rune_from_file_with_base(path_buf.clone(), base_dir)?;
+    let local_config = load_local_config();
+    let settings = LayeredConfig {
+        base: &config,
+        local: local_config.as_ref(),
+    };
 
     // Load the theme block with priority system
     let theme = {
         let mut loaded_theme: Option<Theme> = None;
 
-        // PRIORITY 1: Any imported doc containing theme.light.background
-        for alias in config.import_aliases() {
-            let test_path = format!("{}.theme.light.background", alias);
-            if config.get::<String>(&test_path).is_ok() {
-                loaded_theme = Some(Theme::from_config(&config, Some(&alias)));
-                break;
+        // PRIORITY 1: Any imported doc containing theme.light.background,
+        // preferring the one named by clipboard.theme
+        let aliases: Vec<String> = config.import_aliases().into_iter().map(|a| a.to_string()).collect();
+        if let Ok(preferred) = settings.get::<String>("clipboard.theme") {
+            if aliases.iter().any(|a| *a == preferred) {
+                loaded_theme = theme_for_alias(&config, &preferred);
+            }
+        }
+        if loaded_theme.is_none() {
+            for alias in &aliases {
+                let test_path = format!("{}.theme.light.background", alias);
+                if config.get::<String>(&test_path).is_ok() {
+                    loaded_theme = Some(Theme::from_config(&config, Some(alias)));
+                    break;
+                }
             }
         }
 
@@ -64,7 +264,7 @@ pub fn load_config(path: &str) -> Result<(ClipboardConfig, Theme)> {
 
         // PRIORITY 3: clipboard.theme field (load external theme file)
         if loaded_theme.is_none() {
-            if let Ok(theme_name) = config.get::<String>("clipboard.theme") {
+            if let Ok(theme_name) = settings.get::<String>("clipboard.theme") {
                 if let Some(theme_path) = find_theme_file(&theme_name) {
                     let theme_base = theme_path
                         .parent()
@@ -88,19 +288,91 @@ pub fn load_config(path: &str) -> Result<(ClipboardConfig, Theme)> {
     };
 
     // Load clipboard config (0.4.0: snake_case/kebab-case handled by get/get_or)
-    let history_limit = config.get_or("clipboard.history_max_length", 50u64);
-    let enable_titlebar = config.get_or("clipboard.enable_titlebar", true);
-    let force_dark_mode = config.get_or("clipboard.force_dark_mode", false);
-    let persist_history = config.get_or("clipboard.persist_history", true);
+    let history_limit = settings.get_or("clipboard.history_max_length", 50u64);
+    let enable_titlebar = settings.get_or("clipboard.enable_titlebar", true);
+    let force_dark_mode = settings.get_or("clipboard.force_dark_mode", false);
+    let persist_history = settings.get_or("clipboard.persist_history", true);
+    let min_text_length = settings.get_or("clipboard.min_text_length", 0u64) as usize;
+    let record_files = settings.get_or("clipboard.record_files", true);
+    let record_images = settings.get_or("clipboard.record_images", true);
+    let window_width = settings.get_or("clipboard.window_width", 600u64) as u32;
+    let window_height = settings.get_or("clipboard.window_height", 800u64) as u32;
+    let always_on_top = settings.get_or("clipboard.always_on_top", false);
+    let dedupe_on_startup = settings.get_or("clipboard.dedupe_on_startup", false);
+    let max_image_dimension = settings.get_or("clipboard.max_image_dimension", 0u64) as u32;
+    let timestamp_format = settings.get_or(
+        "clipboard.timestamp_format",
+        crate::utils::DEFAULT_TIMESTAMP_FORMAT.to_string(),
+    );
+    let timezone = settings.get_or("clipboard.timezone", "local".to_string());
+    let auto_paste = settings.get_or("clipboard.auto_paste", false);
+    let tray_content_types = settings.get_or("clipboard.tray_content_types", Vec::<String>::new());
+    let primary_selection = settings.get_or("clipboard.primary_selection", false);
+    let dedup_ignore_whitespace = settings.get_or("clipboard.dedup_ignore_whitespace", false);
+    let max_read_bytes = settings.get_or(
+        "clipboard.max_read_bytes",
+        crate::clipboard::DEFAULT_MAX_READ_BYTES as u64,
+    ) as usize;
+    let tray_history = settings.get_or("clipboard.tray_history", true);
+    let tray_unique = settings.get_or("clipboard.tray_unique", true);
+    let trim_blank_lines = settings.get_or("clipboard.trim_blank_lines", false);
+    let rebuild_index_on_startup = settings.get_or("clipboard.rebuild_index_on_startup", false);
+    let second_instance_action = settings.get_or("clipboard.second_instance_action", "show".to_string());
+    let capture_mode = settings.get_or("clipboard.capture_mode", "always".to_string());
+    let strip_ansi = settings.get_or("clipboard.strip_ansi", false);
+    let tray_empty_text = settings.get_or("clipboard.tray_empty_text", "No clipboard history yet".to_string());
+    let tray_show_stats = settings.get_or("clipboard.tray_show_stats", false);
+    let hide_on_blur = settings.get_or("clipboard.hide_on_blur", false);
+    let hide_on_blur_delay_ms = settings.get_or("clipboard.hide_on_blur_delay_ms", 150u64);
+    let ignore_mimes = validate_ignore_mimes(settings.get_or("clipboard.ignore_mimes", Vec::<String>::new()));
+    let image_dedup = settings.get_or("clipboard.image_dedup", "exact".to_string());
+    let soft_delete = settings.get_or("clipboard.soft_delete", false);
+    let store_format = settings.get_or("clipboard.store_format", "json".to_string());
+    let profile = settings.get_or("clipboard.profile", "default".to_string());
+    let redact_patterns = validate_patterns(
+        "redact_patterns",
+        settings.get_or("clipboard.redact_patterns", Vec::<String>::new()),
+    );
+    let max_text_entries = settings.get_or("clipboard.max_text_entries", 0u64) as usize;
+    let max_image_entries = settings.get_or("clipboard.max_image_entries", 0u64) as usize;
+    let lock_history = settings.get_or("clipboard.lock_history", false);
+    let notify_on_capture = settings.get_or("clipboard.notify_on_capture", false);
+    let idle_after_secs = settings.get_or("clipboard.idle_after_secs", 60u64);
+    let idle_poll_max_ms = settings.get_or("clipboard.idle_poll_max_ms", 2000u64).max(250);
+    let idle_backoff_percent = settings.get_or("clipboard.idle_backoff_percent", 50u64).max(1);
+    let clear_sets_empty_string = settings.get_or("clipboard.clear_sets_empty_string", false);
+    let trim_trailing_newline = settings.get_or("clipboard.trim_trailing_newline", false);
+    let x11_fallback_to_primary = settings.get_or("clipboard.x11_fallback_to_primary", false);
+    let only_record_patterns = validate_patterns(
+        "only_record_patterns",
+        settings.get_or("clipboard.only_record_patterns", Vec::<String>::new()),
+    );
+    let only_record_excludes_images = settings.get_or("clipboard.only_record_excludes_images", false);
+    let auto_pin_recent = settings.get_or("clipboard.auto_pin_recent", 0u64) as usize;
+    let recapture_cooldown_secs = settings.get_or("clipboard.recapture_cooldown_secs", 0u64);
+    let on_oversize = settings.get_or("clipboard.on_oversize", "drop".to_string());
+    let close_on_select = settings.get_or("clipboard.close_on_select", false);
+    let low_memory = settings.get_or("clipboard.low_memory", false);
 
     // Load keybinds
-    let keybinds = Keybinds {
-        up: config.get_or("clipboard.keybinds.up", "ArrowUp".to_string()),
-        down: config.get_or("clipboard.keybinds.down", "ArrowDown".to_string()),
-        delete: config.get_or("clipboard.keybinds.delete", "X".to_string()),
-        delete_all: config.get_or("clipboard.keybinds.delete_all", "shift+X".to_string()),
-        select: config.get_or("clipboard.keybinds.select", "Enter".to_string()),
+    let mut defaulted = Vec::new();
+    let mut keybind = |action: &str, default: &str| {
+        let (key, found) = settings.get_or_tracked(&format!("clipboard.keybinds.{}", action), default.to_string());
+        if !found {
+            defaulted.push(action.to_string());
+        }
+        key
+    };
+    let mut keybinds = Keybinds {
+        up: keybind("up", "ArrowUp"),
+        down: keybind("down", "ArrowDown"),
+        delete: keybind("delete", "X"),
+        delete_all: keybind("delete_all", "shift+X"),
+        select: keybind("select", "Enter"),
+        peek: keybind("peek", "p"),
+        defaulted: Vec::new(),
     };
+    keybinds.defaulted = defaulted;
 
     let clipboard = ClipboardConfig {
         history_limit,
@@ -108,11 +380,119 @@ pub fn load_config(path: &str) -> Result<(ClipboardConfig, Theme)> {
         force_dark_mode,
         keybinds,
         persist_history,
+        min_text_length,
+        record_files,
+        record_images,
+        window_width,
+        window_height,
+        always_on_top,
+        dedupe_on_startup,
+        max_image_dimension,
+        timestamp_format,
+        timezone,
+        auto_paste,
+        tray_content_types,
+        primary_selection,
+        dedup_ignore_whitespace,
+        max_read_bytes,
+        tray_history,
+        tray_unique,
+        trim_blank_lines,
+        rebuild_index_on_startup,
+        second_instance_action,
+        capture_mode,
+        strip_ansi,
+        tray_empty_text,
+        tray_show_stats,
+        session_history_limit: None,
+        hide_on_blur,
+        hide_on_blur_delay_ms,
+        ignore_mimes,
+        image_dedup,
+        soft_delete,
+        store_format,
+        profile,
+        redact_patterns,
+        max_text_entries,
+        max_image_entries,
+        lock_history,
+        notify_on_capture,
+        idle_after_secs,
+        idle_poll_max_ms,
+        idle_backoff_percent,
+        clear_sets_empty_string,
+        trim_trailing_newline,
+        x11_fallback_to_primary,
+        only_record_patterns,
+        only_record_excludes_images,
+        auto_pin_recent,
+        recapture_cooldown_secs,
+        on_oversize,
+        close_on_select,
+        low_memory,
     };
 
+    *crate::utils::lock_recover(&RAW_CONFIG) = Some((config, local_config));
+
     Ok((clipboard, theme))
 }
 
+/// Lower-case `ignore_mimes` entries and drop ones that can't be a MIME type
+/// or prefix of one (e.g. "text/_moz_htmlcontext", "chromium/")
+fn validate_ignore_mimes(mimes: Vec<String>) -> Vec<String> {
+    mimes
+        .into_iter()
+        .filter_map(|mime| {
+            let mime = mime.trim().to_ascii_lowercase();
+            let valid = !mime.is_empty()
+                && mime
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "/.+-_".contains(c));
+            if !valid {
+                eprintln!("Ignoring invalid clipboard.ignore_mimes entry {:?}", mime);
+            }
+            valid.then_some(mime)
+        })
+        .collect()
+}
+
+/// Drop entries of a regex list setting (`clipboard.<key>`) that aren't valid regexes
+fn validate_patterns(key: &str, patterns: Vec<String>) -> Vec<String> {
+    patterns
+        .into_iter()
+        .filter(|pattern| match regex::Regex::new(pattern) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("Ignoring invalid clipboard.{} entry {:?}: {}", key, pattern, e);
+                false
+            }
+        })
+        .collect()
+}
+
+/// Read any dotted key from the last loaded config (local overlay first),
+/// rendered as a string. Covers keys that `ClipboardConfig` doesn't map.
+pub fn raw_config_value(key: &str) -> Option<String> {
+    let guard = crate::utils::lock_recover(&RAW_CONFIG);
+    let (base, local) = guard.as_ref()?;
+    let settings = LayeredConfig {
+        base,
+        local: local.as_ref(),
+    };
+
+    settings
+        .get::<String>(key)
+        .or_else(|_| settings.get::<bool>(key).map(|v| v.to_string()))
+        .or_else(|_| settings.get::<i64>(key).map(|v| v.to_string()))
+        .or_else(|_| settings.get::<f64>(key).map(|v| v.to_string()))
+        .or_else(|_| {
+            settings
+                .get::<Vec<String>>(key)
+                .map(|v| serde_json::to_string(&v).unwrap_or_default())
+        })
+        .ok()
+}
+
 // --- Config file discovery ---
 pub fn find_config() -> Option<PathBuf> {
     if let Some(config_dir) = dirs::config_dir() {
@@ -137,6 +517,373 @@ pub fn find_config() -> Option<PathBuf> {
     None
 }
 
+/// Normalize a rune key so `persist-history` and `persist_history` compare equal
+fn normalize_key(key: &str) -> String {
+    key.trim().replace('-', "_").to_lowercase()
+}
+
+/// Rewrite (or add) a dotted key such as `clipboard.persist_history` in a rune
+/// file. `literal` is written verbatim, so strings must already be quoted.
+/// Comments and unrelated lines are left untouched, including a trailing
+/// comment on the rewritten line; a list value spanning several lines is
+/// replaced as a whole.
+pub fn write_config_value(path: &Path, key: &str, literal: &str) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| eyre!("Failed to read {}: {}", path.display(), e))?;
+
+    let mut output = rewrite_config_value(&content, key, literal)?;
+    if content.ends_with('\n') {
+        output.push('\n');
+    }
+
+    std::fs::write(path, output).map_err(|e| eyre!("Failed to write {}: {}", path.display(), e))
+}
+
+/// `write_config_value` on the text of a rune file, without the final newline
+fn rewrite_config_value(content: &str, key: &str, literal: &str) -> Result<String> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let (sections, name) = split_config_key(key)?;
+    let (found, ancestor_end) = locate_config_key(&lines, &sections, &name);
+
+    if let Some(idx) = found {
+        let line = &lines[idx];
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let line_key = line
+            .trim()
+            .split(|c: char| c == '=' || c.is_whitespace())
+            .next()
+            .unwrap_or_default();
+        let value = line.split_once('=').map_or("", |(_, value)| value);
+
+        // Follow an unclosed list onto the lines after it; the comment kept is
+        // the one after the value ends
+        let mut depth = 0;
+        let mut comment = trailing_comment(value, &mut depth);
+        let mut last = idx;
+        while depth > 0 && last + 1 < lines.len() {
+            last += 1;
+            comment = trailing_comment(&lines[last], &mut depth);
+        }
+
+        let new_line = format!("{}{} = {}{}", indent, line_key, literal, comment.unwrap_or_default());
+        lines.splice(idx..=last, [new_line]);
+    } else {
+        // Add whichever blocks on the key's path are missing inside the
+        // deepest one that exists, or at the end of the file
+        let (depth, at) = match ancestor_end {
+            Some((depth, idx)) => (depth, idx),
+            None => (0, lines.len()),
+        };
+        let mut added = Vec::new();
+        if depth == 0 && !sections.is_empty() {
+            added.push(String::new());
+        }
+        for (level, section) in sections.iter().enumerate().skip(depth) {
+            added.push(format!("{}{}:", "    ".repeat(level), section.replace('_', "-")));
+        }
+        added.push(format!("{}{} = {}", "    ".repeat(sections.len()), name.replace('_', "-"), literal));
+        for level in (depth..sections.len()).rev() {
+            added.push(format!("{}end", "    ".repeat(level)));
+        }
+        lines.splice(at..at, added);
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// Block path and key name of a dotted key, normalized
+fn split_config_key(key: &str) -> Result<(Vec<String>, String)> {
+    let mut parts: Vec<String> = key.split('.').map(normalize_key).collect();
+    let name = parts.pop().filter(|name| !name.is_empty()).ok_or_else(|| eyre!("Empty config key"))?;
+    Ok((parts, name))
+}
+
+/// Line setting `name` directly inside the `sections` block path, plus the
+/// depth and closing `end` line of the deepest block on that path that exists
+fn locate_config_key(lines: &[String], sections: &[String], name: &str) -> (Option<usize>, Option<(usize, usize)>) {
+    let mut stack: Vec<String> = Vec::new();
+    let mut ancestor_end: Option<(usize, usize)> = None;
+
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('@') {
+            continue;
+        }
+
+        if trimmed == "end" {
+            let have_deeper = matches!(ancestor_end, Some((depth, _)) if depth >= stack.len());
+            if !stack.is_empty() && sections.starts_with(&stack) && !have_deeper {
+                ancestor_end = Some((stack.len(), idx));
+            }
+            stack.pop();
+            continue;
+        }
+
+        if let Some(block) = trimmed.strip_suffix(':') {
+            stack.push(normalize_key(block));
+            continue;
+        }
+
+        if stack != sections {
+            continue;
+        }
+
+        let line_key = trimmed
+            .split(|c: char| c == '=' || c.is_whitespace())
+            .next()
+            .unwrap_or_default();
+        if normalize_key(line_key) == name {
+            return (Some(idx), ancestor_end);
+        }
+    }
+
+    (None, ancestor_end)
+}
+
+/// The file a dotted key should be written to: the claw.local.rune overlay when
+/// it sets the key (so a reload doesn't bring the old value back), else claw.rune
+pub fn config_file_for(key: &str) -> Result<PathBuf> {
+    if let Some(local) = find_local_config() {
+        let content = std::fs::read_to_string(&local)
+            .map_err(|e| eyre!("Failed to read {}: {}", local.display(), e))?;
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let (sections, name) = split_config_key(key)?;
+        if locate_config_key(&lines, &sections, &name).0.is_some() {
+            return Ok(local);
+        }
+    }
+    find_config().ok_or_else(|| eyre!("No claw.rune config found"))
+}
+
+/// Track `[`/`]` nesting through `text`, skipping quoted strings. Returns the
+/// `# comment` ending the line, with the spacing before it, once the value is
+/// closed.
+fn trailing_comment(text: &str, depth: &mut usize) -> Option<String> {
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut comment_at = None;
+
+    for (idx, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '[' => *depth += 1,
+            ']' => *depth = depth.saturating_sub(1),
+            '#' => {
+                comment_at = Some(idx);
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let at = comment_at.filter(|_| *depth == 0)?;
+    let before = &text[..at];
+    Some(format!("{}{}", &before[before.trim_end().len()..], &text[at..]))
+}
+
+/// `gather "path" [as alias]` statements in a config file, with `~/` expanded
+pub fn gather_statements(config_path: &Path) -> Vec<(PathBuf, Option<String>)> {
+    let content = std::fs::read_to_string(config_path).unwrap_or_default();
+    let gather_regex = regex::Regex::new(r#"gather\s+"([^"]+)"(?:\s+as\s+(\w+))?"#).unwrap();
+    gather_regex
+        .captures_iter(&content)
+        .filter_map(|cap| {
+            let path_str = &cap[1];
+            let expanded_path = if let Some(rest) = path_str.strip_prefix("~/") {
+                dirs::home_dir()?.join(rest)
+            } else {
+                PathBuf::from(path_str)
+            };
+            Some((expanded_path, cap.get(2).map(|m| m.as_str().to_string())))
+        })
+        .collect()
+}
+
+/// Check every gather statement in the config and report whether it loads
+pub fn gather_results(config_path: &Path) -> Vec<GatherResult> {
+    gather_statements(config_path)
+        .into_iter()
+        .map(|(path, alias)| {
+            let status = if !path.exists() {
+                GatherStatus::Missing
+            } else {
+                let base_dir = path
+                    .parent()
+                    .unwrap_or_else(|| Path::new("."))
+                    .to_path_buf();
+                match rune_from_file_with_base(path.clone(), base_dir) {
+                    Ok(_) => GatherStatus::Loaded,
+                    Err(err) => GatherStatus::ParseError(err.to_string()),
+                }
+            };
+
+            GatherResult {
+                path: path.to_string_lossy().to_string(),
+                alias,
+                status,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ValueKind {
+    Bool,
+    Number,
+    Text,
+    List,
+}
+
+/// Types of the keys `load_config` understands
+fn known_value_kind(key: &str) -> Option<ValueKind> {
+    let key = normalize_key(key);
+    let name = key.strip_prefix("clipboard.")?;
+    if name.starts_with("keybinds.") {
+        return Some(ValueKind::Text);
+    }
+
+    match name {
+        "enable_titlebar" | "force_dark_mode" | "always_on_top" | "hide_on_blur" | "close_on_select"
+        | "record_files" | "record_images" | "only_record_excludes_images" | "notify_on_capture"
+        | "primary_selection" | "x11_fallback_to_primary" | "auto_paste" | "clear_sets_empty_string"
+        | "dedup_ignore_whitespace" | "trim_blank_lines" | "trim_trailing_newline" | "strip_ansi"
+        | "persist_history" | "dedupe_on_startup" | "rebuild_index_on_startup" | "soft_delete"
+        | "lock_history" | "low_memory"
+        | "tray_history" | "tray_unique" | "tray_show_stats" => Some(ValueKind::Bool),
+        "window_width" | "window_height" | "hide_on_blur_delay_ms"
+        | "history_max_length" | "max_text_entries" | "max_image_entries" | "auto_pin_recent"
+        | "min_text_length" | "max_image_dimension" | "max_read_bytes" | "recapture_cooldown_secs"
+        | "idle_after_secs" | "idle_poll_max_ms" | "idle_backoff_percent" => Some(ValueKind::Number),
+        "theme" | "timestamp_format" | "timezone" | "tray_empty_text"
+        | "second_instance_action" | "capture_mode" | "image_dedup"
+        | "on_oversize" | "store_format" | "profile" => Some(ValueKind::Text),
+        "tray_content_types" | "ignore_mimes" | "redact_patterns" | "only_record_patterns" => Some(ValueKind::List),
+        _ => None,
+    }
+}
+
+/// Guess a value's type from how it is written
+fn infer_value_kind(value: &str) -> ValueKind {
+    let value = value.trim();
+    if value.parse::<bool>().is_ok() {
+        ValueKind::Bool
+    } else if value.parse::<f64>().is_ok() {
+        ValueKind::Number
+    } else if value.starts_with('[') {
+        ValueKind::List
+    } else {
+        ValueKind::Text
+    }
+}
+
+/// Turn a user-supplied value into a rune literal, coerced to the key's
+/// known type, or else the type of its current value.
+fn config_literal(key: &str, value: &str) -> Result<String> {
+    let kind = known_value_kind(key)
+        .or_else(|| raw_config_value(key).map(|current| infer_value_kind(&current)))
+        .unwrap_or_else(|| infer_value_kind(value));
+    let value = value.trim();
+
+    match kind {
+        ValueKind::Bool => value
+            .to_lowercase()
+            .parse::<bool>()
+            .map(|b| b.to_string())
+            .map_err(|_| eyre!("{} expects true or false", key)),
+        ValueKind::Number => value
+            .parse::<f64>()
+            .map(|_| value.to_string())
+            .map_err(|_| eyre!("{} expects a number", key)),
+        ValueKind::List => {
+            let items: Vec<String> =
+                serde_json::from_str(value).map_err(|_| eyre!("{} expects a list of strings", key))?;
+            let quoted: Vec<String> = items.iter().map(|i| format!("{:?}", i)).collect();
+            Ok(format!("[{}]", quoted.join(", ")))
+        }
+        ValueKind::Text => Ok(format!("{:?}", value.trim_matches('"'))),
+    }
+}
+
+/// Set a dotted key in the config file it comes from; the config watcher picks
+/// up the change and reloads as if the user had edited the file.
+pub fn set_config_value(key: &str, value: &str) -> Result<()> {
+    let path = config_file_for(key)?;
+    let literal = config_literal(key, value)?;
+    write_config_value(&path, key, &literal)
+}
+
+/// Write the settings from an `export_settings` object back into claw.rune
+/// (or claw.local.rune, for keys the overlay sets).
+/// Every value is checked against its key's type before anything is written,
+/// so a bad import leaves the file untouched. Fields that only exist at runtime
+/// or that this version doesn't know are skipped. Returns how many keys were written.
+pub fn import_settings(settings: &serde_json::Value) -> Result<usize> {
+    let config = settings
+        .get("config")
+        .and_then(|c| c.as_object())
+        .ok_or_else(|| eyre!("Settings have no \"config\" object"))?;
+
+    let mut values: Vec<(String, &serde_json::Value)> = Vec::new();
+    for (field, value) in config {
+        match field.as_str() {
+            "session_history_limit" => {}
+            "keybinds" => {
+                let keybinds = value.as_object().ok_or_else(|| eyre!("keybinds must be an object"))?;
+                values.extend(keybinds.iter().map(|(action, key)| (format!("clipboard.keybinds.{}", action), key)));
+            }
+            "history_limit" => values.push(("clipboard.history_max_length".to_string(), value)),
+            name => values.push((format!("clipboard.{}", name), value)),
+        }
+    }
+    if let Some(theme) = settings.get("theme_name").filter(|t| !t.is_null()) {
+        values.push(("clipboard.theme".to_string(), theme));
+    }
+
+    let mut literals = Vec::new();
+    for (key, value) in values {
+        if known_value_kind(&key).is_none() {
+            eprintln!("Skipping unknown setting {}", key);
+            continue;
+        }
+        let raw = match value {
+            serde_json::Value::Null => continue,
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        literals.push((key.clone(), config_literal(&key, &raw)?));
+    }
+
+    for (key, literal) in &literals {
+        write_config_value(&config_file_for(key)?, key, literal)?;
+    }
+    Ok(literals.len())
+}
+
+/// Machine-specific overlay that sits next to the user config
+pub fn find_local_config() -> Option<PathBuf> {
+    let local = dirs::config_dir()?.join("claw").join("claw.local.rune");
+    local.exists().then_some(local)
+}
+
+/// Snapshot of the managed clipboard config for sync callers (tray, menu events).
+/// Returns None if the state isn't registered yet or is being written.
+pub fn current_clipboard_config(app: &tauri::AppHandle) -> Option<ClipboardConfig> {
+    use tauri::Manager;
+
+    let state = app.try_state::<std::sync::Arc<tokio::sync::RwLock<(ClipboardConfig, Theme)>>>()?;
+    let cfg = state.try_read().ok()?;
+    Some(cfg.0.clone())
+}
+
 /// Top-level config loader that exits gracefully on failure.
 pub fn load_claw_config() -> (ClipboardConfig, Theme) {
     let path = find_config().expect("No claw.rune config found");
@@ -148,3 +895,51 @@ pub fn load_claw_config() -> (ClipboardConfig, Theme) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrite_config_value_replaces_a_list_spanning_lines() {
+        let content = "clipboard:\n    ignore-mimes = [\n        \"a\",\n        \"b\",\n    ]\n    theme = \"default\"\nend";
+        let output = rewrite_config_value(content, "clipboard.ignore_mimes", "[\"c\"]").unwrap();
+        assert_eq!(output, "clipboard:\n    ignore-mimes = [\"c\"]\n    theme = \"default\"\nend");
+    }
+
+    #[test]
+    fn rewrite_config_value_adds_a_missing_block_inside_its_parent() {
+        let content = "clipboard:\n    theme = \"default\"\nend";
+        let output = rewrite_config_value(content, "clipboard.keybinds.peek", "\"p\"").unwrap();
+        assert_eq!(
+            output,
+            "clipboard:\n    theme = \"default\"\n    keybinds:\n        peek = \"p\"\n    end\nend"
+        );
+
+        let output = rewrite_config_value(&output, "clipboard.keybinds.up", "\"k\"").unwrap();
+        assert_eq!(output.matches("keybinds:").count(), 1);
+        assert!(output.contains("        up = \"k\"\n    end\nend"));
+    }
+
+    #[test]
+    fn rewrite_config_value_keeps_a_trailing_comment() {
+        let content = "clipboard:\n    theme = \"default\"  # see themes/\n    tags = [\"#a\"] # hash in a string\nend";
+        let output = rewrite_config_value(content, "clipboard.theme", "\"nord\"").unwrap();
+        assert!(output.contains("    theme = \"nord\"  # see themes/\n"));
+
+        let output = rewrite_config_value(&output, "clipboard.tags", "[]").unwrap();
+        assert!(output.contains("    tags = [] # hash in a string\n"));
+    }
+
+    #[test]
+    fn locate_config_key_only_matches_the_key_in_its_block() {
+        let lines: Vec<String> = "clipboard:\n    keybinds:\n        theme = \"x\"\n    end\n    theme = \"nord\"\nend"
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let (sections, name) = split_config_key("clipboard.theme").unwrap();
+        assert_eq!(locate_config_key(&lines, &sections, &name).0, Some(4));
+        let (sections, name) = split_config_key("clipboard.persist-history").unwrap();
+        assert_eq!(locate_config_key(&lines, &sections, &name), (None, Some((1, 5))));
+    }
+}
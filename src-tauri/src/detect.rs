@@ -7,29 +7,82 @@ pub enum DesktopEnv {
     Unknown,
 }
 
-// Cache the desktop environment detection result
-static DESKTOP_ENV: OnceLock<DesktopEnv> = OnceLock::new();
+// Cache the desktop environment detection result, with the variable that decided it
+static DESKTOP_ENV: OnceLock<(DesktopEnv, Option<&'static str>)> = OnceLock::new();
 
-fn detect_desktop_env() -> DesktopEnv {
+fn detect_desktop_env() -> (DesktopEnv, Option<&'static str>) {
     // First check XDG_SESSION_TYPE which is the most reliable
     if let Ok(session_type) = std::env::var("XDG_SESSION_TYPE") {
         match session_type.to_lowercase().as_str() {
-            "wayland" => return DesktopEnv::Wayland,
-            "x11" => return DesktopEnv::X11,
+            "wayland" => return (DesktopEnv::Wayland, Some("XDG_SESSION_TYPE")),
+            "x11" => return (DesktopEnv::X11, Some("XDG_SESSION_TYPE")),
             _ => {}
         }
     }
 
     // Fallback to checking display variables
     if std::env::var("WAYLAND_DISPLAY").is_ok() {
-        DesktopEnv::Wayland
+        (DesktopEnv::Wayland, Some("WAYLAND_DISPLAY"))
     } else if std::env::var("DISPLAY").is_ok() {
-        DesktopEnv::X11
+        (DesktopEnv::X11, Some("DISPLAY"))
     } else {
-        DesktopEnv::Unknown
+        (DesktopEnv::Unknown, None)
     }
 }
 
 pub fn current_desktop_env() -> DesktopEnv {
-    *DESKTOP_ENV.get_or_init(|| detect_desktop_env())
+    DESKTOP_ENV.get_or_init(detect_desktop_env).0
+}
+
+/// Environment variable the session type was taken from; None when nothing
+/// identified it and both backends are tried
+pub fn desktop_env_source() -> Option<&'static str> {
+    DESKTOP_ENV.get_or_init(detect_desktop_env).1
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Depth-first search of a sway tree for the focused node
+fn sway_focused_app(node: &serde_json::Value) -> Option<String> {
+    if node["focused"].as_bool() == Some(true) {
+        return node["app_id"]
+            .as_str()
+            .or_else(|| node["window_properties"]["class"].as_str())
+            .map(str::to_string);
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+        .find_map(sway_focused_app)
+}
+
+/// Best-effort name (window class / app id) of the focused application.
+/// Needs `xdotool` on X11; on Wayland only Hyprland and sway are supported.
+pub fn current_focused_app() -> Option<String> {
+    let app = match current_desktop_env() {
+        DesktopEnv::X11 => command_output("xdotool", &["getactivewindow", "getwindowclassname"]),
+        DesktopEnv::Wayland if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() => {
+            let json = command_output("hyprctl", &["activewindow", "-j"])?;
+            let window: serde_json::Value = serde_json::from_str(&json).ok()?;
+            window["class"].as_str().map(str::to_string)
+        }
+        DesktopEnv::Wayland if std::env::var("SWAYSOCK").is_ok() => {
+            let json = command_output("swaymsg", &["-t", "get_tree"])?;
+            sway_focused_app(&serde_json::from_str(&json).ok()?)
+        }
+        _ => None,
+    }?;
+
+    (!app.is_empty()).then_some(app)
 }
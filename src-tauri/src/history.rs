@@ -1,13 +1,28 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
 use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use tauri::AppHandle;
+use tauri::{AppHandle, Emitter};
 use tauri_plugin_store::StoreBuilder;
 
+use crate::utils::{
+    collapse_whitespace, detect_content_type, detect_text_subtype, dominant_color, gif_frame_count,
+    is_text_content_type, is_url, lock_recover, record_error, NEUTRAL_COLOR,
+};
+
 // Maximum size per entry (5MB)
-const MAX_ENTRY_SIZE: usize = 5 * 1024 * 1024;
+pub(crate) const MAX_ENTRY_SIZE: usize = 5 * 1024 * 1024;
+
+// Longest text written per entry by `export_text`
+const EXPORT_TEXT_LIMIT: usize = 4000;
+
+/// Version of the stored history layout. Stores written before versioning read as 0.
+pub const HISTORY_SCHEMA_VERSION: u32 = 1;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClipboardEntry {
@@ -16,14 +31,156 @@ pub struct ClipboardEntry {
     pub content_type: String,
     pub source_path: Option<String>,
     pub content_size: usize,
+    #[serde(default)]
+    pub pinned: bool,
+    /// History copy was shrunk to `max_image_dimension`; the clipboard had the original
+    #[serde(default)]
+    pub downscaled: bool,
+    /// Entry is dropped from history once this passes
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Frame count for GIF entries (more than one means animated)
+    #[serde(default)]
+    pub frame_count: Option<usize>,
+    /// Times a pinned entry was copied back since it was pinned
+    #[serde(default)]
+    pub use_count: u64,
+    /// Rebuilt from a cached file without its original metadata
+    #[serde(default)]
+    pub recovered: bool,
+    /// Application that was focused when the entry was captured
+    #[serde(default)]
+    pub source_app: Option<String>,
+    /// Original encoding of text that was transcoded to UTF-8
+    #[serde(default)]
+    pub source_encoding: Option<String>,
+    /// Position among manually ordered pins (see `reorder_pins`); unpinned entries have none
+    #[serde(default)]
+    pub pin_order: Option<u32>,
+    /// Name given to a snippet (see `save_snippet`); plain captures have none
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Width and height of an image entry, filled in by `image_info` on first use
+    #[serde(default)]
+    pub dimensions: Option<(u32, u32)>,
+    /// Last time the entry was copied back from history
+    #[serde(default)]
+    pub last_used: Option<DateTime<Utc>>,
+    /// Text cut to `MAX_ENTRY_SIZE` under `on_oversize = "truncate"`
+    #[serde(default)]
+    pub truncated: bool,
+    /// Every MIME type the source offered (Wayland only); the ones the stored
+    /// content can serve are offered again when it is copied back
+    #[serde(default)]
+    pub mimes: Vec<String>,
     #[serde(skip)]
     pub content: Vec<u8>,
 }
 
+impl ClipboardEntry {
+    /// Entry captured now, with every optional detail left unset
+    pub fn new(id: String, content_type: String, content_size: usize) -> Self {
+        Self {
+            id,
+            timestamp: Utc::now(),
+            content_type,
+            source_path: None,
+            content_size,
+            pinned: false,
+            downscaled: false,
+            expires_at: None,
+            frame_count: None,
+            use_count: 0,
+            recovered: false,
+            source_app: None,
+            source_encoding: None,
+            pin_order: None,
+            label: None,
+            dimensions: None,
+            last_used: None,
+            truncated: false,
+            mimes: Vec::new(),
+            content: Vec::new(),
+        }
+    }
+}
+
+/// Header details of an image entry, for `get_image_info`
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+    /// Format from the content type, e.g. "PNG"
+    pub format: String,
+    pub size_bytes: usize,
+}
+
+/// Parameters of `ClipboardHistory::query`; every one is optional
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct QueryParams {
+    pub offset: usize,
+    pub limit: Option<usize>,
+    /// Content type prefix, e.g. "image/" or "text"
+    pub content_type_filter: Option<String>,
+    pub search: Option<String>,
+    pub fuzzy: bool,
+    /// "display" (pins first, the default), "newest", "oldest", "largest" or "smallest"
+    pub order: Option<String>,
+    pub date_from: Option<DateTime<Utc>>,
+    pub date_to: Option<DateTime<Utc>>,
+    pub pinned_only: bool,
+}
+
+/// One page of a query, and how many entries matched in all
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryResult {
+    pub entries: Vec<ClipboardEntry>,
+    pub total: usize,
+}
+
+/// How long a soft-deleted entry can be restored before its content is removed
+pub const TRASH_GRACE_SECS: i64 = 10 * 60;
+
+/// An entry removed with `soft_delete` on, kept until the grace period ends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedEntry {
+    pub entry: ClipboardEntry,
+    pub deleted_at: DateTime<Utc>,
+    /// Index the entry had in history, so a restore puts it back in place
+    pub position: usize,
+}
+
+/// What `add_entry` did with new content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddOutcome {
+    /// Recorded as the new front entry, with this id
+    Inserted(String),
+    /// Repeats the latest entry, which is left as it was
+    Duplicate,
+    /// Dropped as oversized, or its content couldn't be saved
+    Rejected,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ClipboardHistory {
     pub entries: VecDeque<ClipboardEntry>,
     pub max_entries: usize,
+    #[serde(default)]
+    pub schema_version: u32,
+    /// Soft-deleted entries whose content is still on disk
+    #[serde(default)]
+    pub trash: Vec<TrashedEntry>,
+    /// Treat text differing only in whitespace as a duplicate
+    #[serde(skip)]
+    pub dedup_ignore_whitespace: bool,
+    /// Drop images identical to an existing entry; off keeps every image
+    #[serde(skip, default = "default_true")]
+    pub dedup_images: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for ClipboardHistory {
@@ -31,15 +188,149 @@ impl Default for ClipboardHistory {
         Self {
             entries: VecDeque::new(),
             max_entries: 100,
+            schema_version: HISTORY_SCHEMA_VERSION,
+            trash: Vec::new(),
+            dedup_ignore_whitespace: false,
+            dedup_images: true,
         }
     }
 }
 
-/// Get the history directory path (~/.cache/claw/)
-fn get_history_dir() -> PathBuf {
-    let cache_dir = dirs::cache_dir()
+/// Profile that keeps the original file names (`claw_history.json`, ~/.cache/claw/)
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// History profile in use; empty means `DEFAULT_PROFILE`
+static ACTIVE_PROFILE: Mutex<String> = Mutex::new(String::new());
+
+pub fn active_profile() -> String {
+    let profile = lock_recover(&ACTIVE_PROFILE);
+    if profile.is_empty() {
+        DEFAULT_PROFILE.to_string()
+    } else {
+        profile.clone()
+    }
+}
+
+/// Route history reads and writes to `name`'s store and content directory
+pub fn set_active_profile(name: &str) -> Result<(), String> {
+    validate_profile_name(name)?;
+    let name = if name == DEFAULT_PROFILE { "" } else { name };
+    *lock_recover(&ACTIVE_PROFILE) = name.to_string();
+    lock_recover(&KNOWN_IDS).clear();
+    Ok(())
+}
+
+/// Profile names end up in file names, so keep them to letters, digits, `-` and `_`
+pub fn validate_profile_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid profile name \"{}\" (use letters, digits, - and _)", name))
+    }
+}
+
+/// `claw_history.<ext>` for the default profile, `claw_history_<name>.<ext>` otherwise
+fn profile_store_file(profile: &str, ext: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        format!("claw_history.{}", ext)
+    } else {
+        format!("claw_history_{}.{}", profile, ext)
+    }
+}
+
+fn store_file() -> PathBuf {
+    PathBuf::from(profile_store_file(&active_profile(), "json"))
+}
+
+/// ~/.cache/claw/ for the default profile, ~/.cache/claw/profiles/<name>/ otherwise
+fn profile_history_dir(profile: &str) -> PathBuf {
+    let base = dirs::cache_dir()
         .unwrap_or_else(|| PathBuf::from("/tmp"))
         .join("claw");
+    if profile == DEFAULT_PROFILE {
+        base
+    } else {
+        base.join("profiles").join(profile)
+    }
+}
+
+/// Profiles that have a content directory or a store file, default first
+pub fn list_profiles(app_handle: &AppHandle) -> Vec<String> {
+    use tauri::Manager;
+
+    let mut profiles = HashSet::new();
+    if let Ok(dirs) = fs::read_dir(profile_history_dir(DEFAULT_PROFILE).join("profiles")) {
+        profiles.extend(
+            dirs.flatten()
+                .filter(|d| d.path().is_dir())
+                .filter_map(|d| d.file_name().to_str().map(str::to_string)),
+        );
+    }
+    let store_files = app_handle.path().app_data_dir().ok().and_then(|dir| fs::read_dir(dir).ok());
+    if let Some(files) = store_files {
+        profiles.extend(files.flatten().filter_map(|f| {
+            let name = f.file_name().to_str()?.to_string();
+            let stem = name.strip_prefix("claw_history_")?;
+            let profile = stem
+                .strip_suffix(".json")
+                .or_else(|| stem.strip_suffix(".bin"))
+                .or_else(|| stem.strip_suffix(".index.jsonl"))?;
+            Some(profile.to_string())
+        }));
+    }
+    profiles.retain(|p| p != DEFAULT_PROFILE && validate_profile_name(p).is_ok());
+
+    let mut profiles: Vec<String> = profiles.into_iter().collect();
+    profiles.sort();
+    profiles.insert(0, DEFAULT_PROFILE.to_string());
+    profiles
+}
+
+pub fn create_profile(name: &str) -> Result<(), String> {
+    validate_profile_name(name)?;
+    if name == DEFAULT_PROFILE {
+        return Err("The default profile always exists".to_string());
+    }
+    fs::create_dir_all(profile_history_dir(name)).map_err(|e| format!("Failed to create profile: {}", e))
+}
+
+/// Remove a profile's store and cached content. The active and default profiles can't be deleted.
+pub fn delete_profile(app_handle: &AppHandle, name: &str) -> Result<(), String> {
+    use tauri::Manager;
+
+    validate_profile_name(name)?;
+    if name == DEFAULT_PROFILE {
+        return Err("The default profile can't be deleted".to_string());
+    }
+    if name == active_profile() {
+        return Err("Switch to another profile before deleting this one".to_string());
+    }
+
+    // Drop the plugin's cached copy so it isn't written back later
+    let json = profile_store_file(name, "json");
+    if let Ok(store) = StoreBuilder::new(app_handle, PathBuf::from(&json)).build() {
+        store.close_resource();
+    }
+    if let Ok(dir) = app_handle.path().app_data_dir() {
+        let _ = fs::remove_file(dir.join(&json));
+        let _ = fs::remove_file(dir.join(profile_store_file(name, "bin")));
+        let _ = fs::remove_file(dir.join(profile_store_file(name, "index.jsonl")));
+        let _ = fs::remove_file(dir.join(profile_store_file(name, "lock")));
+    }
+
+    let content_dir = profile_history_dir(name);
+    if content_dir.exists() {
+        fs::remove_dir_all(&content_dir).map_err(|e| format!("Failed to delete profile: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Content directory of the active profile (~/.cache/claw/ by default)
+fn get_history_dir() -> PathBuf {
+    let cache_dir = profile_history_dir(&active_profile());
     
     // Ensure directory exists
     if !cache_dir.exists() {
@@ -54,50 +345,180 @@ impl ClipboardHistory {
         Self {
             entries: VecDeque::new(),
             max_entries,
+            schema_version: HISTORY_SCHEMA_VERSION,
+            trash: Vec::new(),
+            dedup_ignore_whitespace: false,
+            dedup_images: true,
         }
     }
 
-    pub fn add_entry(&mut self, content: Vec<u8>, content_type: String, source_path: Option<String>) {
-        // Skip oversized entries
-        if content.len() > MAX_ENTRY_SIZE {
-            return;
-        }
+    /// Add content as the newest entry, unless it repeats the latest one
+    pub fn add_entry(
+        &mut self,
+        content: Vec<u8>,
+        content_type: String,
+        source_path: Option<String>,
+    ) -> AddOutcome {
+        // Oversized entries are skipped, cut or kept as `on_oversize` says
+        let (content, truncated) = match oversize_action(&content_type, content.len()) {
+            OversizeAction::Fits | OversizeAction::StoreAnyway => (content, false),
+            OversizeAction::Truncate => (truncate_text(content, MAX_ENTRY_SIZE), true),
+            OversizeAction::Drop => return AddOutcome::Rejected,
+        };
 
         if let Some(last) = self.entries.front() {
-            if last.content_size == content.len() {
-                if let Some(last_content) = self.get_entry_content_internal(&last.id) {
-                    if last_content == content {
-                        return;
-                    }
-                }
+            if self.is_duplicate_of(last, &content, &content_type) {
+                return AddOutcome::Duplicate;
             }
         }
 
         let content_size = content.len();
+        let frame_count = if content_type == "image/gif" {
+            gif_frame_count(&content)
+        } else {
+            None
+        };
         let entry = ClipboardEntry {
-            id: uuid::Uuid::new_v4().to_string(),
             content: content.clone(),
-            timestamp: Utc::now(),
-            content_type,
             source_path,
-            content_size,
+            frame_count,
+            truncated,
+            ..ClipboardEntry::new(uuid::Uuid::new_v4().to_string(), content_type, content_size)
         };
 
         if let Err(_) = self.save_entry_content(&entry) {
-            return;
+            return AddOutcome::Rejected;
         }
 
         let mut entry_for_memory = entry;
         entry_for_memory.content = Vec::new();
         entry_for_memory.content.shrink_to_fit();
 
+        let id = entry_for_memory.id.clone();
         self.entries.push_front(entry_for_memory);
+        self.evict_overflow();
+
+        AddOutcome::Inserted(id)
+    }
 
+    /// Evict the oldest unpinned entries beyond `max_entries` and the per-type
+    /// limits; pinned and auto-pinned ones never age out
+    fn evict_overflow(&mut self) {
+        let protected = self.auto_pinned_ids();
         while self.entries.len() > self.max_entries {
-            if let Some(old_entry) = self.entries.pop_back() {
+            let Some(pos) = self.entries.iter().rposition(|e| !e.pinned && !protected.contains(&e.id)) else {
+                break;
+            };
+            if let Some(old_entry) = self.entries.remove(pos) {
                 self.delete_entry_file(&old_entry.id);
+                log_deletion(&old_entry, "evicted");
             }
         }
+        self.enforce_type_limits();
+    }
+
+    /// Evict the oldest unpinned text and image entries beyond their own caps
+    /// (`max_text_entries`/`max_image_entries`), on top of `max_entries`
+    fn enforce_type_limits(&mut self) {
+        let protected = self.auto_pinned_ids();
+        let limits: [(usize, fn(&str) -> bool); 2] = [
+            (TEXT_ENTRY_LIMIT.load(Ordering::Relaxed), is_text_content_type),
+            (IMAGE_ENTRY_LIMIT.load(Ordering::Relaxed), |t| t.starts_with("image/")),
+        ];
+        for (limit, is_type) in limits {
+            if limit == 0 {
+                continue;
+            }
+            let of_type =
+                |e: &ClipboardEntry| !e.pinned && !protected.contains(&e.id) && is_type(&e.content_type);
+            while self.entries.iter().filter(|e| of_type(e)).count() > limit {
+                let Some(pos) = self.entries.iter().rposition(of_type) else {
+                    break;
+                };
+                if let Some(old_entry) = self.entries.remove(pos) {
+                    self.delete_entry_file(&old_entry.id);
+                    log_deletion(&old_entry, "evicted");
+                }
+            }
+        }
+    }
+
+    /// Unpinned entries kept from eviction by `clipboard.auto_pin_recent`: the
+    /// N most recently used. Manual pins are protected anyway and don't use a slot.
+    fn auto_pinned_ids(&self) -> HashSet<String> {
+        let limit = AUTO_PIN_RECENT.load(Ordering::Relaxed);
+        if limit == 0 {
+            return HashSet::new();
+        }
+        let mut used: Vec<&ClipboardEntry> = self
+            .entries
+            .iter()
+            .filter(|e| !e.pinned && e.last_used.is_some())
+            .collect();
+        used.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+        used.into_iter().take(limit).map(|e| e.id.clone()).collect()
+    }
+
+    /// Whether `content` repeats `last`: byte-equal, or for text in
+    /// whitespace-insensitive mode, equal once whitespace is collapsed
+    fn is_duplicate_of(&self, last: &ClipboardEntry, content: &[u8], content_type: &str) -> bool {
+        if !self.dedup_images && content_type.starts_with("image/") {
+            return false;
+        }
+
+        if self.dedup_ignore_whitespace
+            && is_text_content_type(content_type)
+            && is_text_content_type(&last.content_type)
+        {
+            return self
+                .get_entry_content_internal(&last.id)
+                .map(|last_content| {
+                    collapse_whitespace(&String::from_utf8_lossy(&last_content))
+                        == collapse_whitespace(&String::from_utf8_lossy(content))
+                })
+                .unwrap_or(false);
+        }
+
+        last.content_size == content.len()
+            && self
+                .get_entry_content_internal(&last.id)
+                .is_some_and(|last_content| last_content == content)
+    }
+
+    /// Keep `content` as a snippet: a pinned, labelled entry that never ages
+    /// out. Content already in history is promoted rather than copied.
+    /// Returns the snippet's entry id.
+    pub fn save_snippet(&mut self, content: Vec<u8>, content_type: String, label: &str) -> Result<String, String> {
+        let existing = self.find_by_content(&content).map(|e| e.id.clone());
+        let id = match existing {
+            Some(id) => id,
+            None => match self.add_entry(content, content_type, None) {
+                AddOutcome::Inserted(id) => id,
+                // Equal to the latest entry once whitespace is ignored
+                AddOutcome::Duplicate => self
+                    .entries
+                    .front()
+                    .map(|e| e.id.clone())
+                    .ok_or_else(|| "Failed to store snippet content".to_string())?,
+                AddOutcome::Rejected => return Err("Failed to store snippet content".to_string()),
+            },
+        };
+
+        self.set_pinned(&id, true);
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == id) {
+            entry.label = Some(label.to_string());
+        }
+        Ok(id)
+    }
+
+    /// Newest entry whose stored content equals `content`
+    pub fn find_by_content(&self, content: &[u8]) -> Option<&ClipboardEntry> {
+        self.entries.iter().find(|e| {
+            e.content_size == content.len()
+                && self
+                    .get_entry_content_internal(&e.id)
+                    .is_some_and(|stored| stored == content)
+        })
     }
 
     fn save_entry_content(&self, entry: &ClipboardEntry) -> std::io::Result<()> {
@@ -108,15 +529,181 @@ impl ClipboardHistory {
         Ok(())
     }
 
+    /// Hex SHA-256 of an entry's stored content, read from disk in chunks so
+    /// large entries aren't loaded whole
+    pub fn entry_checksum(&self, id: &str) -> Result<String, String> {
+        use sha2::{Digest, Sha256};
+        use std::io::Read;
+
+        if !self.entries.iter().any(|e| e.id == id) {
+            return Err("Entry not found".to_string());
+        }
+        let mut file = fs::File::open(self.get_entry_path(id))
+            .map_err(|e| format!("Entry content could not be read: {}", e))?;
+
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = file
+                .read(&mut buf)
+                .map_err(|e| format!("Entry content could not be read: {}", e))?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+
+    /// Readable dump of the history (`markdown` or plain text), oldest first.
+    /// Text matching `redact_patterns` is left out, images and binary get a
+    /// placeholder line and long text is cut at `EXPORT_TEXT_LIMIT` bytes.
+    /// Returns the document and how many entries it holds.
+    pub fn export_text(&self, markdown: bool, timestamp_format: &str, timezone: &str) -> (String, usize) {
+        let mut out = String::new();
+        let mut written = 0;
+
+        for entry in self.entries.iter().rev() {
+            let time = crate::utils::format_timestamp(&entry.timestamp, timestamp_format, timezone);
+            let heading = match &entry.label {
+                Some(label) => format!("{} — {}", time, label),
+                None => time,
+            };
+
+            let body = if is_text_content_type(&entry.content_type) {
+                let Ok(content) = Self::load_entry_content_from_disk(&entry.id) else {
+                    continue;
+                };
+                let text = String::from_utf8_lossy(&content);
+                if crate::preview::is_sensitive(&text) {
+                    continue;
+                }
+                let mut cut = text.len().min(EXPORT_TEXT_LIMIT);
+                while !text.is_char_boundary(cut) {
+                    cut -= 1;
+                }
+                let mut body = text[..cut].trim_end_matches('\n').to_string();
+                if cut < text.len() {
+                    body.push_str(&format!(
+                        "\n[… truncated, {} total]",
+                        crate::preview::human_size_from_bytes(entry.content_size)
+                    ));
+                }
+                if markdown {
+                    // A fence longer than any backtick run inside keeps the block closed
+                    let longest = body
+                        .split(|c| c != '`')
+                        .map(str::len)
+                        .max()
+                        .unwrap_or(0);
+                    let fence = "`".repeat(longest.max(2) + 1);
+                    format!("{}\n{}\n{}", fence, body, fence)
+                } else {
+                    body
+                }
+            } else {
+                let mut line = format!(
+                    "[{}, {}",
+                    entry.content_type,
+                    crate::preview::human_size_from_bytes(entry.content_size)
+                );
+                if let Some(source) = &entry.source_path {
+                    line.push_str(&format!(", from {}", source));
+                }
+                line.push(']');
+                if markdown {
+                    format!("_{}_", line)
+                } else {
+                    line
+                }
+            };
+
+            if markdown {
+                out.push_str(&format!("## {}\n\n{}\n\n", heading, body));
+            } else {
+                out.push_str(&format!("[{}]\n{}\n\n", heading, body));
+            }
+            written += 1;
+        }
+
+        if markdown {
+            out.insert_str(0, "# Clipboard history\n\n");
+        }
+        (out, written)
+    }
+
+    /// Dimensions and format of an image entry, read from the image header only.
+    /// The dimensions are cached on the entry; the bool is true when they were
+    /// just computed and the history needs saving.
+    pub fn image_info(&mut self, id: &str) -> Result<(ImageInfo, bool), String> {
+        let path = self.get_entry_path(id);
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .ok_or_else(|| "Entry not found".to_string())?;
+        let Some(subtype) = entry.content_type.strip_prefix("image/") else {
+            return Err(format!("Entry is not an image ({})", entry.content_type));
+        };
+        let format = subtype.to_uppercase();
+
+        let (computed, (width, height)) = match entry.dimensions {
+            Some(dimensions) => (false, dimensions),
+            None => {
+                let dimensions = image::ImageReader::open(&path)
+                    .and_then(|reader| reader.with_guessed_format())
+                    .map_err(|e| format!("Entry content could not be read: {}", e))?
+                    .into_dimensions()
+                    .map_err(|e| format!("Unreadable image: {}", e))?;
+                entry.dimensions = Some(dimensions);
+                (true, dimensions)
+            }
+        };
+
+        let info = ImageInfo {
+            width,
+            height,
+            format,
+            size_bytes: entry.content_size,
+        };
+        Ok((info, computed))
+    }
+
     fn get_entry_path(&self, id: &str) -> PathBuf {
         get_history_dir().join(format!("{}.bin", id))
     }
 
     fn delete_entry_file(&self, id: &str) {
-        let path = self.get_entry_path(id);
+        Self::delete_entry_file_by_id(id);
+    }
+
+    fn delete_entry_file_by_id(id: &str) {
+        let path = get_history_dir().join(format!("{}.bin", id));
         if path.exists() {
             let _ = fs::remove_file(path);
         }
+        let _ = fs::remove_file(get_history_dir().join(format!("{}.color", id)));
+    }
+
+    /// Dominant color of an image entry as `#rrggbb`, cached in `<id>.color`.
+    /// Images that fail to decode get `NEUTRAL_COLOR` (not cached).
+    pub fn dominant_color(&self, id: &str) -> String {
+        let cache = get_history_dir().join(format!("{}.color", id));
+        if let Ok(color) = fs::read_to_string(&cache) {
+            return color;
+        }
+
+        match self.get_entry_content_internal(id).and_then(|c| dominant_color(&c)) {
+            Some(color) => {
+                let _ = fs::write(&cache, &color);
+                color
+            }
+            None => NEUTRAL_COLOR.to_string(),
+        }
     }
 
     fn load_entry_content_from_disk(entry_id: &str) -> std::io::Result<Vec<u8>> {
@@ -130,7 +717,9 @@ impl ClipboardHistory {
 
     pub fn remove_entry(&mut self, id: &str) -> bool {
         if let Some(pos) = self.entries.iter().position(|entry| entry.id == id) {
-            self.entries.remove(pos);
+            if let Some(entry) = self.entries.remove(pos) {
+                log_deletion(&entry, "deleted");
+            }
             self.delete_entry_file(id);
             true
         } else {
@@ -138,92 +727,2080 @@ impl ClipboardHistory {
         }
     }
 
+    /// Move an entry to the trash, keeping its content for `restore_entry`
+    pub fn trash_entry(&mut self, id: &str) -> bool {
+        let Some(position) = self.entries.iter().position(|entry| entry.id == id) else {
+            return false;
+        };
+        if let Some(entry) = self.entries.remove(position) {
+            log_deletion(&entry, "trashed");
+            self.trash.push(TrashedEntry {
+                entry,
+                deleted_at: Utc::now(),
+                position,
+            });
+        }
+        true
+    }
+
+    /// Put a soft-deleted entry back where it was
+    pub fn restore_entry(&mut self, id: &str) -> Result<(), String> {
+        let idx = self
+            .trash
+            .iter()
+            .position(|t| t.entry.id == id)
+            .ok_or_else(|| "Entry is not in the trash (it may have been purged)".to_string())?;
+        let trashed = self.trash.remove(idx);
+        if !self.get_entry_path(id).exists() {
+            return Err("Entry content is gone".to_string());
+        }
+
+        let position = trashed.position.min(self.entries.len());
+        self.entries.insert(position, trashed.entry);
+        Ok(())
+    }
+
+    /// Permanently delete trashed entries past the grace period
+    pub fn purge_trash(&mut self) -> usize {
+        let cutoff = Utc::now() - chrono::Duration::seconds(TRASH_GRACE_SECS);
+        let before = self.trash.len();
+        let (expired, kept): (Vec<_>, Vec<_>) = self.trash.drain(..).partition(|t| t.deleted_at <= cutoff);
+
+        for trashed in &expired {
+            self.delete_entry_file(&trashed.entry.id);
+            log_deletion(&trashed.entry, "purged");
+        }
+        self.trash = kept;
+
+        before - self.trash.len()
+    }
+
     pub fn clear(&mut self) {
         for entry in &self.entries {
             self.delete_entry_file(&entry.id);
+            log_deletion(entry, "cleared");
         }
         self.entries.clear();
         self.entries.shrink_to_fit();
     }
 
-    fn get_entry_content_internal(&self, id: &str) -> Option<Vec<u8>> {
-        Self::load_entry_content_from_disk(id).ok()
+    /// Remove every entry that isn't pinned, keeping pinned ones in order
+    pub fn clear_unpinned(&mut self) -> usize {
+        let before = self.entries.len();
+        let (pinned, unpinned): (VecDeque<_>, VecDeque<_>) =
+            self.entries.drain(..).partition(|e| e.pinned);
+
+        for entry in &unpinned {
+            self.delete_entry_file(&entry.id);
+            log_deletion(entry, "cleared");
+        }
+        self.entries = pinned;
+
+        before - self.entries.len()
     }
 
-    pub fn get_entry_content(&self, id: &str) -> Option<Vec<u8>> {
-        if !self.entries.iter().any(|e| e.id == id) {
-            return None;
+    /// Drop entries whose content duplicates another one, keeping the newest
+    /// copy of each. Pinned entries are never removed: where a copy is pinned,
+    /// only the unpinned ones go. Duplicates are those `find_duplicates`
+    /// confirms byte for byte; entries whose content can't be read are left
+    /// alone. Returns how many were removed.
+    pub fn dedupe(&mut self) -> usize {
+        let pinned: HashSet<&str> = self.entries.iter().filter(|e| e.pinned).map(|e| e.id.as_str()).collect();
+        let mut doomed: HashSet<String> = HashSet::new();
+        for ids in self.find_duplicates() {
+            let any_pinned = ids.iter().any(|id| pinned.contains(id.as_str()));
+            doomed.extend(
+                ids.iter()
+                    .enumerate()
+                    .filter(|(i, id)| !pinned.contains(id.as_str()) && (any_pinned || *i > 0))
+                    .map(|(_, id)| id.clone()),
+            );
         }
-        
-        Self::load_entry_content_from_disk(id).ok()
+
+        let before = self.entries.len();
+        let mut kept = VecDeque::with_capacity(before - doomed.len());
+        for entry in self.entries.drain(..) {
+            if doomed.contains(&entry.id) {
+                Self::delete_entry_file_by_id(&entry.id);
+                log_deletion(&entry, "deduped");
+            } else {
+                kept.push_back(entry);
+            }
+        }
+        self.entries = kept;
+
+        before - self.entries.len()
     }
 
-    pub fn get_entries(&self, limit: Option<usize>) -> Vec<ClipboardEntry> {
-        match limit {
-            Some(n) => self.entries.iter().take(n).cloned().collect(),
-            None => self.entries.iter().cloned().collect(),
+    /// Groups of entry ids (newest first) whose content is identical, for
+    /// offering cleanup without touching anything. Only entries sharing a type
+    /// and size are read and hashed, and hash matches are confirmed byte for byte.
+    pub fn find_duplicates(&self) -> Vec<Vec<String>> {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut by_size: HashMap<(&str, usize), Vec<&ClipboardEntry>> = HashMap::new();
+        for entry in &self.entries {
+            if !self.dedup_images && entry.content_type.starts_with("image/") {
+                continue;
+            }
+            by_size
+                .entry((entry.content_type.as_str(), entry.content_size))
+                .or_default()
+                .push(entry);
+        }
+
+        let mut groups = Vec::new();
+        for candidates in by_size.into_values().filter(|c| c.len() > 1) {
+            // (hash, content of the group's first entry, ids)
+            let mut found: Vec<(u64, Vec<u8>, Vec<String>)> = Vec::new();
+            for entry in candidates {
+                let Some(content) = self.get_entry_content_internal(&entry.id) else {
+                    continue;
+                };
+                let mut hasher = DefaultHasher::new();
+                content.hash(&mut hasher);
+                let hash = hasher.finish();
+
+                match found.iter_mut().find(|(h, first, _)| *h == hash && *first == content) {
+                    Some((_, _, ids)) => ids.push(entry.id.clone()),
+                    None => found.push((hash, content, vec![entry.id.clone()])),
+                }
+            }
+            groups.extend(found.into_iter().map(|(_, _, ids)| ids).filter(|ids| ids.len() > 1));
         }
+
+        // Most copies first, so the biggest wins are on top
+        let order: HashMap<&str, usize> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(idx, e)| (e.id.as_str(), idx))
+            .collect();
+        groups.sort_by_key(|ids| (std::cmp::Reverse(ids.len()), order.get(ids[0].as_str()).copied()));
+        groups
     }
-}
 
-const STORE_FILE: &str = "claw_history.json";
-const HISTORY_KEY: &str = "history";
+    /// Rewrite an entry's content in place, keeping its id and position.
+    /// Image entries can only be replaced by another decodable image.
+    pub fn replace_content(&mut self, id: &str, content: Vec<u8>) -> Result<(), String> {
+        if content.len() > MAX_ENTRY_SIZE {
+            return Err(format!("Content exceeds the {} byte entry limit", MAX_ENTRY_SIZE));
+        }
 
-pub fn load_history(
-    app_handle: &AppHandle,
-    max_entries: usize,
-) -> Result<ClipboardHistory, String> {
-    let store = StoreBuilder::new(app_handle, PathBuf::from(STORE_FILE))
-        .build()
-        .map_err(|e| format!("Failed to create store: {}", e))?;
+        let pos = self
+            .entries
+            .iter()
+            .position(|e| e.id == id)
+            .ok_or_else(|| "Entry not found".to_string())?;
+
+        let content_type = refine_content_type(&content, detect_content_type(&content));
+        let is_image = content_type.starts_with("image/");
+        if is_image && image::load_from_memory(&content).is_err() {
+            return Err("New content is not a valid image".to_string());
+        }
+        if self.entries[pos].content_type.starts_with("image/") && !is_image {
+            return Err("Image entries can only be replaced with an image".to_string());
+        }
+
+        fs::write(self.get_entry_path(id), &content)
+            .map_err(|e| format!("Failed to write entry: {}", e))?;
+        // Cached dominant color belongs to the old content
+        let _ = fs::remove_file(get_history_dir().join(format!("{}.color", id)));
+
+        let entry = &mut self.entries[pos];
+        entry.frame_count = if content_type == "image/gif" {
+            gif_frame_count(&content)
+        } else {
+            None
+        };
+        entry.content_size = content.len();
+        entry.content_type = content_type;
+        // Details of the captured content that the new content doesn't share
+        entry.downscaled = false;
+        entry.dimensions = None;
+        entry.truncated = false;
+        entry.mimes = Vec::new();
+        entry.source_encoding = None;
+        Ok(())
+    }
 
-    let mut history = match store.get(HISTORY_KEY) {
-        Some(value) => {
-            match serde_json::from_value::<ClipboardHistory>(value.clone()) {
-                Ok(h) => h,
-                Err(_) => ClipboardHistory::new(max_entries)
+    /// Set or clear (`None`) an entry's expiry time
+    pub fn set_expiry(&mut self, id: &str, expires_at: Option<DateTime<Utc>>) -> bool {
+        match self.entries.iter_mut().find(|e| e.id == id) {
+            Some(entry) => {
+                entry.expires_at = expires_at;
+                true
             }
+            None => false,
         }
-        None => ClipboardHistory::new(max_entries),
-    };
+    }
 
-    history.max_entries = max_entries;
-    
-    for entry in &mut history.entries {
-        entry.content = Vec::new();
-        entry.content.shrink_to_fit();
+    /// Override an entry's detected content type. Only `text`, `binary` and
+    /// `image/<subtype>` are accepted.
+    pub fn set_content_type(&mut self, id: &str, content_type: &str) -> Result<bool, String> {
+        let valid = match content_type.strip_prefix("image/") {
+            Some(subtype) => {
+                !subtype.is_empty()
+                    && subtype
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+            }
+            None => matches!(content_type, "text" | "binary"),
+        };
+        if !valid {
+            return Err(format!(
+                "Invalid content type \"{}\" (use text, binary or image/<type>)",
+                content_type
+            ));
+        }
+
+        match self.entries.iter_mut().find(|e| e.id == id) {
+            Some(entry) => {
+                entry.content_type = content_type.to_string();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
-    
-    Ok(history)
-}
 
-pub fn save_history(app_handle: &AppHandle, history: &ClipboardHistory) -> Result<(), String> {
-    let store = StoreBuilder::new(app_handle, PathBuf::from(STORE_FILE))
-        .build()
-        .map_err(|e| format!("Failed to create store: {}", e))?;
+    /// Drop entries whose individual expiry has passed
+    pub fn prune_expired(&mut self) -> usize {
+        let now = Utc::now();
+        let before = self.entries.len();
+        let (expired, kept): (VecDeque<_>, VecDeque<_>) = self
+            .entries
+            .drain(..)
+            .partition(|e| e.expires_at.is_some_and(|at| at <= now));
 
-    let value =
-        serde_json::to_value(history).map_err(|e| format!("Failed to serialize history: {}", e))?;
+        for entry in &expired {
+            self.delete_entry_file(&entry.id);
+            log_deletion(entry, "expired");
+        }
+        self.entries = kept;
 
-    store.set(HISTORY_KEY.to_string(), value);
-    store
-        .save()
-        .map_err(|e| format!("Failed to save store: {}", e))?;
+        before - self.entries.len()
+    }
 
-    Ok(())
-}
+    /// Ids of text entries matching `query` (case-insensitive), newest first.
+    /// Fuzzy matching accepts the query's characters in order with gaps.
+    pub fn search(&self, query: &str, fuzzy: bool) -> Vec<String> {
+        let needle = query.to_lowercase();
+        if needle.is_empty() {
+            return Vec::new();
+        }
 
-pub fn add_to_history(
-    app_handle: &AppHandle,
-    content: &[u8],
-    content_type: String,
-    max_entries: usize,
-    source_path: Option<String>
-) -> Result<(), String> {
-    let mut history = load_history(app_handle, max_entries)?;
-    history.add_entry(content.to_vec(), content_type, source_path);
-    save_history(app_handle, &history)?;
-    
-    drop(history);
-    Ok(())
+        self.entries
+            .iter()
+            .filter(|e| self.text_matches(e, &needle, fuzzy))
+            .map(|e| e.id.clone())
+            .collect()
+    }
+
+    /// Whether a text entry contains `needle` (already lowercased); images
+    /// and binary never match
+    fn text_matches(&self, entry: &ClipboardEntry, needle: &str, fuzzy: bool) -> bool {
+        if entry.content_type.starts_with("image/") || entry.content_type == "binary" {
+            return false;
+        }
+        let content = self.get_entry_content_internal(&entry.id).unwrap_or_default();
+        let haystack = String::from_utf8_lossy(&content).to_lowercase();
+        if fuzzy {
+            let mut chars = haystack.chars();
+            needle.chars().all(|c| chars.any(|h| h == c))
+        } else {
+            haystack.contains(needle)
+        }
+    }
+
+    /// Filter, sort and page history in one pass. Cheap metadata filters run
+    /// before the text search so content is only read for entries still in play.
+    pub fn query(&self, params: &QueryParams) -> Result<QueryResult, String> {
+        let needle = params
+            .search
+            .as_deref()
+            .map(str::to_lowercase)
+            .filter(|n| !n.is_empty());
+
+        let mut matched: Vec<&ClipboardEntry> = self
+            .display_order()
+            .into_iter()
+            .filter(|e| !params.pinned_only || e.pinned)
+            .filter(|e| {
+                params
+                    .content_type_filter
+                    .as_deref()
+                    .is_none_or(|prefix| e.content_type.starts_with(prefix))
+            })
+            .filter(|e| params.date_from.is_none_or(|from| e.timestamp >= from))
+            .filter(|e| params.date_to.is_none_or(|to| e.timestamp <= to))
+            .filter(|e| needle.as_deref().is_none_or(|n| self.text_matches(e, n, params.fuzzy)))
+            .collect();
+
+        // Stable sorts keep display order among ties
+        match params.order.as_deref().unwrap_or("display") {
+            "display" => {}
+            "newest" => matched.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)),
+            "oldest" => matched.sort_by_key(|e| e.timestamp),
+            "largest" => matched.sort_by(|a, b| b.content_size.cmp(&a.content_size)),
+            "smallest" => matched.sort_by_key(|e| e.content_size),
+            other => {
+                return Err(format!(
+                    "Unknown order '{}' (expected display, newest, oldest, largest or smallest)",
+                    other
+                ))
+            }
+        }
+
+        let total = matched.len();
+        let entries = matched
+            .into_iter()
+            .skip(params.offset)
+            .take(params.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        Ok(QueryResult { entries, total })
+    }
+
+    pub fn set_pinned(&mut self, id: &str, pinned: bool) -> bool {
+        match self.entries.iter_mut().find(|e| e.id == id) {
+            Some(entry) => {
+                if pinned && !entry.pinned {
+                    entry.use_count = 0;
+                }
+                if !pinned {
+                    entry.pin_order = None;
+                }
+                entry.pinned = pinned;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Give pinned entries the order of `ordered_ids`. Every id must be a
+    /// pinned entry; pins left out lose their manual position.
+    pub fn reorder_pins(&mut self, ordered_ids: &[String]) -> Result<(), String> {
+        let mut seen = HashSet::new();
+        for id in ordered_ids {
+            if !seen.insert(id.as_str()) {
+                return Err(format!("Entry {} is listed twice", id));
+            }
+            if !self.entries.iter().any(|e| e.id == *id && e.pinned) {
+                return Err(format!("Entry {} is not pinned", id));
+            }
+        }
+
+        for entry in self.entries.iter_mut().filter(|e| e.pinned) {
+            entry.pin_order = ordered_ids.iter().position(|id| *id == entry.id).map(|i| i as u32);
+        }
+        Ok(())
+    }
+
+    /// Note a copy-back of an entry: its last use time, plus the use count for
+    /// pinned ones. Returns true if the entry was found and changed.
+    pub fn record_use(&mut self, id: &str) -> bool {
+        match self.entries.iter_mut().find(|e| e.id == id) {
+            Some(entry) => {
+                entry.last_used = Some(Utc::now());
+                if entry.pinned {
+                    entry.use_count = entry.use_count.saturating_add(1);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pinned entries ordered by use count, most used first
+    pub fn top_pinned(&self, limit: usize) -> Vec<&ClipboardEntry> {
+        let mut pinned: Vec<&ClipboardEntry> = self.entries.iter().filter(|e| e.pinned).collect();
+        // Stable sort keeps newest-first order among equal counts
+        pinned.sort_by(|a, b| b.use_count.cmp(&a.use_count));
+        pinned.truncate(limit);
+        pinned
+    }
+
+    /// Average growth over the span of the current history and how long until
+    /// `max_entries` is reached at that rate. Nulls when there is too little
+    /// history (fewer than two entries or under an hour) to extrapolate from.
+    pub fn growth_estimate(&self) -> GrowthEstimate {
+        let empty = GrowthEstimate {
+            daily_avg_bytes: None,
+            daily_avg_entries: None,
+            projected_days_to_limit: None,
+        };
+
+        let (Some(oldest), Some(newest)) = (
+            self.entries.iter().map(|e| e.timestamp).min(),
+            self.entries.iter().map(|e| e.timestamp).max(),
+        ) else {
+            return empty;
+        };
+        let span_secs = (newest - oldest).num_seconds();
+        if self.entries.len() < 2 || span_secs < 3600 {
+            return empty;
+        }
+
+        let days = span_secs as f64 / 86_400.0;
+        let daily_avg_entries = self.entries.len() as f64 / days;
+        let total_bytes: usize = self.entries.iter().map(|e| e.content_size).sum();
+        let remaining = self.max_entries.saturating_sub(self.entries.len());
+
+        GrowthEstimate {
+            daily_avg_bytes: Some(total_bytes as f64 / days),
+            daily_avg_entries: Some(daily_avg_entries),
+            projected_days_to_limit: Some(remaining as f64 / daily_avg_entries),
+        }
+    }
+
+    pub fn reset_usage_stats(&mut self) {
+        for entry in self.entries.iter_mut() {
+            entry.use_count = 0;
+        }
+    }
+
+    fn get_entry_content_internal(&self, id: &str) -> Option<Vec<u8>> {
+        Self::load_entry_content_from_disk(id).ok()
+    }
+
+    pub fn get_entry_content(&self, id: &str) -> Option<Vec<u8>> {
+        if !self.entries.iter().any(|e| e.id == id) {
+            return None;
+        }
+        
+        Self::load_entry_content_from_disk(id).ok()
+    }
+
+    /// Entries in display order: manually ordered pins first by `pin_order`,
+    /// then everything else newest first
+    fn display_order(&self) -> Vec<&ClipboardEntry> {
+        let mut entries: Vec<&ClipboardEntry> = self.entries.iter().collect();
+        // Stable sort keeps recency order among the rest
+        entries.sort_by_key(|e| (e.pin_order.is_none(), e.pin_order));
+        entries
+    }
+
+    pub fn get_entries(&self, limit: Option<usize>) -> Vec<ClipboardEntry> {
+        let mut entries = self.display_order();
+        entries.truncate(limit.unwrap_or(usize::MAX));
+        entries.into_iter().cloned().collect()
+    }
+
+    /// Neighbour of an entry in display order, `up` (towards the top) or `down`;
+    /// None at either end
+    pub fn adjacent_entry(&self, id: &str, direction: &str) -> Result<Option<ClipboardEntry>, String> {
+        let entries = self.display_order();
+        let pos = entries
+            .iter()
+            .position(|e| e.id == id)
+            .ok_or_else(|| "Entry not found".to_string())?;
+        let neighbour = match direction {
+            "up" | "previous" => pos.checked_sub(1),
+            "down" | "next" => Some(pos + 1),
+            other => return Err(format!("Unknown direction '{}' (expected up or down)", other)),
+        };
+        Ok(neighbour.and_then(|i| entries.get(i)).map(|e| (*e).clone()))
+    }
+}
+
+const HISTORY_KEY: &str = "history";
+const LAST_SELECTED_KEY: &str = "last_selected";
+const DELETION_LOG_KEY: &str = "deletion_log";
+/// Most removals `get_deletion_log` remembers
+const DELETION_LOG_LEN: usize = 200;
+
+/// Metadata of a removed entry; its content is not kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletionRecord {
+    pub id: String,
+    pub content_type: String,
+    pub content_size: usize,
+    pub removed_at: DateTime<Utc>,
+    /// "deleted", "trashed", "purged", "evicted", "expired", "deduped" or "cleared"
+    pub reason: String,
+}
+
+/// Removals not yet written to the store; flushed with the next history save.
+/// Kept outside `ClipboardHistory` so the bincode layout stays unchanged.
+static PENDING_DELETIONS: Mutex<Vec<DeletionRecord>> = Mutex::new(Vec::new());
+
+fn log_deletion(entry: &ClipboardEntry, reason: &str) {
+    lock_recover(&PENDING_DELETIONS).push(DeletionRecord {
+        id: entry.id.clone(),
+        content_type: entry.content_type.clone(),
+        content_size: entry.content_size,
+        removed_at: Utc::now(),
+        reason: reason.to_string(),
+    });
+}
+
+/// Logged removals, newest first, including ones not saved yet
+pub fn deletion_log(app_handle: &AppHandle) -> Result<Vec<DeletionRecord>, String> {
+    let store = StoreBuilder::new(app_handle, store_file())
+        .build()
+        .map_err(|e| format!("Failed to create store: {}", e))?;
+
+    let pending = lock_recover(&PENDING_DELETIONS);
+    let stored: Vec<DeletionRecord> = store
+        .get(DELETION_LOG_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    Ok(pending.iter().rev().cloned().chain(stored).take(DELETION_LOG_LEN).collect())
+}
+
+/// Move pending removals into the store's log (the caller saves the store).
+/// Returns whether there was anything to add.
+fn stage_deletion_log(store: &tauri_plugin_store::Store<tauri::Wry>) -> bool {
+    let mut pending = lock_recover(&PENDING_DELETIONS);
+    if pending.is_empty() {
+        return false;
+    }
+    let stored: Vec<DeletionRecord> = store
+        .get(DELETION_LOG_KEY)
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    let log: Vec<DeletionRecord> = pending.drain(..).rev().chain(stored).take(DELETION_LOG_LEN).collect();
+    store.set(DELETION_LOG_KEY.to_string(), serde_json::json!(log));
+    true
+}
+
+/// History metadata in bincode (`store_format = "bincode"`) is kept next to the
+/// JSON store as `claw_history[_<profile>].bin`.
+/// Layout: `BINARY_STORE_MAGIC`, a layout version byte, then the
+/// bincode-encoded `ClipboardHistory`.
+const BINARY_STORE_MAGIC: &[u8; 7] = b"CLAWHST";
+
+/// bincode stores no field names, so this must be bumped whenever a field is
+/// added to `ClipboardEntry`. A store in any other layout is not read.
+const BINARY_LAYOUT_VERSION: u8 = 1;
+
+/// Decode a binary store, rejecting other layouts and trailing bytes so a
+/// mismatched file fails rather than misreading fields
+pub fn decode_binary_store(bytes: &[u8]) -> Option<ClipboardHistory> {
+    use bincode::Options;
+
+    let body = bytes
+        .strip_prefix(BINARY_STORE_MAGIC.as_slice())?
+        .strip_prefix(&[BINARY_LAYOUT_VERSION])?;
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize::<ClipboardHistory>(body)
+        .ok()
+        .filter(|h| h.schema_version == HISTORY_SCHEMA_VERSION)
+}
+
+/// `history` in the binary store layout
+pub fn encode_binary_store(history: &ClipboardHistory) -> Result<Vec<u8>, String> {
+    let body = bincode::serialize(history).map_err(|e| format!("Failed to serialize history: {}", e))?;
+
+    let mut bytes = Vec::with_capacity(BINARY_STORE_MAGIC.len() + 1 + body.len());
+    bytes.extend_from_slice(BINARY_STORE_MAGIC);
+    bytes.push(BINARY_LAYOUT_VERSION);
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+/// What to do with content over `MAX_ENTRY_SIZE` (`clipboard.on_oversize`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OversizePolicy {
+    Drop,
+    Truncate,
+    StoreAnyway,
+}
+
+static OVERSIZE_POLICY: Mutex<OversizePolicy> = Mutex::new(OversizePolicy::Drop);
+
+/// Unknown values keep the default, `drop`
+pub fn set_oversize_policy(policy: &str) {
+    let policy = match policy {
+        "truncate" => OversizePolicy::Truncate,
+        "store_anyway" => OversizePolicy::StoreAnyway,
+        _ => OversizePolicy::Drop,
+    };
+    *lock_recover(&OVERSIZE_POLICY) = policy;
+}
+
+/// How `add_entry` handles content of `size` bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OversizeAction {
+    Fits,
+    Drop,
+    Truncate,
+    StoreAnyway,
+}
+
+/// Only text is ever truncated; cutting an image or binary blob corrupts it,
+/// so those are dropped instead
+pub fn oversize_action(content_type: &str, size: usize) -> OversizeAction {
+    if size <= MAX_ENTRY_SIZE {
+        return OversizeAction::Fits;
+    }
+    match *lock_recover(&OVERSIZE_POLICY) {
+        OversizePolicy::StoreAnyway => OversizeAction::StoreAnyway,
+        OversizePolicy::Truncate if is_text_content_type(content_type) => OversizeAction::Truncate,
+        _ => OversizeAction::Drop,
+    }
+}
+
+/// First `max_bytes` of UTF-8 text, cut back to a char boundary
+fn truncate_text(mut content: Vec<u8>, max_bytes: usize) -> Vec<u8> {
+    let mut cut = max_bytes.min(content.len());
+    // Step back over continuation bytes (10xxxxxx) to the start of a char
+    while cut > 0 && cut < content.len() && (content[cut] & 0xC0) == 0x80 {
+        cut -= 1;
+    }
+    content.truncate(cut);
+    content
+}
+
+/// Save history as bincode instead of JSON (`clipboard.store_format`)
+static BINARY_STORE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_binary_store(enabled: bool) {
+    BINARY_STORE.store(enabled, Ordering::Relaxed);
+}
+
+/// Keep history as a line-per-entry index (`clipboard.low_memory`); takes
+/// precedence over `store_format`
+static LOW_MEMORY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_low_memory(enabled: bool) {
+    LOW_MEMORY.store(enabled, Ordering::Relaxed);
+}
+
+/// Where history metadata is kept on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StoreFormat {
+    Json,
+    Binary,
+    Index,
+}
+
+fn configured_store_format() -> StoreFormat {
+    if LOW_MEMORY.load(Ordering::Relaxed) {
+        StoreFormat::Index
+    } else if BINARY_STORE.load(Ordering::Relaxed) {
+        StoreFormat::Binary
+    } else {
+        StoreFormat::Json
+    }
+}
+
+/// Store file for `format` (JSON resolved the same way tauri-plugin-store does)
+fn store_path(app_handle: &AppHandle, format: StoreFormat) -> Option<PathBuf> {
+    use tauri::Manager;
+
+    let dir = app_handle.path().app_data_dir().ok()?;
+    Some(match format {
+        StoreFormat::Json => dir.join(store_file()),
+        StoreFormat::Binary => dir.join(profile_store_file(&active_profile(), "bin")),
+        StoreFormat::Index => dir.join(profile_store_file(&active_profile(), "index.jsonl")),
+    })
+}
+
+/// Binary or index store files left over from a format other than `keep`
+fn stale_store_files(app_handle: &AppHandle, keep: StoreFormat) -> Vec<PathBuf> {
+    [StoreFormat::Binary, StoreFormat::Index]
+        .into_iter()
+        .filter(|format| *format != keep)
+        .filter_map(|format| store_path(app_handle, format))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+fn binary_store_path(app_handle: &AppHandle) -> Option<PathBuf> {
+    store_path(app_handle, StoreFormat::Binary)
+}
+
+/// `clipboard.lock_history`: history is read-only. Commands and the watcher
+/// check this before recording or changing anything.
+static HISTORY_LOCKED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_history_locked(locked: bool) {
+    HISTORY_LOCKED.store(locked, Ordering::Relaxed);
+}
+
+pub fn history_locked() -> bool {
+    HISTORY_LOCKED.load(Ordering::Relaxed)
+}
+
+/// Per-type caps from `clipboard.max_text_entries`/`max_image_entries`; 0 is unset
+static TEXT_ENTRY_LIMIT: AtomicUsize = AtomicUsize::new(0);
+static IMAGE_ENTRY_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_type_limits(max_text_entries: usize, max_image_entries: usize) {
+    TEXT_ENTRY_LIMIT.store(max_text_entries, Ordering::Relaxed);
+    IMAGE_ENTRY_LIMIT.store(max_image_entries, Ordering::Relaxed);
+}
+
+/// `clipboard.auto_pin_recent`; 0 is off
+static AUTO_PIN_RECENT: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_auto_pin_recent(count: usize) {
+    AUTO_PIN_RECENT.store(count, Ordering::Relaxed);
+}
+
+/// Entry ids this process has loaded or saved. On save, stored entries outside
+/// this set were added by another claw instance and are merged in; ones inside
+/// it but missing from the history being saved were deleted here.
+static KNOWN_IDS: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+fn remember_ids(history: &ClipboardHistory) {
+    *lock_recover(&KNOWN_IDS) = entry_ids(history);
+}
+
+/// Ids of every entry in `history`, trashed ones included
+fn entry_ids(history: &ClipboardHistory) -> HashSet<String> {
+    history
+        .entries
+        .iter()
+        .map(|e| e.id.clone())
+        .chain(history.trash.iter().map(|t| t.entry.id.clone()))
+        .collect()
+}
+
+/// Advisory lock on the history store, held while it is read or written so two
+/// claw instances (nested sessions, a racing restart) can't interleave. Released
+/// on drop.
+struct StoreLock(fs::File);
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.0);
+    }
+}
+
+fn lock_store(app_handle: &AppHandle) -> Result<StoreLock, String> {
+    use tauri::Manager;
+
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("No app data directory: {}", e))?;
+    let _ = fs::create_dir_all(&dir);
+    lock_store_at(&dir.join(profile_store_file(&active_profile(), "lock")))
+}
+
+fn lock_store_at(path: &std::path::Path) -> Result<StoreLock, String> {
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open history lock: {}", e))?;
+    file.lock_exclusive()
+        .map_err(|e| format!("Failed to lock history: {}", e))?;
+    Ok(StoreLock(file))
+}
+
+/// History from the bincode store, if there is one. An unreadable file is moved aside to `<store>.bin.bak` rather
+/// than silently overwritten.
+fn read_binary_store(app_handle: &AppHandle) -> Option<ClipboardHistory> {
+    let path = binary_store_path(app_handle)?;
+    let bytes = fs::read(&path).ok()?;
+
+    let decoded = decode_binary_store(&bytes);
+    if decoded.is_none() {
+        record_error(format!("Unreadable binary history store {}; moved aside", path.display()));
+        let _ = fs::rename(&path, path.with_extension("bin.bak"));
+    }
+    decoded
+}
+
+fn write_binary_store(app_handle: &AppHandle, history: &ClipboardHistory) -> Result<(), String> {
+    let path = binary_store_path(app_handle).ok_or_else(|| "No app data directory".to_string())?;
+    let bytes = encode_binary_store(history)?;
+
+    // Write then rename so a crash never leaves a half-written store
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let tmp = path.with_extension("bin.tmp");
+    fs::write(&tmp, &bytes).map_err(|e| format!("Failed to write history: {}", e))?;
+    fs::rename(&tmp, &path).map_err(|e| format!("Failed to save history: {}", e))
+}
+
+/// The low-memory index (`claw_history[_<profile>].index.jsonl`) keeps no
+/// copy of the history in the store plugin and is read a line at a time.
+/// Layout: a header line with the history's own fields and entry count, then
+/// one `[position, entry]` line per entry in display order, so a page of the
+/// list is the first lines of the file and a full read can restore the stored
+/// order from the positions.
+#[derive(Deserialize)]
+struct IndexHeader {
+    schema_version: u32,
+    max_entries: usize,
+    entries: usize,
+    #[serde(default)]
+    trash: Vec<TrashedEntry>,
+}
+
+/// Write `history` as a low-memory index at `path`
+pub fn write_index_at(path: &std::path::Path, history: &ClipboardHistory) -> Result<(), String> {
+    use std::io::Write;
+
+    let write_err = |e: std::io::Error| format!("Failed to write history: {}", e);
+    let serialize_err = |e: serde_json::Error| format!("Failed to serialize history: {}", e);
+
+    // Write then rename so a crash never leaves a half-written index
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    let mut out = std::io::BufWriter::new(fs::File::create(&tmp).map_err(write_err)?);
+
+    let header = serde_json::json!({
+        "schema_version": HISTORY_SCHEMA_VERSION,
+        "max_entries": history.max_entries,
+        "entries": history.entries.len(),
+        "trash": history.trash,
+    });
+    serde_json::to_writer(&mut out, &header).map_err(serialize_err)?;
+    out.write_all(b"\n").map_err(write_err)?;
+
+    // Same order as `display_order`
+    let mut order: Vec<usize> = (0..history.entries.len()).collect();
+    order.sort_by_key(|&pos| (history.entries[pos].pin_order.is_none(), history.entries[pos].pin_order));
+    for pos in order {
+        serde_json::to_writer(&mut out, &(pos, &history.entries[pos])).map_err(serialize_err)?;
+        out.write_all(b"\n").map_err(write_err)?;
+    }
+
+    out.flush().map_err(write_err)?;
+    drop(out);
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to save history: {}", e))
+}
+
+/// Lines of an index after its header, if the header is in the current schema
+fn index_lines<R: std::io::Read>(reader: R) -> Option<(IndexHeader, std::io::Lines<std::io::BufReader<R>>)> {
+    use std::io::BufRead;
+
+    let mut lines = std::io::BufReader::new(reader).lines();
+    let header: IndexHeader = serde_json::from_str(&lines.next()?.ok()?).ok()?;
+    (header.schema_version == HISTORY_SCHEMA_VERSION).then_some((header, lines))
+}
+
+/// The whole history from a low-memory index, parsed an entry at a time.
+/// None for an index in another schema, or one with a line unreadable,
+/// missing or repeated.
+pub fn read_index(reader: impl std::io::Read) -> Option<ClipboardHistory> {
+    let (header, lines) = index_lines(reader)?;
+
+    let mut slots: Vec<Option<ClipboardEntry>> = vec![None; header.entries];
+    for line in lines {
+        let (pos, entry): (usize, ClipboardEntry) = serde_json::from_str(&line.ok()?).ok()?;
+        if slots.get_mut(pos)?.replace(entry).is_some() {
+            return None;
+        }
+    }
+
+    Some(ClipboardHistory {
+        entries: slots.into_iter().collect::<Option<VecDeque<_>>>()?,
+        trash: header.trash,
+        ..ClipboardHistory::new(header.max_entries)
+    })
+}
+
+/// Up to `limit` entries from `offset` in display order, leaving out expired
+/// ones as a full load would. Reading stops once the window is filled.
+pub fn read_index_window(reader: impl std::io::Read, offset: usize, limit: usize) -> Option<Vec<ClipboardEntry>> {
+    let (_, lines) = index_lines(reader)?;
+
+    let now = Utc::now();
+    let mut skipped = 0;
+    let mut window = Vec::new();
+    for line in lines {
+        if window.len() >= limit {
+            break;
+        }
+        let (_, entry): (usize, ClipboardEntry) = serde_json::from_str(&line.ok()?).ok()?;
+        if entry.expires_at.is_some_and(|at| at <= now) {
+            continue;
+        }
+        if skipped < offset {
+            skipped += 1;
+            continue;
+        }
+        window.push(entry);
+    }
+    Some(window)
+}
+
+/// History from the low-memory index, if there is one. An unreadable file is
+/// moved aside to `<store>.index.jsonl.bak` rather than silently overwritten.
+fn read_index_store(app_handle: &AppHandle) -> Option<ClipboardHistory> {
+    let path = store_path(app_handle, StoreFormat::Index)?;
+    let file = fs::File::open(&path).ok()?;
+
+    let history = read_index(file);
+    if history.is_none() {
+        record_error(format!("Unreadable history index {}; moved aside", path.display()));
+        let _ = fs::rename(&path, path.with_extension("jsonl.bak"));
+    }
+    history
+}
+
+/// A page of history in display order, read from the low-memory index without
+/// loading the rest. None when low-memory mode is off or the index can't be
+/// used yet; callers then fall back to `load_history`, which also converts
+/// the store to an index.
+pub fn load_history_window(
+    app_handle: &AppHandle,
+    offset: usize,
+    limit: usize,
+) -> Result<Option<Vec<ClipboardEntry>>, String> {
+    if configured_store_format() != StoreFormat::Index {
+        return Ok(None);
+    }
+    let _lock = lock_store(app_handle)?;
+    let Some(file) = store_path(app_handle, StoreFormat::Index).and_then(|path| fs::File::open(path).ok()) else {
+        return Ok(None);
+    };
+    Ok(read_index_window(file, offset, limit))
+}
+
+pub fn load_history(
+    app_handle: &AppHandle,
+    max_entries: usize,
+) -> Result<ClipboardHistory, String> {
+    let _lock = lock_store(app_handle)?;
+    let store = StoreBuilder::new(app_handle, store_file())
+        .build()
+        .map_err(|e| format!("Failed to create store: {}", e))?;
+
+    // The format is detected from what is on disk (index, then bincode, then
+    // JSON); saving converts it to the configured one
+    let mut migrated = false;
+    let (stored, found) = match read_index_store(app_handle) {
+        Some(history) => (Some(history), StoreFormat::Index),
+        None => match read_binary_store(app_handle) {
+            Some(history) => (Some(history), StoreFormat::Binary),
+            None => {
+                let json = store.get(HISTORY_KEY).map(|value| {
+                    match serde_json::from_value::<ClipboardHistory>(value.clone()) {
+                        Ok(h) if h.schema_version == HISTORY_SCHEMA_VERSION => h,
+                        _ => {
+                            migrated = true;
+                            migrate_history(value, max_entries)
+                        }
+                    }
+                });
+                (json, StoreFormat::Json)
+            }
+        },
+    };
+    migrated |= stored.is_some() && found != configured_store_format();
+    let mut history = stored.unwrap_or_else(|| ClipboardHistory::new(max_entries));
+
+    history.max_entries = max_entries;
+    remember_ids(&history);
+    if migrated {
+        write_history_store(app_handle, &history).inspect_err(|e| record_error(e.clone()))?;
+    }
+    
+    for entry in &mut history.entries {
+        entry.content = Vec::new();
+        entry.content.shrink_to_fit();
+    }
+
+    // Locked history keeps even expired entries until it is unlocked
+    if !history_locked() && history.prune_expired() + history.purge_trash() > 0 {
+        write_history_store(app_handle, &history).inspect_err(|e| record_error(e.clone()))?;
+        remember_ids(&history);
+    }
+    
+    Ok(history)
+}
+
+/// Bring an older or partly unreadable stored history up to the current
+/// schema, keeping every entry that can be salvaged instead of starting fresh
+fn migrate_history(mut value: serde_json::Value, max_entries: usize) -> ClipboardHistory {
+    let from = value.get("schema_version").and_then(|v| v.as_u64()).unwrap_or(0);
+    let mut history = ClipboardHistory::new(max_entries);
+
+    let Some(entries) = value.get_mut("entries").and_then(|e| e.as_array_mut()) else {
+        eprintln!("History store v{} has no entries list; starting fresh", from);
+        return history;
+    };
+
+    let history_dir = get_history_dir();
+    let mut dropped = 0;
+    for raw in entries.iter_mut() {
+        let Some(fields) = raw.as_object_mut() else {
+            dropped += 1;
+            continue;
+        };
+        // Without an id the cached content can't be found again
+        let Some(id) = fields.get("id").and_then(|v| v.as_str()).map(str::to_string) else {
+            dropped += 1;
+            continue;
+        };
+
+        fields
+            .entry("timestamp")
+            .or_insert_with(|| serde_json::json!(Utc::now()));
+        fields.entry("content_type").or_insert_with(|| serde_json::json!("text"));
+        fields.entry("source_path").or_insert(serde_json::Value::Null);
+        fields.entry("pinned").or_insert(serde_json::Value::Bool(false));
+        if !fields.get("content_size").is_some_and(|v| v.is_u64()) {
+            let size = fs::metadata(history_dir.join(format!("{}.bin", id)))
+                .map(|m| m.len())
+                .unwrap_or(0);
+            fields.insert("content_size".to_string(), serde_json::json!(size));
+        }
+
+        match serde_json::from_value::<ClipboardEntry>(raw.take()) {
+            Ok(entry) => history.entries.push_back(entry),
+            Err(_) => dropped += 1,
+        }
+    }
+
+    eprintln!(
+        "Migrated history store from v{} to v{} ({} entries kept, {} dropped)",
+        from,
+        HISTORY_SCHEMA_VERSION,
+        history.entries.len(),
+        dropped
+    );
+    history
+}
+
+/// Schema version the history store was last written with, if it has one
+pub fn stored_schema_version(app_handle: &AppHandle) -> Result<Option<u32>, String> {
+    let header = store_path(app_handle, StoreFormat::Index)
+        .and_then(|path| fs::File::open(path).ok())
+        .and_then(index_lines);
+    if let Some((header, _)) = header {
+        return Ok(Some(header.schema_version));
+    }
+    if let Some(history) = read_binary_store(app_handle) {
+        return Ok(Some(history.schema_version));
+    }
+
+    let store = StoreBuilder::new(app_handle, store_file())
+        .build()
+        .map_err(|e| format!("Failed to create store: {}", e))?;
+
+    Ok(store
+        .get(HISTORY_KEY)
+        .and_then(|h| h.get("schema_version").and_then(|v| v.as_u64()))
+        .map(|v| v as u32))
+}
+
+/// Remember the entry last applied from the picker (None forgets it)
+pub fn set_last_selected(app_handle: &AppHandle, entry_id: Option<&str>) -> Result<(), String> {
+    let store = StoreBuilder::new(app_handle, store_file())
+        .build()
+        .map_err(|e| format!("Failed to create store: {}", e))?;
+
+    match entry_id {
+        Some(id) => store.set(LAST_SELECTED_KEY.to_string(), serde_json::Value::String(id.to_string())),
+        None => {
+            store.delete(LAST_SELECTED_KEY);
+        }
+    }
+    store
+        .save()
+        .map_err(|e| format!("Failed to save store: {}", e))
+}
+
+/// Last applied entry id, if that entry is still in `history`
+pub fn last_selected(app_handle: &AppHandle, history: &ClipboardHistory) -> Option<String> {
+    let store = StoreBuilder::new(app_handle, store_file()).build().ok()?;
+    let id = store.get(LAST_SELECTED_KEY)?.as_str()?.to_string();
+    history.entries.iter().any(|e| e.id == id).then_some(id)
+}
+
+/// Save `history`, first merging in entries another claw instance stored since
+/// this one last loaded, so neither overwrites the other's captures
+pub fn save_history(app_handle: &AppHandle, history: &ClipboardHistory) -> Result<(), String> {
+    let _lock = lock_store(app_handle)?;
+
+    let result = match foreign_entries(app_handle, history) {
+        foreign if foreign.is_empty() => {
+            write_history_store(app_handle, history).map(|_| remember_ids(history))
+        }
+        foreign => {
+            let merged = merge_entries(history, foreign);
+            write_history_store(app_handle, &merged).map(|_| remember_ids(&merged))
+        }
+    };
+    result.inspect_err(|e| record_error(e.clone()))
+}
+
+/// Stored entries this process has never seen, re-read from disk
+fn foreign_entries(app_handle: &AppHandle, history: &ClipboardHistory) -> Vec<ClipboardEntry> {
+    let stored: Vec<ClipboardEntry> = match read_index_store(app_handle).or_else(|| read_binary_store(app_handle)) {
+        Some(stored) => stored.entries.into(),
+        None => {
+            let Ok(store) = StoreBuilder::new(app_handle, store_file()).build() else {
+                return Vec::new();
+            };
+            // The plugin caches the file; another instance may have rewritten it
+            let _ = store.reload();
+            store
+                .get(HISTORY_KEY)
+                .and_then(|v| serde_json::from_value::<ClipboardHistory>(v).ok())
+                .map(|h| h.entries.into())
+                .unwrap_or_default()
+        }
+    };
+
+    let known = lock_recover(&KNOWN_IDS);
+    unseen_entries(stored, &known, history)
+}
+
+/// Entries of `stored` that are neither in `history` nor in `known`, the ids
+/// this process has seen (and so removed on purpose if they are gone now)
+fn unseen_entries(
+    stored: Vec<ClipboardEntry>,
+    known: &HashSet<String>,
+    history: &ClipboardHistory,
+) -> Vec<ClipboardEntry> {
+    let ours: HashSet<&str> = history.entries.iter().map(|e| e.id.as_str()).collect();
+    stored
+        .into_iter()
+        .filter(|e| !known.contains(&e.id) && !ours.contains(e.id.as_str()))
+        .collect()
+}
+
+/// `history` with `foreign` slotted in by timestamp, trimmed back to the overall
+/// and per-type limits
+fn merge_entries(history: &ClipboardHistory, foreign: Vec<ClipboardEntry>) -> ClipboardHistory {
+    let mut merged = ClipboardHistory {
+        entries: history.entries.clone(),
+        max_entries: history.max_entries,
+        schema_version: history.schema_version,
+        trash: history.trash.clone(),
+        dedup_ignore_whitespace: history.dedup_ignore_whitespace,
+        dedup_images: history.dedup_images,
+    };
+
+    for entry in foreign {
+        let pos = merged
+            .entries
+            .iter()
+            .position(|e| e.timestamp < entry.timestamp)
+            .unwrap_or(merged.entries.len());
+        merged.entries.insert(pos, entry);
+    }
+
+    merged.evict_overflow();
+    merged
+}
+
+fn write_history_store(app_handle: &AppHandle, history: &ClipboardHistory) -> Result<(), String> {
+    let store = StoreBuilder::new(app_handle, store_file())
+        .build()
+        .map_err(|e| format!("Failed to create store: {}", e))?;
+
+    let logged = stage_deletion_log(&store);
+
+    let format = configured_store_format();
+    if format != StoreFormat::Json {
+        let stale = stale_store_files(app_handle, format);
+        let read_back: fn(&AppHandle) -> Option<ClipboardHistory> = match format {
+            StoreFormat::Index => {
+                write_index_at(&store_path(app_handle, format).ok_or("No app data directory")?, history)?;
+                read_index_store
+            }
+            _ => {
+                write_binary_store(app_handle, history)?;
+                read_binary_store
+            }
+        };
+        // Converted from another format: the old copy is dropped only once
+        // the new one reads back, so a bad write can't lose the history
+        let converting = store.has(HISTORY_KEY) || !stale.is_empty();
+        let round_trip =
+            || read_back(app_handle).is_some_and(|stored| stored.entries.len() == history.entries.len());
+        if !converting || round_trip() {
+            for path in stale {
+                let _ = fs::remove_file(path);
+            }
+            if store.delete(HISTORY_KEY) || logged {
+                store
+                    .save()
+                    .map_err(|e| format!("Failed to save store: {}", e))?;
+            }
+            return Ok(());
+        }
+        record_error("History store did not read back; saving it as JSON instead".to_string());
+    }
+
+    let mut value =
+        serde_json::to_value(history).map_err(|e| format!("Failed to serialize history: {}", e))?;
+    value["schema_version"] = serde_json::json!(HISTORY_SCHEMA_VERSION);
+
+    store.set(HISTORY_KEY.to_string(), value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save store: {}", e))?;
+
+    // Converted from bincode or an index, which would otherwise win on load
+    for path in stale_store_files(app_handle, StoreFormat::Json) {
+        let _ = fs::remove_file(path);
+    }
+
+    Ok(())
+}
+
+/// A text entry that is nothing but a URL, or that looks like code or markup,
+/// gets a more specific type
+pub(crate) fn refine_content_type(content: &[u8], content_type: String) -> String {
+    match std::str::from_utf8(content) {
+        Ok(text) if content_type == "text" && is_url(text) => "text/uri".to_string(),
+        Ok(text) if content_type == "text" => detect_text_subtype(text).unwrap_or(content_type),
+        _ => content_type,
+    }
+}
+
+/// Where a new history entry came from
+#[derive(Debug, Clone, Default)]
+pub struct EntrySource {
+    pub path: Option<String>,
+    pub app: Option<String>,
+    /// Encoding the text was transcoded from, if it wasn't UTF-8
+    pub encoding: Option<String>,
+    /// MIME types the owner offered alongside the one read
+    pub mimes: Vec<String>,
+}
+
+pub fn add_to_history(
+    app_handle: &AppHandle,
+    content: &[u8],
+    content_type: String,
+    max_entries: usize,
+    source: EntrySource,
+    dedup_ignore_whitespace: bool,
+    dedup_images: bool,
+) -> Result<(), String> {
+    let content_type = refine_content_type(content, content_type);
+
+    let action = oversize_action(&content_type, content.len());
+    let oversize = OversizeEvent {
+        content_type: content_type.clone(),
+        size: content.len(),
+        limit: MAX_ENTRY_SIZE,
+    };
+
+    let mut history = load_history(app_handle, max_entries)?;
+    history.dedup_ignore_whitespace = dedup_ignore_whitespace;
+    history.dedup_images = dedup_images;
+    if let AddOutcome::Inserted(id) = history.add_entry(content.to_vec(), content_type, source.path) {
+        if let Some(entry) = history.entries.iter_mut().find(|e| e.id == id) {
+            entry.source_app = source.app;
+            entry.source_encoding = source.encoding;
+            entry.mimes = source.mimes;
+        }
+    }
+    save_history(app_handle, &history)?;
+    
+    drop(history);
+
+    // Let the frontend tell the user their copy didn't go in whole
+    match action {
+        OversizeAction::Drop => {
+            let _ = app_handle.emit("history-entry-skipped", oversize);
+        }
+        OversizeAction::Truncate => {
+            let _ = app_handle.emit("history-entry-truncated", oversize);
+        }
+        OversizeAction::Fits | OversizeAction::StoreAnyway => {}
+    }
+    Ok(())
+}
+
+/// Payload of `history-entry-skipped` and `history-entry-truncated`
+#[derive(Debug, Clone, Serialize)]
+pub struct OversizeEvent {
+    pub content_type: String,
+    pub size: usize,
+    pub limit: usize,
+}
+
+/// Load, dedupe and save history in one go. Does nothing while history is locked.
+pub fn dedupe_history(app_handle: &AppHandle, max_entries: usize, dedup_images: bool) -> Result<usize, String> {
+    if history_locked() {
+        return Ok(0);
+    }
+    let mut history = load_history(app_handle, max_entries)?;
+    history.dedup_images = dedup_images;
+    let removed = history.dedupe();
+    if removed > 0 {
+        save_history(app_handle, &history)?;
+    }
+    Ok(removed)
+}
+
+/// Re-add `.bin` files in the history directory that the index doesn't know
+/// about. Timestamps come from file mtimes; entries are flagged `recovered`.
+/// Does nothing while history is locked.
+pub fn rebuild_index_from_disk(app_handle: &AppHandle, max_entries: usize) -> Result<usize, String> {
+    if history_locked() {
+        return Ok(0);
+    }
+    let mut history = load_history(app_handle, max_entries)?;
+    let known: HashSet<String> = history
+        .entries
+        .iter()
+        .chain(history.trash.iter().map(|t| &t.entry))
+        .map(|e| e.id.clone())
+        .collect();
+
+    let dir = fs::read_dir(get_history_dir()).map_err(|e| format!("Failed to read history dir: {}", e))?;
+    let mut recovered = Vec::new();
+
+    for file in dir.flatten() {
+        let path = file.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("bin") {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else {
+            continue;
+        };
+        if known.contains(&id) {
+            continue;
+        }
+
+        let Ok(content) = fs::read(&path) else { continue };
+        if content.is_empty() {
+            continue;
+        }
+
+        let timestamp = file
+            .metadata()
+            .and_then(|m| m.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+        let content_type = refine_content_type(&content, detect_content_type(&content));
+        let frame_count = if content_type == "image/gif" {
+            gif_frame_count(&content)
+        } else {
+            None
+        };
+
+        recovered.push(ClipboardEntry {
+            timestamp,
+            frame_count,
+            recovered: true,
+            ..ClipboardEntry::new(id, content_type, content.len())
+        });
+    }
+
+    let count = recovered.len();
+    if count == 0 {
+        return Ok(0);
+    }
+
+    history.entries.extend(recovered);
+    history.entries.make_contiguous().sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+    // Over the limit: evict as a capture would, sparing pinned entries
+    history.evict_overflow();
+
+    save_history(app_handle, &history)?;
+    Ok(count)
+}
+
+/// Record a downscaled image and flag the entry so the UI knows it isn't the original
+pub fn add_downscaled_image_to_history(
+    app_handle: &AppHandle,
+    content: &[u8],
+    content_type: String,
+    max_entries: usize,
+    source_app: Option<String>,
+    dedup_images: bool,
+) -> Result<(), String> {
+    let mut history = load_history(app_handle, max_entries)?;
+    history.dedup_images = dedup_images;
+    if let AddOutcome::Inserted(id) = history.add_entry(content.to_vec(), content_type, None) {
+        if let Some(entry) = history.entries.iter_mut().find(|e| e.id == id) {
+            entry.downscaled = true;
+            entry.source_app = source_app;
+        }
+    }
+    save_history(app_handle, &history)?;
+
+    drop(history);
+    Ok(())
+}
+
+/// Entry ids grouped by source application, newest first within each group.
+/// Entries without a recorded app go under "unknown".
+pub fn group_by_app(history: &ClipboardHistory) -> HashMap<String, Vec<String>> {
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in &history.entries {
+        let app = entry.source_app.clone().unwrap_or_else(|| "unknown".to_string());
+        groups.entry(app).or_default().push(entry.id.clone());
+    }
+    groups
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrowthEstimate {
+    pub daily_avg_bytes: Option<f64>,
+    pub daily_avg_entries: Option<f64>,
+    /// 0 once history is full (older entries are already being evicted)
+    pub projected_days_to_limit: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryHealth {
+    pub history_dir: String,
+    pub dir_writable: bool,
+    pub store_path: Option<String>,
+    pub store_exists: bool,
+    pub store_parses: bool,
+    pub round_trip_ok: bool,
+    pub errors: Vec<String>,
+}
+
+/// Check that history can actually be persisted: the content directory is
+/// writable, the store file parses in the configured format, and an entry
+/// survives a write/read.
+pub fn health_check(app_handle: &AppHandle) -> HistoryHealth {
+    let mut errors = Vec::new();
+    let history_dir = get_history_dir();
+
+    // Content directory
+    let probe = history_dir.join(format!(".health-{}", uuid::Uuid::new_v4()));
+    let dir_writable = match fs::write(&probe, b"claw") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(e) => {
+            errors.push(format!("History directory not writable: {}", e));
+            false
+        }
+    };
+
+    // Store file in the configured format
+    let format = configured_store_format();
+    let path = store_path(app_handle, format);
+    let store_exists = path.as_ref().map(|p| p.exists()).unwrap_or(false);
+    let store_parses = match path.as_ref().filter(|_| store_exists) {
+        Some(path) if format == StoreFormat::Binary => match fs::read(path) {
+            Ok(bytes) if decode_binary_store(&bytes).is_some() => true,
+            Ok(_) => {
+                errors.push("Store file is not a readable binary history".to_string());
+                false
+            }
+            Err(e) => {
+                errors.push(format!("Store file unreadable: {}", e));
+                false
+            }
+        },
+        Some(path) if format == StoreFormat::Index => match fs::File::open(path) {
+            Ok(file) if read_index(file).is_some() => true,
+            Ok(_) => {
+                errors.push("Store file is not a readable history index".to_string());
+                false
+            }
+            Err(e) => {
+                errors.push(format!("Store file unreadable: {}", e));
+                false
+            }
+        },
+        Some(path) => match fs::read_to_string(path).map(|s| serde_json::from_str::<serde_json::Value>(&s)) {
+            Ok(Ok(_)) => true,
+            Ok(Err(e)) => {
+                errors.push(format!("Store file is not valid JSON: {}", e));
+                false
+            }
+            Err(e) => {
+                errors.push(format!("Store file unreadable: {}", e));
+                false
+            }
+        },
+        None => {
+            errors.push("Store file does not exist yet".to_string());
+            false
+        }
+    };
+
+    // Round trip an entry through the same code path history uses
+    let probe_entry = ClipboardEntry {
+        content: b"claw".to_vec(),
+        ..ClipboardEntry::new(format!("health-{}", uuid::Uuid::new_v4()), "text".to_string(), 4)
+    };
+    let scratch = ClipboardHistory::new(1);
+    let round_trip_ok = match scratch.save_entry_content(&probe_entry) {
+        Ok(()) => {
+            let read_back = ClipboardHistory::load_entry_content_from_disk(&probe_entry.id);
+            scratch.delete_entry_file(&probe_entry.id);
+            match read_back {
+                Ok(bytes) if bytes == probe_entry.content => true,
+                Ok(_) => {
+                    errors.push("Entry content changed on round trip".to_string());
+                    false
+                }
+                Err(e) => {
+                    errors.push(format!("Entry could not be read back: {}", e));
+                    false
+                }
+            }
+        }
+        Err(e) => {
+            errors.push(format!("Entry could not be written: {}", e));
+            false
+        }
+    };
+
+    HistoryHealth {
+        history_dir: history_dir.to_string_lossy().to_string(),
+        dir_writable,
+        store_path: path.map(|p| p.to_string_lossy().to_string()),
+        store_exists,
+        store_parses,
+        round_trip_ok,
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{canonical_clipboard_bytes, clipboard_hash};
+
+    /// Serialize tests that touch entry files or the history statics, with
+    /// entry files kept in a scratch cache directory
+    fn setup() -> std::sync::MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+        static CACHE: std::sync::Once = std::sync::Once::new();
+        CACHE.call_once(|| {
+            let cache = std::env::temp_dir().join(format!("claw-test-{}", std::process::id()));
+            std::env::set_var("XDG_CACHE_HOME", cache);
+        });
+        lock_recover(&LOCK)
+    }
+
+    fn entry(id: &str, content_type: &str, content_size: usize) -> ClipboardEntry {
+        ClipboardEntry::new(id.to_string(), content_type.to_string(), content_size)
+    }
+
+    fn with_header(layout: u8, body: Vec<u8>) -> Vec<u8> {
+        let mut bytes = BINARY_STORE_MAGIC.to_vec();
+        bytes.push(layout);
+        bytes.extend(body);
+        bytes
+    }
+
+    #[test]
+    fn binary_store_round_trips_in_current_layout() {
+        let mut history = ClipboardHistory::new(10);
+        let mut labelled = entry("a", "text", 5);
+        labelled.label = Some("greeting".to_string());
+        labelled.mimes = vec!["text/plain".to_string()];
+        history.entries.push_back(labelled);
+        history.entries.push_back(entry("b", "image/png", 300));
+
+        let bytes = encode_binary_store(&history).unwrap();
+        let decoded = decode_binary_store(&bytes).unwrap();
+
+        assert_eq!(decoded.entries.len(), 2);
+        assert_eq!(decoded.entries[0].label.as_deref(), Some("greeting"));
+        assert_eq!(decoded.entries[0].mimes, vec!["text/plain".to_string()]);
+        assert_eq!(decoded.entries[1].content_type, "image/png");
+    }
+
+    #[test]
+    fn binary_store_rejects_unknown_layout() {
+        let history = ClipboardHistory::new(10);
+        let bytes = with_header(BINARY_LAYOUT_VERSION + 1, bincode::serialize(&history).unwrap());
+        assert!(decode_binary_store(&bytes).is_none());
+    }
+
+    /// `history` written as an index, in memory
+    fn index_bytes(history: &ClipboardHistory) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!("claw-index-{}.index.jsonl", uuid::Uuid::new_v4()));
+        write_index_at(&path, history).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        bytes
+    }
+
+    fn pinned_history() -> ClipboardHistory {
+        let mut history = ClipboardHistory::new(10);
+        for id in ["a", "b", "c", "d"] {
+            history.entries.push_back(entry(id, "text", 5));
+        }
+        history.entries[2].pinned = true;
+        history.entries[2].pin_order = Some(0);
+        history.entries[1].expires_at = Some(Utc::now() - chrono::Duration::seconds(1));
+        history
+    }
+
+    #[test]
+    fn index_round_trips_in_stored_order() {
+        let history = pinned_history();
+        let read = read_index(index_bytes(&history).as_slice()).unwrap();
+
+        let ids: Vec<&str> = read.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c", "d"]);
+        assert_eq!(read.entries[2].pin_order, Some(0));
+        assert_eq!(read.max_entries, 10);
+    }
+
+    #[test]
+    fn index_window_reads_display_order_without_expired_entries() {
+        let bytes = index_bytes(&pinned_history());
+
+        let ids = |offset, limit| -> Vec<String> {
+            read_index_window(bytes.as_slice(), offset, limit)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.id)
+                .collect()
+        };
+        assert_eq!(ids(0, 2), vec!["c", "a"]);
+        assert_eq!(ids(1, 10), vec!["a", "d"]);
+    }
+
+    #[test]
+    fn index_cut_short_is_unreadable() {
+        let bytes = index_bytes(&pinned_history());
+        let mut lines: Vec<&[u8]> = bytes.split(|b| *b == b'\n').collect();
+        lines.truncate(3);
+        assert!(read_index(lines.join(&b'\n').as_slice()).is_none());
+    }
+
+    #[test]
+    fn add_entry_dedups_identical_images_byte_for_byte() {
+        let _guard = setup();
+        // Trailing CRLF and NULs are what text normalization used to strip
+        let mut image = b"\x89PNG\r\n\x1a\n".to_vec();
+        image.extend([0x42; 200]);
+        image.extend_from_slice(b"\r\n\0\0");
+        assert_eq!(canonical_clipboard_bytes(&image), image);
+
+        let mut history = ClipboardHistory::new(10);
+        let first = history.add_entry(canonical_clipboard_bytes(&image), "image/png".to_string(), None);
+        assert!(matches!(first, AddOutcome::Inserted(_)));
+        let again = history.add_entry(canonical_clipboard_bytes(&image), "image/png".to_string(), None);
+        assert_eq!(again, AddOutcome::Duplicate);
+        assert_eq!(history.entries.len(), 1);
+
+        // What is read back hashes like what was captured, so keep-alive sees no change
+        let id = history.entries[0].id.clone();
+        let stored = history.get_entry_content(&id).unwrap();
+        assert_eq!(stored, image);
+        assert_eq!(clipboard_hash(&stored), clipboard_hash(&image));
+        history.delete_entry_file(&id);
+    }
+
+    #[test]
+    fn add_entry_keeps_repeated_images_without_dedup_images() {
+        let _guard = setup();
+        let mut image = b"\x89PNG\r\n\x1a\n".to_vec();
+        image.extend([0x17; 200]);
+
+        let mut history = ClipboardHistory::new(10);
+        history.dedup_images = false;
+        history.add_entry(image.clone(), "image/png".to_string(), None);
+        history.add_entry(image, "image/png".to_string(), None);
+        assert_eq!(history.entries.len(), 2);
+
+        for id in history.entries.iter().map(|e| e.id.clone()).collect::<Vec<_>>() {
+            history.delete_entry_file(&id);
+        }
+    }
+
+    /// Stored entries of the binary store at `store`, if any
+    fn stored_history(store: &std::path::Path) -> Option<ClipboardHistory> {
+        fs::read(store).ok().and_then(|bytes| decode_binary_store(&bytes))
+    }
+
+    /// `load_history` for one writer: read the store under the lock and note what it saw
+    fn load_as_writer(store: &std::path::Path, lock: &std::path::Path) -> (ClipboardHistory, HashSet<String>) {
+        let _lock = lock_store_at(lock).unwrap();
+        let history = stored_history(store).unwrap_or_else(|| ClipboardHistory::new(1000));
+        let known = entry_ids(&history);
+        (history, known)
+    }
+
+    /// `save_history` for one writer: merge in entries it never saw, then write
+    fn save_as_writer(
+        store: &std::path::Path,
+        lock: &std::path::Path,
+        history: &ClipboardHistory,
+        known: &HashSet<String>,
+    ) {
+        let _lock = lock_store_at(lock).unwrap();
+        let stored: Vec<ClipboardEntry> = stored_history(store).map(|h| h.entries.into()).unwrap_or_default();
+        let merged = merge_entries(history, unseen_entries(stored, known, history));
+
+        let tmp = store.with_extension("bin.tmp");
+        fs::write(&tmp, encode_binary_store(&merged).unwrap()).unwrap();
+        fs::rename(&tmp, store).unwrap();
+    }
+
+    #[test]
+    fn merge_applies_per_type_limits() {
+        let _guard = setup();
+        set_type_limits(1, 0);
+        let mut history = ClipboardHistory::new(10);
+        history.entries.push_back(entry("merge-ours", "text", 1));
+        let mut theirs = entry("merge-theirs", "text", 1);
+        theirs.timestamp = history.entries[0].timestamp + chrono::Duration::seconds(1);
+
+        let merged = merge_entries(&history, vec![theirs]);
+        set_type_limits(0, 0);
+        let ids: Vec<&str> = merged.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["merge-theirs"]);
+    }
+
+    #[test]
+    fn concurrent_saves_lose_no_entries() {
+        const PER_WRITER: usize = 25;
+        let _guard = setup();
+        let dir = std::env::temp_dir().join(format!("claw-test-store-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let store = dir.join("claw_history.bin");
+        let lock = dir.join("claw_history.lock");
+
+        // Each writer loads, records one capture and saves, racing the other
+        let writers: Vec<_> = (0..2)
+            .map(|writer| {
+                let (store, lock) = (store.clone(), lock.clone());
+                std::thread::spawn(move || {
+                    let mut ids = Vec::new();
+                    for i in 0..PER_WRITER {
+                        let (mut history, known) = load_as_writer(&store, &lock);
+                        let captured = entry(&format!("writer{}-{}", writer, i), "text", 1);
+                        ids.push(captured.id.clone());
+                        history.entries.push_front(captured);
+                        save_as_writer(&store, &lock, &history, &known);
+                    }
+                    ids
+                })
+            })
+            .collect();
+        let expected: HashSet<String> = writers.into_iter().flat_map(|w| w.join().unwrap()).collect();
+
+        let stored = stored_history(&store).unwrap();
+        assert_eq!(stored.entries.len(), 2 * PER_WRITER);
+        assert_eq!(entry_ids(&stored), expected);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn save_does_not_resurrect_entries_this_writer_removed() {
+        let mut history = ClipboardHistory::new(10);
+        history.entries.push_back(entry("kept", "text", 1));
+        let known: HashSet<String> = ["kept".to_string(), "deleted".to_string()].into();
+        let stored = vec![entry("kept", "text", 1), entry("deleted", "text", 1), entry("other", "text", 1)];
+
+        let unseen = unseen_entries(stored, &known, &history);
+        assert_eq!(unseen.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), vec!["other"]);
+    }
+
+    /// Contents of `history`'s entries, newest first, with their files removed
+    fn drain_contents(history: &mut ClipboardHistory) -> Vec<String> {
+        let contents = history
+            .entries
+            .iter()
+            .map(|e| String::from_utf8_lossy(&history.get_entry_content(&e.id).unwrap_or_default()).to_string())
+            .collect();
+        for id in history.entries.iter().map(|e| e.id.clone()).collect::<Vec<_>>() {
+            history.delete_entry_file(&id);
+        }
+        contents
+    }
+
+    #[test]
+    fn dedupe_keeps_the_pinned_copy_and_skips_unreadable_entries() {
+        let _guard = setup();
+        let mut history = ClipboardHistory::new(10);
+        let ids = ["dedupe-new", "dedupe-pinned", "dedupe-other", "dedupe-gone-1", "dedupe-gone-2"];
+        for id in ids {
+            history.entries.push_back(entry(id, "text", 3));
+        }
+        history.entries[1].pinned = true;
+        fs::write(history.get_entry_path("dedupe-new"), b"dup").unwrap();
+        fs::write(history.get_entry_path("dedupe-pinned"), b"dup").unwrap();
+        // Same type and size, different bytes
+        fs::write(history.get_entry_path("dedupe-other"), b"abc").unwrap();
+
+        assert_eq!(history.dedupe(), 1);
+        let kept: Vec<&str> = history.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(kept, vec!["dedupe-pinned", "dedupe-other", "dedupe-gone-1", "dedupe-gone-2"]);
+        assert!(!history.get_entry_path("dedupe-new").exists());
+        drain_contents(&mut history);
+    }
+
+    #[test]
+    fn replace_content_clears_details_of_the_old_content() {
+        let _guard = setup();
+        let mut history = ClipboardHistory::new(10);
+        let mut old = entry("replace-me", "text", 3);
+        old.truncated = true;
+        old.dimensions = Some((4, 4));
+        old.mimes = vec!["text/html".to_string()];
+        old.source_encoding = Some("windows-1252".to_string());
+        history.entries.push_back(old);
+
+        history.replace_content("replace-me", b"new text".to_vec()).unwrap();
+        let replaced = &history.entries[0];
+        assert_eq!(replaced.content_size, 8);
+        assert!(!replaced.truncated);
+        assert_eq!(replaced.dimensions, None);
+        assert!(replaced.mimes.is_empty());
+        assert_eq!(replaced.source_encoding, None);
+        assert_eq!(drain_contents(&mut history), vec!["new text".to_string()]);
+    }
+
+    #[test]
+    fn dedupe_never_removes_pinned_duplicates() {
+        let _guard = setup();
+        let mut history = ClipboardHistory::new(10);
+        for id in ["pinned-dup-1", "pinned-dup-loose", "pinned-dup-2"] {
+            history.entries.push_back(entry(id, "text", 4));
+            fs::write(history.get_entry_path(id), b"same").unwrap();
+        }
+        history.entries[0].pinned = true;
+        history.entries[2].pinned = true;
+
+        assert_eq!(history.dedupe(), 1);
+        let kept: Vec<&str> = history.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(kept, vec!["pinned-dup-1", "pinned-dup-2"]);
+        drain_contents(&mut history);
+    }
+
+    fn png(fill: u8) -> Vec<u8> {
+        let mut image = b"\x89PNG\r\n\x1a\n".to_vec();
+        image.extend([fill; 120]);
+        image
+    }
+
+    #[test]
+    fn max_text_entries_evicts_oldest_text_only() {
+        let _guard = setup();
+        set_type_limits(2, 0);
+        let mut history = ClipboardHistory::new(10);
+        history.add_entry(b"t1".to_vec(), "text".to_string(), None);
+        history.add_entry(png(1), "image/png".to_string(), None);
+        history.add_entry(b"t2".to_vec(), "text".to_string(), None);
+        history.add_entry(b"t3".to_vec(), "text".to_string(), None);
+        set_type_limits(0, 0);
+
+        let types: Vec<String> = history.entries.iter().map(|e| e.content_type.clone()).collect();
+        assert_eq!(types, vec!["text", "text", "image/png"]);
+        assert_eq!(drain_contents(&mut history)[..2], ["t3".to_string(), "t2".to_string()]);
+    }
+
+    #[test]
+    fn max_image_entries_evicts_oldest_image_only() {
+        let _guard = setup();
+        set_type_limits(0, 1);
+        let mut history = ClipboardHistory::new(10);
+        history.add_entry(png(1), "image/png".to_string(), None);
+        history.add_entry(b"text".to_vec(), "text".to_string(), None);
+        history.add_entry(png(2), "image/png".to_string(), None);
+        set_type_limits(0, 0);
+
+        let images: Vec<usize> = history
+            .entries
+            .iter()
+            .filter(|e| e.content_type == "image/png")
+            .map(|e| e.content_size)
+            .collect();
+        assert_eq!(images.len(), 1);
+        let contents = drain_contents(&mut history);
+        assert_eq!(contents.len(), 2);
+        assert_eq!(contents[1], "text");
+        assert!(contents[0].as_bytes().ends_with(&[2; 120]));
+    }
+
+    #[test]
+    fn type_limits_skip_pinned_entries() {
+        let _guard = setup();
+        set_type_limits(1, 0);
+        let mut history = ClipboardHistory::new(10);
+        history.add_entry(b"pinned".to_vec(), "text".to_string(), None);
+        let pinned = history.entries[0].id.clone();
+        history.set_pinned(&pinned, true);
+        history.add_entry(b"old".to_vec(), "text".to_string(), None);
+        history.add_entry(b"new".to_vec(), "text".to_string(), None);
+        set_type_limits(0, 0);
+
+        let mut contents = drain_contents(&mut history);
+        contents.sort();
+        assert_eq!(contents, vec!["new", "pinned"]);
+    }
+
+    #[test]
+    fn max_text_entries_counts_text_subtypes() {
+        let _guard = setup();
+        set_type_limits(1, 0);
+        let mut history = ClipboardHistory::new(10);
+        history.add_entry(b"{\"a\": 1}".to_vec(), "text/json".to_string(), None);
+        history.add_entry(b"#!/bin/sh\necho hi".to_vec(), "application/x-sh".to_string(), None);
+        set_type_limits(0, 0);
+
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.entries[0].content_type, "application/x-sh");
+        drain_contents(&mut history);
+    }
+
+    #[test]
+    fn no_type_limits_fall_back_to_max_entries() {
+        let _guard = setup();
+        set_type_limits(0, 0);
+        let mut history = ClipboardHistory::new(2);
+        history.add_entry(b"a".to_vec(), "text".to_string(), None);
+        history.add_entry(png(3), "image/png".to_string(), None);
+        history.add_entry(b"b".to_vec(), "text".to_string(), None);
+
+        let types: Vec<String> = history.entries.iter().map(|e| e.content_type.clone()).collect();
+        assert_eq!(types, vec!["text", "image/png"]);
+        drain_contents(&mut history);
+    }
+
+    #[test]
+    fn evict_overflow_spares_pinned_entries() {
+        let _guard = setup();
+        set_type_limits(0, 0);
+        let mut history = query_history();
+        history.max_entries = 2;
+        // The two oldest are pinned, so only unpinned ones make room
+        history.set_pinned("c", true);
+        history.set_pinned("d", true);
+        history.evict_overflow();
+
+        let ids: Vec<&str> = history.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "d"]);
+    }
+
+    /// Newest first, a minute apart: a (text, 30 B), b (image/png, 500 B),
+    /// c (text/json, 10 B) and d (text, 200 B)
+    fn query_history() -> ClipboardHistory {
+        let now = Utc::now();
+        let mut history = ClipboardHistory::new(10);
+        let entries = [("a", "text", 30), ("b", "image/png", 500), ("c", "text/json", 10), ("d", "text", 200)];
+        for (i, (id, content_type, size)) in entries.into_iter().enumerate() {
+            let mut e = entry(id, content_type, size);
+            e.timestamp = now - chrono::Duration::minutes(i as i64);
+            history.entries.push_back(e);
+        }
+        history
+    }
+
+    fn query_ids(history: &ClipboardHistory, params: QueryParams) -> (Vec<String>, usize) {
+        let result = history.query(&params).unwrap();
+        (result.entries.into_iter().map(|e| e.id).collect(), result.total)
+    }
+
+    #[test]
+    fn query_defaults_to_display_order() {
+        let mut history = query_history();
+        history.set_pinned("c", true);
+        history.reorder_pins(&["c".to_string()]).unwrap();
+
+        let (ids, total) = query_ids(&history, QueryParams::default());
+        assert_eq!(ids, vec!["c", "a", "b", "d"]);
+        assert_eq!(total, 4);
+    }
+
+    #[test]
+    fn query_filters_by_content_type_prefix() {
+        let history = query_history();
+        let params = |prefix: &str| QueryParams { content_type_filter: Some(prefix.to_string()), ..Default::default() };
+
+        assert_eq!(query_ids(&history, params("text")), (vec!["a".to_string(), "c".to_string(), "d".to_string()], 3));
+        assert_eq!(query_ids(&history, params("image/")), (vec!["b".to_string()], 1));
+        assert_eq!(query_ids(&history, params("audio/")), (Vec::new(), 0));
+    }
+
+    #[test]
+    fn query_filters_pinned_only() {
+        let mut history = query_history();
+        history.set_pinned("d", true);
+
+        let (ids, total) = query_ids(&history, QueryParams { pinned_only: true, ..Default::default() });
+        assert_eq!(ids, vec!["d"]);
+        assert_eq!(total, 1);
+    }
+
+    #[test]
+    fn query_filters_by_inclusive_date_range() {
+        let history = query_history();
+        let params = QueryParams {
+            date_from: Some(history.entries[2].timestamp),
+            date_to: Some(history.entries[1].timestamp),
+            ..Default::default()
+        };
+        assert_eq!(query_ids(&history, params).0, vec!["b", "c"]);
+
+        let params = QueryParams { date_from: Some(history.entries[1].timestamp), ..Default::default() };
+        assert_eq!(query_ids(&history, params).0, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn query_sorts_by_order() {
+        let mut history = query_history();
+        // Pins only lead in display order
+        history.set_pinned("d", true);
+        history.reorder_pins(&["d".to_string()]).unwrap();
+        let order = |order: &str| QueryParams { order: Some(order.to_string()), ..Default::default() };
+
+        assert_eq!(query_ids(&history, order("display")).0, vec!["d", "a", "b", "c"]);
+        assert_eq!(query_ids(&history, order("newest")).0, vec!["a", "b", "c", "d"]);
+        assert_eq!(query_ids(&history, order("oldest")).0, vec!["d", "c", "b", "a"]);
+        assert_eq!(query_ids(&history, order("largest")).0, vec!["b", "d", "a", "c"]);
+        assert_eq!(query_ids(&history, order("smallest")).0, vec!["c", "a", "d", "b"]);
+        assert!(history.query(&order("random")).is_err());
+    }
+
+    #[test]
+    fn query_pages_with_offset_and_limit() {
+        let history = query_history();
+        let page = |offset: usize, limit: Option<usize>| QueryParams { offset, limit, ..Default::default() };
+
+        assert_eq!(query_ids(&history, page(1, Some(2))), (vec!["b".to_string(), "c".to_string()], 4));
+        assert_eq!(query_ids(&history, page(3, Some(5))), (vec!["d".to_string()], 4));
+        assert_eq!(query_ids(&history, page(0, Some(0))), (Vec::new(), 4));
+        assert_eq!(query_ids(&history, page(10, None)), (Vec::new(), 4));
+    }
+
+    #[test]
+    fn query_pages_after_filtering_and_sorting() {
+        let history = query_history();
+        let params = QueryParams {
+            content_type_filter: Some("text".to_string()),
+            order: Some("largest".to_string()),
+            offset: 1,
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(query_ids(&history, params), (vec!["a".to_string()], 3));
+    }
+
+    #[test]
+    fn query_searches_text_content() {
+        let _guard = setup();
+        let mut history = ClipboardHistory::new(10);
+        history.add_entry(b"Hello World".to_vec(), "text".to_string(), None);
+        history.add_entry(b"help me".to_vec(), "text".to_string(), None);
+        history.add_entry(png(4), "image/png".to_string(), None);
+        let search = |needle: &str, fuzzy: bool| QueryParams {
+            search: Some(needle.to_string()),
+            fuzzy,
+            ..Default::default()
+        };
+
+        let result = history.query(&search("HEL", false)).unwrap();
+        assert_eq!(result.total, 2);
+        assert!(result.entries.iter().all(|e| e.content_type == "text"));
+        assert_eq!(history.query(&search("world", false)).unwrap().total, 1);
+        assert_eq!(history.query(&search("hlo", false)).unwrap().total, 0);
+        assert_eq!(history.query(&search("hlo", true)).unwrap().total, 1);
+        // Images never match, even when their bytes do
+        assert_eq!(history.query(&search("png", false)).unwrap().total, 0);
+        // An empty search filters nothing
+        assert_eq!(history.query(&search("", false)).unwrap().total, 3);
+        drain_contents(&mut history);
+    }
 }
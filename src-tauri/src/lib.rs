@@ -3,6 +3,8 @@ mod commands;
 mod config;
 mod detect;
 mod history;
+mod paste;
+mod preview;
 mod theme;
 mod tray;
 mod utils;
@@ -22,12 +24,43 @@ use tokio::sync::RwLock;
 
 use theme::Theme;
 
+// For benches/store_formats.rs and benches/history_window.rs
+#[doc(hidden)]
+pub use history::{
+    decode_binary_store, encode_binary_store, read_index, read_index_window, write_index_at, ClipboardEntry,
+    ClipboardHistory,
+};
+
 static LAST_WRITTEN_CLIPBOARD: Lazy<Mutex<Option<u64>>> = Lazy::new(|| Mutex::new(None));
 
 use commands::{
     clear_clipboard_history, get_claw_config, get_clipboard_history, get_history_stats,
     get_system_clipboard, get_theme, remove_clipboard_entry, set_clipboard_from_history,
-    set_system_clipboard, get_clipboard_entry_content
+    set_system_clipboard, get_clipboard_entry_content, get_recent_previews, open_entry_url,
+    reveal_in_file_manager, clear_unpinned_history, set_entry_pinned,
+    set_persist_history, dedupe_history, get_config_path, open_config_file,
+    get_gather_results, get_app_info, concat_entries, set_active_theme_alias,
+    get_top_pinned, reset_usage_stats, set_tray_history_enabled,
+    list_clipboard_mimes, get_clipboard_mime, get_current_entry_id,
+    set_clipboard_from_history_as, rebuild_index_from_disk,
+    get_schema_version, get_last_error, clear_last_error,
+    materialize_entry, capture_now, get_watched_paths, get_last_selected,
+    get_history_by_app, set_session_history_limit, set_primary_from_history,
+    get_entry_text_lossy, transform_entries, restore_entry, get_entry_dominant_color,
+    get_growth_estimate, get_entry_full_text, reorder_pins, get_entry_at_time,
+    reinject_clipboard, get_active_profile, set_active_profile, list_profiles, create_profile,
+    delete_profile, remove_clipboard_entries, find_duplicates, rescan_gather_files,
+    apply_snippet, get_template_variables, explain_current_clipboard, snippet_from_clipboard,
+    get_entries_by_size, set_entry_content_type, get_effective_keybinds,
+    import_image_file, get_deletion_log, get_entry_checksum,
+    append_to_clipboard, set_force_dark_mode, export_history_text,
+    get_session_stats, get_image_info, get_adjacent_entry,
+    get_backend_status, add_history_entry, set_always_on_top,
+    get_always_on_top, query_history, get_window_monitor,
+    export_settings, import_settings,
+    history_health_check, set_entry_expiry, pin_matching, update_entry_content,
+    get_watcher_diagnostics, get_watcher_metrics, get_entries_in_range, get_config_value,
+    set_config_value, pause_config_watch, resume_config_watch, quick_paste
 };
 use config::{load_claw_config, ClipboardConfig};
 
@@ -42,30 +75,55 @@ struct ConfigUpdate {
 pub fn run() {
     tauri::Builder::default()      
         .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
-            if args.contains(&"--toggle".into()) {
+            // args[0] is the binary; flags may come in any order
+            let has_flag = |flag: &str| args.iter().skip(1).any(|a| a.trim() == flag);
+
+            if has_flag("--reinject") {
+                if let Err(e) = clipboard::reinject_clipboard() {
+                    utils::record_error(format!("Failed to reinject clipboard: {}", e));
+                }
+            } else if has_flag("--toggle") {
                 window::toggle_main_window(app);
-                return;
-            }
+            } else if has_flag("--hide") {
+                window::hide_main_window(app);
+            } else {
+                // Without --show, follow clipboard.second_instance_action
+                let action = if has_flag("--show") {
+                    "show".to_string()
+                } else {
+                    config::current_clipboard_config(app)
+                        .map(|cfg| cfg.second_instance_action)
+                        .unwrap_or_else(|| "show".to_string())
+                };
 
-            if let Some(window) = app.get_webview_window("main") {
-                let _ = window.show();
-                let _ = window.set_focus();
-                let _ = window.unminimize();
+                match action.as_str() {
+                    "ignore" => {}
+                    "toggle" => window::toggle_main_window(app),
+                    _ => {
+                        window::show_main_window(app);
+                        if let Some(window) = app.get_webview_window("main") {
+                            let _ = window.unminimize();
+                        }
+                    }
+                }
             }
         }))
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_cli::init())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
             let app_handle = app.handle();
 
             let claw_config = Arc::new(RwLock::new(load_claw_config()));
             app.manage(claw_config.clone());
 
-            // Cleanup history on exit if persistence is disabled
+            // Cleanup drag-out temp files, and history too if persistence is disabled
             {
                 let app_handle = app_handle.clone();
                 let claw_config = claw_config.clone();
                 app_handle.clone().once("tauri://exit", move |_event| {
+                    utils::cleanup_materialized_files();
+
                     let app_handle = app_handle.clone();
                     let claw_config = claw_config.clone();
                     tauri::async_runtime::spawn(async move {
@@ -80,6 +138,37 @@ pub fn run() {
                 });
             }
 
+            if let Ok(cfg) = claw_config.try_read() {
+                // Before anything loads or saves history
+                history::set_binary_store(cfg.0.store_format == "bincode");
+                history::set_oversize_policy(&cfg.0.on_oversize);
+                history::set_low_memory(cfg.0.low_memory);
+                history::set_type_limits(cfg.0.max_text_entries, cfg.0.max_image_entries);
+                history::set_auto_pin_recent(cfg.0.auto_pin_recent);
+                history::set_history_locked(cfg.0.lock_history);
+                if let Err(e) = history::set_active_profile(&cfg.0.profile) {
+                    eprintln!("{}; using the default profile", e);
+                }
+
+                // Re-index cached entries the history file lost
+                if cfg.0.rebuild_index_on_startup {
+                    match history::rebuild_index_from_disk(app_handle, cfg.0.max_entries()) {
+                        Ok(0) => {}
+                        Ok(count) => eprintln!("Recovered {} history entries from the cache", count),
+                        Err(e) => eprintln!("Failed to rebuild history index: {}", e),
+                    }
+                }
+
+                // Clean up duplicates left behind by crashes or older versions
+                if cfg.0.dedupe_on_startup {
+                    match history::dedupe_history(app_handle, cfg.0.max_entries(), cfg.0.dedup_images()) {
+                        Ok(0) => {}
+                        Ok(removed) => eprintln!("Removed {} duplicate history entries", removed),
+                        Err(e) => eprintln!("Failed to dedupe history: {}", e),
+                    }
+                }
+            }
+
             let main_window = app.get_webview_window("main").unwrap();
 
             // Parse CLI arguments
@@ -90,9 +179,21 @@ pub fn run() {
             if should_hide {
                 main_window.hide().ok();
             }
+            window::set_main_window_visible(main_window.is_visible().unwrap_or(!should_hide));
 
-            // Setup window close handler
+            // Setup window event handlers and keep the window on screen
             window::setup_window_close_handler(app_handle.clone());
+            window::spawn_display_watcher(app_handle.clone());
+
+            // Apply configured window size/stacking and clipboard read limits
+            if let Ok(cfg) = claw_config.try_read() {
+                window::apply_window_config(app_handle, &cfg.0);
+                clipboard::set_max_read_bytes(cfg.0.max_read_bytes);
+                clipboard::set_ignore_mimes(cfg.0.ignore_mimes.clone());
+                clipboard::set_x11_fallback_to_primary(cfg.0.x11_fallback_to_primary);
+                preview::set_redact_patterns(&cfg.0.redact_patterns);
+                watchers::set_only_record_patterns(&cfg.0.only_record_patterns);
+            }
 
             // Create initial tray menu
             let show_i = MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
@@ -151,12 +252,96 @@ pub fn run() {
             get_system_clipboard,
             get_clipboard_history,
             get_clipboard_entry_content,
+            get_entries_in_range,
+            get_recent_previews,
             clear_clipboard_history,
+            clear_unpinned_history,
+            set_entry_pinned,
+            pin_matching,
+            set_entry_expiry,
+            dedupe_history,
             remove_clipboard_entry,
+            update_entry_content,
             set_clipboard_from_history,
+            quick_paste,
+            concat_entries,
+            open_entry_url,
+            reveal_in_file_manager,
             get_history_stats,
+            history_health_check,
+            set_persist_history,
             get_theme,
             get_claw_config,
+            get_config_value,
+            set_config_value,
+            pause_config_watch,
+            resume_config_watch,
+            get_config_path,
+            open_config_file,
+            get_gather_results,
+            set_active_theme_alias,
+            get_top_pinned,
+            reset_usage_stats,
+            set_tray_history_enabled,
+            list_clipboard_mimes,
+            get_clipboard_mime,
+            get_current_entry_id,
+            set_clipboard_from_history_as,
+            rebuild_index_from_disk,
+            get_schema_version,
+            get_last_error,
+            clear_last_error,
+            materialize_entry,
+            capture_now,
+            get_watched_paths,
+            get_last_selected,
+            get_history_by_app,
+            set_session_history_limit,
+            set_primary_from_history,
+            get_entry_text_lossy,
+            transform_entries,
+            restore_entry,
+            get_entry_dominant_color,
+            get_growth_estimate,
+            get_entry_full_text,
+            reorder_pins,
+            get_entry_at_time,
+            reinject_clipboard,
+            get_active_profile,
+            set_active_profile,
+            list_profiles,
+            create_profile,
+            delete_profile,
+            remove_clipboard_entries,
+            find_duplicates,
+            rescan_gather_files,
+            apply_snippet,
+            get_template_variables,
+            explain_current_clipboard,
+            snippet_from_clipboard,
+            get_entries_by_size,
+            set_entry_content_type,
+            get_effective_keybinds,
+            import_image_file,
+            get_deletion_log,
+            get_entry_checksum,
+            append_to_clipboard,
+            set_force_dark_mode,
+            export_history_text,
+            get_session_stats,
+            get_image_info,
+            get_adjacent_entry,
+            get_backend_status,
+            add_history_entry,
+            set_always_on_top,
+            get_always_on_top,
+            query_history,
+            get_window_monitor,
+            export_settings,
+            import_settings,
+            get_app_info,
+            get_watcher_diagnostics,
+            get_watcher_metrics,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -179,7 +364,14 @@ fn handle_tray_menu_event(
         "quit" => {
             app.exit(0);
         }
-        "clear_history" => {
+        "capture_now" => {
+            if let Some(cfg) = config::current_clipboard_config(app_handle) {
+                if let Err(e) = watchers::capture_now(app_handle, &cfg) {
+                    eprintln!("Capture failed: {}", e);
+                }
+            }
+        }
+        "clear_history" if !history::history_locked() => {
             if let Ok(mut hist) = history::load_history(app_handle, 100) {
                 hist.clear();
                 let _ = history::save_history(app_handle, &hist);
@@ -191,7 +383,10 @@ fn handle_tray_menu_event(
         id if id.starts_with("history_") => {
             if let Ok(idx) = id.strip_prefix("history_").unwrap().parse::<usize>() {
                 if let Ok(hist) = history::load_history(app_handle, 100) {
-                    let entries = hist.get_entries(Some(5));
+                    let (content_types, unique) = config::current_clipboard_config(app_handle)
+                        .map(|cfg| (cfg.tray_content_types, cfg.tray_unique))
+                        .unwrap_or_else(|| (Vec::new(), true));
+                    let entries = tray::tray_entries(&hist, &content_types, unique);
                     if let Some(entry) = entries.get(idx) {
                         if let Some(content) = hist.get_entry_content(&entry.id) {
                             clipboard::cache_clipboard_data(&content);
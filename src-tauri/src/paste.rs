@@ -0,0 +1,50 @@
+// Author: Dustin Pilgrim
+// License: MIT
+
+//! Synthesized Ctrl+V for `clipboard.auto_paste`.
+//!
+//! There is no portable way to inject keystrokes, so this shells out to
+//! whichever tool is installed. On Wayland `wtype` needs a compositor with
+//! the virtual-keyboard protocol (wlroots-based ones; not GNOME), and
+//! `ydotool` needs its daemon running with access to /dev/uinput. On X11
+//! `xdotool` works everywhere. When nothing succeeds the caller simply
+//! leaves the content on the clipboard for a manual paste.
+
+use std::process::{Command, Stdio};
+
+use crate::detect::DesktopEnv;
+
+fn run(program: &str, args: &[&str]) -> bool {
+    Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn paste_wayland() -> bool {
+    run("wtype", &["-M", "ctrl", "v", "-m", "ctrl"])
+        // KEY_LEFTCTRL = 29, KEY_V = 47
+        || run("ydotool", &["key", "29:1", "47:1", "47:0", "29:0"])
+}
+
+fn paste_x11() -> bool {
+    run("xdotool", &["key", "--clearmodifiers", "ctrl+v"])
+}
+
+/// Send Ctrl+V to the focused window
+pub fn synthesize_paste() -> Result<(), String> {
+    let pasted = match crate::detect::current_desktop_env() {
+        DesktopEnv::Wayland => paste_wayland(),
+        DesktopEnv::X11 => paste_x11(),
+        DesktopEnv::Unknown => paste_wayland() || paste_x11(),
+    };
+
+    if pasted {
+        Ok(())
+    } else {
+        Err("No working paste tool found (install wtype, ydotool or xdotool)".to_string())
+    }
+}
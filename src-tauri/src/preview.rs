@@ -0,0 +1,148 @@
+// Author: Dustin Pilgrim
+// License: MIT
+
+use std::sync::RwLock;
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::history::{ClipboardEntry, ClipboardHistory};
+use crate::utils::{file_path_from_source, read_recover, write_recover};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryPreview {
+    pub id: String,
+    pub content_type: String,
+    pub timestamp: DateTime<Utc>,
+    pub preview: String,
+}
+
+pub fn human_size_from_bytes(size: usize) -> String {
+    let kb = size as f64 / 1024.0;
+    if kb < 1024.0 {
+        format!("{:.0} KB", kb)
+    } else {
+        format!("{:.1} MB", kb / 1024.0)
+    }
+}
+
+/// Label for an image entry based on where it was copied from
+fn image_source_label(entry: &ClipboardEntry) -> String {
+    if let Some(src) = &entry.source_path {
+        if src.starts_with("file://") {
+            let path = file_path_from_source(src).unwrap_or_default();
+            if let Some(fname) = path.file_name() {
+                return format!("{} ({})", fname.to_string_lossy(), human_size_from_bytes(entry.content_size));
+            }
+        } else if let Ok(url) = url::Url::parse(src) {
+            let host = url.host_str().unwrap_or("web");
+            let filename = url
+                .path_segments()
+                .and_then(|s| s.last())
+                .unwrap_or("image");
+            return format!("{} / {} ({})", host, filename, human_size_from_bytes(entry.content_size));
+        }
+    }
+
+    match entry.frame_count {
+        Some(frames) if frames > 1 => format!(
+            "GIF (animated, {} frames, {})",
+            frames,
+            human_size_from_bytes(entry.content_size)
+        ),
+        _ => format!("Image ({})", human_size_from_bytes(entry.content_size)),
+    }
+}
+
+const REDACTED: &str = "•••";
+
+/// `clipboard.redact_patterns`, compiled; matches are hidden in previews only
+static REDACT_PATTERNS: Lazy<RwLock<Vec<Regex>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Patterns are validated at config load, so any that still fail are skipped
+pub fn set_redact_patterns(patterns: &[String]) {
+    let compiled = patterns.iter().filter_map(|p| Regex::new(p).ok()).collect();
+    *write_recover(&REDACT_PATTERNS) = compiled;
+}
+
+/// Text a `redact_patterns` entry matches, so it shouldn't leave claw in readable form
+pub fn is_sensitive(text: &str) -> bool {
+    let patterns = read_recover(&REDACT_PATTERNS);
+    patterns.iter().any(|re| re.is_match(text))
+}
+
+fn redact(text: String) -> String {
+    let patterns = read_recover(&REDACT_PATTERNS);
+    patterns.iter().fold(text, |text, re| re.replace_all(&text, REDACTED).into_owned())
+}
+
+/// Single-line text prefix of at most `max_bytes`, cut on a char boundary.
+/// Redaction runs before the cut so a secret can't show half-hidden.
+fn text_prefix(bytes: &[u8], max_bytes: usize) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let flat = redact(text.split_whitespace().collect::<Vec<_>>().join(" "));
+
+    if flat.len() <= max_bytes {
+        return flat;
+    }
+
+    let mut end = max_bytes;
+    while end > 0 && !flat.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &flat[..end])
+}
+
+/// One-line summary of freshly captured content, e.g. "340 KB image" or
+/// "text: the quick…" (redacted like every other preview)
+pub fn capture_summary(content: &[u8], content_type: &str) -> String {
+    if content_type.starts_with("image/") {
+        format!("{} image", human_size_from_bytes(content.len()))
+    } else if content_type == "binary" {
+        format!("{} of binary data", human_size_from_bytes(content.len()))
+    } else {
+        let end = content.len().min(CAPTURE_SUMMARY_BYTES * 4);
+        format!("text: {}", text_prefix(&content[..end], CAPTURE_SUMMARY_BYTES))
+    }
+}
+
+const CAPTURE_SUMMARY_BYTES: usize = 60;
+
+pub fn entry_preview(history: &ClipboardHistory, entry: &ClipboardEntry, preview_bytes: usize) -> EntryPreview {
+    let preview = if entry.content_type.starts_with("image/") {
+        image_source_label(entry)
+    } else if entry.content_type == "binary" {
+        format!("{} ({})", entry.content_type, human_size_from_bytes(entry.content_size))
+    } else {
+        // Bound the slice we flatten so huge text entries stay cheap
+        let content = history.get_entry_content(&entry.id).unwrap_or_default();
+        let end = content.len().min(preview_bytes.saturating_mul(4));
+        text_prefix(&content[..end], preview_bytes)
+    };
+
+    EntryPreview {
+        id: entry.id.clone(),
+        content_type: entry.content_type.clone(),
+        timestamp: entry.timestamp,
+        preview,
+    }
+}
+
+/// Previews of the `n` most recent entries, newest first
+pub fn recent_previews(history: &ClipboardHistory, n: usize, preview_bytes: usize) -> Vec<EntryPreview> {
+    previews_of(history, history.entries.iter().take(n), preview_bytes)
+}
+
+/// Previews of an already-selected set of entries, in the given order
+pub fn previews_of<'a>(
+    history: &ClipboardHistory,
+    entries: impl IntoIterator<Item = &'a ClipboardEntry>,
+    preview_bytes: usize,
+) -> Vec<EntryPreview> {
+    entries
+        .into_iter()
+        .map(|entry| entry_preview(history, entry, preview_bytes))
+        .collect()
+}
@@ -23,15 +23,93 @@ pub struct ThemeColors {
     pub outline: String,
 }
 
+/// Non-color styling from a theme's `ui` block; sizes are in pixels
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ThemeUi {
+    #[serde(rename = "font-family")]
+    pub font_family: String,
+    #[serde(rename = "font-size")]
+    pub font_size: u32,
+    #[serde(rename = "border-radius")]
+    pub border_radius: u32,
+    pub spacing: u32,
+}
+
+impl Default for ThemeUi {
+    fn default() -> Self {
+        Self {
+            font_family: "system-ui, sans-serif".to_string(),
+            font_size: 14,
+            border_radius: 6,
+            spacing: 8,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct Theme {
     pub light: ThemeColors,
     pub dark: ThemeColors,
+    #[serde(default)]
+    pub ui: ThemeUi,
+}
+
+// Guards against themes that extend each other in a loop
+const MAX_THEME_DEPTH: usize = 8;
+
+impl ThemeColors {
+    /// Fill every unset (empty) color from `base`
+    fn inherit_from(&mut self, base: &ThemeColors) {
+        let pairs = [
+            (&mut self.background, &base.background),
+            (&mut self.background_alt, &base.background_alt),
+            (&mut self.titlebar_background, &base.titlebar_background),
+            (&mut self.text_primary, &base.text_primary),
+            (&mut self.text_secondary, &base.text_secondary),
+            (&mut self.hover, &base.hover),
+            (&mut self.hover_titlebar, &base.hover_titlebar),
+            (&mut self.selected, &base.selected),
+            (&mut self.selected_foreground, &base.selected_foreground),
+            (&mut self.highlight, &base.highlight),
+            (&mut self.outline, &base.outline),
+        ];
+        for (value, base_value) in pairs {
+            if value.is_empty() {
+                *value = base_value.clone();
+            }
+        }
+    }
+}
+
+/// Load the theme named by `extends`, or the default theme if it can't be found
+fn load_base_theme(name: &str, depth: usize) -> Theme {
+    if depth >= MAX_THEME_DEPTH {
+        eprintln!("Theme inheritance too deep at \"{}\", ignoring base", name);
+        return Theme::default();
+    }
+
+    let Some(path) = find_theme_file(name) else {
+        eprintln!("Base theme \"{}\" not found, using default", name);
+        return Theme::default();
+    };
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
+    match RuneConfig::from_file_with_base(path.clone(), base_dir) {
+        Ok(cfg) => Theme::from_config_at_depth(&cfg, None, depth + 1),
+        Err(e) => {
+            eprintln!("Failed to load base theme \"{}\": {}", name, e);
+            Theme::default()
+        }
+    }
 }
 
 impl Theme {
     /// Load theme from a RuneConfig, optionally from a document alias
     pub fn from_config(cfg: &RuneConfig, doc_alias: Option<&str>) -> Self {
+        Self::from_config_at_depth(cfg, doc_alias, 0)
+    }
+
+    fn from_config_at_depth(cfg: &RuneConfig, doc_alias: Option<&str>, depth: usize) -> Self {
         let get_value = |base: &str, key: &str| -> String {
             if let Some(alias) = doc_alias {
                 // First try alias.theme.base.key
@@ -76,7 +154,51 @@ impl Theme {
             outline: get_value("dark", "outline"),
         };
 
-        Self { light, dark }
+        // `ui` settings are read the same way, but may be absent or non-strings
+        let get_ui = |key: &str| -> Option<String> {
+            let paths = match doc_alias {
+                Some(alias) => vec![format!("{alias}.theme.ui.{key}"), format!("{alias}.ui.{key}")],
+                None => vec![format!("theme.ui.{key}")],
+            };
+            paths.iter().find_map(|path| {
+                cfg.get::<String>(path)
+                    .ok()
+                    .or_else(|| cfg.get::<u32>(path).ok().map(|n| n.to_string()))
+            })
+        };
+        let font_family = get_ui("font-family");
+        let get_px = |key: &str| get_ui(key).and_then(|v| v.trim().trim_end_matches("px").parse::<u32>().ok());
+        let (font_size, border_radius, spacing) = (get_px("font-size"), get_px("border-radius"), get_px("spacing"));
+
+        let mut theme = Self {
+            light,
+            dark,
+            ui: ThemeUi::default(),
+        };
+
+        // `theme.extends = "name"`: unset colors come from the base theme
+        let extends = match doc_alias {
+            Some(alias) => cfg
+                .get::<String>(&format!("{alias}.theme.extends"))
+                .or_else(|_| cfg.get::<String>(&format!("{alias}.extends"))),
+            None => cfg.get::<String>("theme.extends"),
+        };
+        if let Ok(base_name) = extends {
+            let base = load_base_theme(&base_name, depth);
+            theme.light.inherit_from(&base.light);
+            theme.dark.inherit_from(&base.dark);
+            theme.ui = base.ui;
+        }
+
+        // Unset `ui` values keep the base theme's, or the defaults
+        if let Some(font_family) = font_family.filter(|f| !f.trim().is_empty()) {
+            theme.ui.font_family = font_family;
+        }
+        theme.ui.font_size = font_size.unwrap_or(theme.ui.font_size);
+        theme.ui.border_radius = border_radius.unwrap_or(theme.ui.border_radius);
+        theme.ui.spacing = spacing.unwrap_or(theme.ui.spacing);
+
+        theme
     }
 }
 
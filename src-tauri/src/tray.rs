@@ -2,47 +2,80 @@ use tauri::{
     menu::{Menu, MenuItem, Submenu},
     AppHandle,
 };
-use crate::history::{self, ClipboardEntry};
+use crate::config::current_clipboard_config;
+use crate::history::{self, ClipboardEntry, ClipboardHistory};
+use crate::preview::{human_size_from_bytes, previews_of, EntryPreview};
+use crate::utils::{clipboard_hash, format_timestamp, DEFAULT_TIMESTAMP_FORMAT};
+use std::cell::OnceCell;
 
 pub const TRAY_ID: &str = "claw-tray";
 
-pub fn human_size_from_bytes(size: usize) -> String {
-    let kb = size as f64 / 1024.0;
-    if kb < 1024.0 {
-        format!("{:.0} KB", kb)
-    } else {
-        format!("{:.1} MB", kb / 1024.0)
+// Bytes of text shown per tray entry
+const TRAY_PREVIEW_BYTES: usize = 40;
+// Entries listed in the "Recent Clipboard" submenu
+const TRAY_ENTRY_COUNT: usize = 5;
+
+/// Entries shown in the tray, in menu order. Menu item `history_{idx}`
+/// refers to index `idx` of this list. With `unique`, only the most recent
+/// entry of each distinct content is kept. Content is only read for entries
+/// whose type and size match one already listed.
+pub fn tray_entries<'a>(
+    history: &'a ClipboardHistory,
+    content_types: &[String],
+    unique: bool,
+) -> Vec<&'a ClipboardEntry> {
+    let mut shown: Vec<&ClipboardEntry> = Vec::new();
+    // Content hash of each shown entry, read the first time it is needed
+    let mut shown_hashes: Vec<OnceCell<Option<u64>>> = Vec::new();
+    let content_hash = |entry: &ClipboardEntry| history.get_entry_content(&entry.id).map(|c| clipboard_hash(&c));
+
+    let candidates = history.entries.iter().filter(|e| {
+        content_types.is_empty() || content_types.iter().any(|prefix| e.content_type.starts_with(prefix.as_str()))
+    });
+    for entry in candidates {
+        if shown.len() >= TRAY_ENTRY_COUNT {
+            break;
+        }
+
+        let mut lookalikes = shown
+            .iter()
+            .zip(&shown_hashes)
+            .filter(|(s, _)| unique && s.content_type == entry.content_type && s.content_size == entry.content_size)
+            .peekable();
+        if lookalikes.peek().is_some() {
+            if let Some(hash) = content_hash(entry) {
+                if lookalikes.any(|(s, cached)| *cached.get_or_init(|| content_hash(s)) == Some(hash)) {
+                    continue;
+                }
+            }
+        }
+
+        shown.push(entry);
+        shown_hashes.push(OnceCell::new());
     }
+    shown
 }
 
-fn clipboard_entry_label_lightweight(entry: &ClipboardEntry) -> String {
-    if entry.content_type.starts_with("image/") {
-        image_menu_label_lightweight(entry)
-    } else if entry.content_type == "text" {
-        format!("📝 Text ({} bytes)", entry.content_size)
+/// Icon the tray and capture notifications show for a content type
+pub fn content_type_icon(content_type: &str) -> &'static str {
+    if content_type.starts_with("image/") {
+        "🖼️"
+    } else if content_type == "text/uri" {
+        "🔗"
+    } else if content_type == "text" {
+        "📝"
+    } else if matches!(
+        content_type,
+        "text/json" | "text/html" | "text/x-rust" | "text/x-python" | "text/javascript" | "application/x-sh"
+    ) {
+        "💻"
     } else {
-        format!("📎 {} ({} bytes)", entry.content_type, entry.content_size)
+        "📎"
     }
 }
 
-fn image_menu_label_lightweight(entry: &ClipboardEntry) -> String {
-    if let Some(src) = &entry.source_path {
-        if src.starts_with("file://") {
-            let path = &src[7..];
-            if let Some(fname) = std::path::Path::new(path).file_name() {
-                return format!("🖼️ {} ({})", fname.to_string_lossy(), human_size_from_bytes(entry.content_size));
-            }
-        } else if let Ok(url) = url::Url::parse(src) {
-            let host = url.host_str().unwrap_or("web");
-            let filename = url
-                .path_segments()
-                .and_then(|s| s.last())
-                .unwrap_or("image");
-            return format!("🖼️ {} / {} ({})", host, filename, human_size_from_bytes(entry.content_size));
-        }
-    }
-
-    format!("🖼️ Image ({})", human_size_from_bytes(entry.content_size))
+fn tray_label(preview: &EntryPreview, time: &str) -> String {
+    format!("{} {} · {}", content_type_icon(&preview.content_type), preview.preview, time)
 }
 
 pub fn update_tray_menu(
@@ -51,16 +84,60 @@ pub fn update_tray_menu(
 ) -> Result<(), Box<dyn std::error::Error>> {
     let tray = app.tray_by_id(tray_id).ok_or("Tray not found")?;
 
+    let cfg = current_clipboard_config(app);
+    let (timestamp_format, timezone, content_types, show_history, unique) = cfg
+        .clone()
+        .map(|cfg| {
+            (cfg.timestamp_format, cfg.timezone, cfg.tray_content_types, cfg.tray_history, cfg.tray_unique)
+        })
+        .unwrap_or_else(|| {
+            (DEFAULT_TIMESTAMP_FORMAT.to_string(), "local".to_string(), Vec::new(), true, true)
+        });
+    let manual_capture = cfg.as_ref().is_some_and(|cfg| cfg.capture_mode == "manual");
+    let show_stats = cfg.as_ref().is_some_and(|cfg| cfg.tray_show_stats);
+    let empty_text = cfg
+        .map(|cfg| cfg.tray_empty_text)
+        .unwrap_or_else(|| "No clipboard history yet".to_string());
+
     let history = history::load_history(app, 100)?;
-    let recent_items = history.get_entries(Some(5));
+    // With the submenu turned off, fall through to the basic Show/Quit menu
+    let recent_items = if show_history {
+        previews_of(&history, tray_entries(&history, &content_types, unique), TRAY_PREVIEW_BYTES)
+    } else {
+        Vec::new()
+    };
 
     let show_i = MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
+    // Manual capture mode: the tray is one of the ways to record
+    let capture_i = MenuItem::with_id(app, "capture_now", "Capture Now (manual mode)", true, None::<&str>)?;
+    let locked = history::history_locked();
+    let mut items: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> = vec![&show_i];
+    if manual_capture && !locked {
+        items.push(&capture_i);
+    }
+    let locked_i = MenuItem::with_id(app, "locked", "🔒 History locked", false, None::<&str>)?;
+    if locked {
+        items.push(&locked_i);
+    }
+
+    // Disabled items that only describe state
+    let total_bytes: usize = history.entries.iter().map(|e| e.content_size).sum();
+    let stats_text = format!("{} items • {}", history.entries.len(), human_size_from_bytes(total_bytes));
+    let stats_i = MenuItem::with_id(app, "stats", stats_text, false, None::<&str>)?;
+    if show_stats {
+        items.push(&stats_i);
+    }
+    let empty_i = MenuItem::with_id(app, "empty", &empty_text, false, None::<&str>)?;
+    if show_history && recent_items.is_empty() {
+        items.push(&empty_i);
+    }
 
     let menu = if !recent_items.is_empty() {
         let mut history_items = Vec::new();
 
-        for (idx, entry) in recent_items.iter().enumerate() {
-            let display_text = clipboard_entry_label_lightweight(&entry);
+        for (idx, preview) in recent_items.iter().enumerate() {
+            let time = format_timestamp(&preview.timestamp, &timestamp_format, &timezone);
+            let display_text = tray_label(preview, &time);
             
             let item_id = format!("history_{}", idx);
             let menu_item = MenuItem::with_id(app, &item_id, display_text, true, None::<&str>)?;
@@ -77,13 +154,15 @@ pub fn update_tray_menu(
                 .collect::<Vec<_>>(),
         )?;
 
-        let clear_i = MenuItem::with_id(app, "clear_history", "Clear History", true, None::<&str>)?;
+        let clear_i = MenuItem::with_id(app, "clear_history", "Clear History", !locked, None::<&str>)?;
         let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
 
-        Menu::with_items(app, &[&show_i, &history_submenu, &clear_i, &quit_i])?
+        items.extend([&history_submenu as &dyn tauri::menu::IsMenuItem<tauri::Wry>, &clear_i, &quit_i]);
+        Menu::with_items(app, &items)?
     } else {
         let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-        Menu::with_items(app, &[&show_i, &quit_i])?
+        items.push(&quit_i);
+        Menu::with_items(app, &items)?
     };
 
     tray.set_menu(Some(menu))?;
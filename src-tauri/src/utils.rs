@@ -1,6 +1,58 @@
 // Author: Dustin Pilgrim
 // License: MIT
 
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+static POISON_LOGGED: AtomicBool = AtomicBool::new(false);
+
+/// Recover the data behind a lock a previous holder panicked with, logging
+/// the first time it happens
+fn recover_poisoned<G>(poisoned: std::sync::PoisonError<G>) -> G {
+    if !POISON_LOGGED.swap(true, Ordering::Relaxed) {
+        eprintln!("A lock was poisoned by a panic; recovering its last value");
+    }
+    poisoned.into_inner()
+}
+
+/// Lock `mutex`, recovering the data if a previous holder panicked
+pub fn lock_recover<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(recover_poisoned)
+}
+
+/// `lock_recover` for reading an `RwLock`
+pub fn read_recover<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(recover_poisoned)
+}
+
+/// `lock_recover` for writing an `RwLock`
+pub fn write_recover<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(recover_poisoned)
+}
+
+/// Most recent backend failure, kept for the UI to poll
+static LAST_ERROR: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+/// Log a backend failure and keep it as the last error.
+/// Repeats of the current error are not logged again.
+pub fn record_error(message: impl Into<String>) {
+    let message = message.into();
+    let mut last = lock_recover(&LAST_ERROR);
+    if last.as_deref() != Some(message.as_str()) {
+        eprintln!("{}", message);
+        *last = Some(message);
+    }
+}
+
+pub fn last_error() -> Option<String> {
+    lock_recover(&LAST_ERROR).clone()
+}
+
+pub fn clear_last_error() {
+    *lock_recover(&LAST_ERROR) = None;
+}
+
 pub fn detect_content_type(bytes: &[u8]) -> String {
     if bytes.len() < 4 {
         return "text".to_string();
@@ -72,3 +124,724 @@ pub fn normalize_clipboard_bytes(bytes: &[u8]) -> Vec<u8> {
     // because uri-lists and gnome-copied-files are line-based formats.
     trimmed
 }
+
+/// Transcode non-UTF-8 clipboard text (Latin-1, Windows-1252, UTF-16, ...) to UTF-8.
+/// Returns the UTF-8 bytes and the source encoding name, or `None` when the bytes
+/// are already UTF-8 or no encoding decodes them confidently.
+pub fn transcode_to_utf8(bytes: &[u8]) -> Option<(Vec<u8>, &'static str)> {
+    if bytes.is_empty() || std::str::from_utf8(bytes).is_ok() {
+        return None;
+    }
+
+    let (encoding, body) = match encoding_rs::Encoding::for_bom(bytes) {
+        Some((encoding, bom_len)) => (encoding, &bytes[bom_len..]),
+        None => match guess_utf16(bytes) {
+            Some(encoding) => (encoding, bytes),
+            None => {
+                let mut detector = chardetng::EncodingDetector::new();
+                detector.feed(bytes, true);
+                let (encoding, confident) = detector.guess_assess(None, false);
+                if !confident {
+                    return None;
+                }
+                (encoding, bytes)
+            }
+        },
+    };
+
+    let (text, had_errors) = encoding.decode_without_bom_handling(body);
+    let text = text.trim_end_matches('\0');
+    if had_errors || text.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\r' | '\n')) {
+        return None;
+    }
+
+    Some((text.as_bytes().to_vec(), encoding.name()))
+}
+
+/// BOM-less UTF-16 shows up as NUL bytes in every other position for ASCII-range text
+fn guess_utf16(bytes: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+    if bytes.len() < 4 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let pairs = bytes.len() / 2;
+    let even_nuls = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_nuls = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+
+    if odd_nuls * 10 >= pairs * 7 && even_nuls * 10 <= pairs {
+        Some(encoding_rs::UTF_16LE)
+    } else if even_nuls * 10 >= pairs * 7 && odd_nuls * 10 <= pairs {
+        Some(encoding_rs::UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Bytes used for dedup hashing and storage: text formats are normalized,
+/// images and other binary payloads are kept byte-for-byte so their hash is
+/// stable across reads.
+pub fn canonical_clipboard_bytes(bytes: &[u8]) -> Vec<u8> {
+    let content_type = detect_content_type(bytes);
+    if content_type.starts_with("image/") || content_type == "binary" {
+        bytes.to_vec()
+    } else {
+        normalize_clipboard_bytes(bytes)
+    }
+}
+
+/// Collapse every run of whitespace (spaces, tabs, CR/LF) to one space and
+/// trim the ends, so "hello " and "hello\n" compare equal
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Remove ANSI escape sequences (CSI such as colors and cursor moves, OSC such
+/// as titles and hyperlinks, and two-byte escapes) from terminal text
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI: parameters and intermediates, ended by a byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: ended by BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Any other escape is ESC plus one character
+            _ => {}
+        }
+    }
+
+    out
+}
+
+/// Remove leading and trailing whitespace-only lines, keeping the text
+/// between them (and its line endings) as-is. All-blank text becomes empty.
+pub fn trim_blank_lines(text: &str) -> String {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let Some(first) = lines.iter().position(|l| !l.trim().is_empty()) else {
+        return String::new();
+    };
+    let last = lines.iter().rposition(|l| !l.trim().is_empty()).unwrap_or(first);
+
+    let kept = lines[first..=last].concat();
+    kept.trim_end_matches(['\r', '\n']).to_string()
+}
+
+/// Strip one trailing `\n` or `\r\n`; any further newlines stay
+pub fn trim_trailing_newline(text: &str) -> &str {
+    text.strip_suffix("\r\n")
+        .or_else(|| text.strip_suffix('\n'))
+        .unwrap_or(text)
+}
+
+/// Names accepted by `apply_text_transform`
+pub const TEXT_TRANSFORMS: &[&str] = &[
+    "lowercase",
+    "uppercase",
+    "trim",
+    "collapse_whitespace",
+    "trim_blank_lines",
+    "strip_ansi",
+    "sort_lines",
+    "dedupe_lines",
+];
+
+/// Apply one named transform (see `TEXT_TRANSFORMS`) to entry text
+pub fn apply_text_transform(text: &str, op: &str) -> Result<String, String> {
+    let out = match op {
+        "lowercase" => text.to_lowercase(),
+        "uppercase" => text.to_uppercase(),
+        "trim" => text.trim().to_string(),
+        "collapse_whitespace" => collapse_whitespace(text),
+        "trim_blank_lines" => trim_blank_lines(text),
+        "strip_ansi" => strip_ansi(text),
+        "sort_lines" => {
+            let mut lines: Vec<&str> = text.lines().collect();
+            lines.sort_unstable();
+            lines.join("\n")
+        }
+        "dedupe_lines" => {
+            let mut seen = std::collections::HashSet::new();
+            text.lines().filter(|l| seen.insert(*l)).collect::<Vec<_>>().join("\n")
+        }
+        _ => return Err(format!("Unknown transform \"{}\"", op)),
+    };
+    Ok(out)
+}
+
+/// Placeholders `expand_template` fills in, with what each becomes
+pub const TEMPLATE_VARIABLES: &[(&str, &str)] = &[
+    ("date", "today's date, YYYY-MM-DD"),
+    ("time", "the current time, HH:MM"),
+    ("datetime", "date and time, YYYY-MM-DD HH:MM"),
+    ("clipboard", "what was on the clipboard before the snippet"),
+    ("cursor", "nothing; marks where to carry on typing"),
+];
+
+static TEMPLATE_PLACEHOLDER: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r"\{\{\s*([a-z]+)\s*\}\}").unwrap());
+
+/// Fill `{{name}}` placeholders (see `TEMPLATE_VARIABLES`) in snippet text.
+/// Unknown placeholders are left exactly as written.
+pub fn expand_template(template: &str, clipboard: &str) -> String {
+    let now = chrono::Local::now();
+    TEMPLATE_PLACEHOLDER
+        .replace_all(template, |caps: &regex::Captures| match &caps[1] {
+            "date" => now.format("%Y-%m-%d").to_string(),
+            "time" => now.format("%H:%M").to_string(),
+            "datetime" => now.format("%Y-%m-%d %H:%M").to_string(),
+            "clipboard" => clipboard.to_string(),
+            "cursor" => String::new(),
+            _ => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// Dedup hash of clipboard content (see `canonical_clipboard_bytes`)
+pub fn clipboard_hash(bytes: &[u8]) -> u64 {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    canonical_clipboard_bytes(bytes).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// True when the bytes are a file-manager copy (gnome-copied-files or a
+/// uri-list made only of file:// URIs), as opposed to free text that merely
+/// starts with "file://".
+pub fn is_file_copy(bytes: &[u8]) -> bool {
+    match detect_content_type(bytes).as_str() {
+        "x-special/gnome-copied-files" => true,
+        "text/uri-list" => {
+            let Ok(s) = std::str::from_utf8(bytes) else {
+                return false;
+            };
+            s.lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .all(|l| l.starts_with("file://") && !l.contains(char::is_whitespace))
+        }
+        _ => false,
+    }
+}
+
+const URL_SCHEMES: [&str; 3] = ["http", "https", "ftp"];
+
+/// True when the whole (trimmed) text is a single http/https/ftp URL
+pub fn is_url(text: &str) -> bool {
+    let trimmed = text.trim();
+    if trimmed.is_empty() || trimmed.contains(char::is_whitespace) {
+        return false;
+    }
+
+    match url::Url::parse(trimmed) {
+        Ok(url) => URL_SCHEMES.contains(&url.scheme()) && url.host_str().is_some(),
+        Err(_) => false,
+    }
+}
+
+/// First whitespace-separated token in the text that is a URL
+pub fn extract_first_url(text: &str) -> Option<String> {
+    text.split_whitespace()
+        .find(|token| is_url(token))
+        .map(|token| token.to_string())
+}
+
+/// Local path for a `file://` source, decoding percent-escapes
+pub fn file_path_from_source(src: &str) -> Option<std::path::PathBuf> {
+    let first = src.lines().map(|l| l.trim()).find(|l| l.starts_with("file://"))?;
+    url::Url::parse(first)
+        .ok()
+        .and_then(|u| u.to_file_path().ok())
+        .or_else(|| Some(std::path::PathBuf::from(&first[7..])))
+}
+
+/// Open a path or URL with the desktop's default handler
+pub fn open_with_default(target: &str) -> Result<(), String> {
+    std::process::Command::new("xdg-open")
+        .arg(target)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open {}: {}", target, e))
+}
+
+/// Show a file selected in the file manager, or open its parent directory
+pub fn reveal_path(path: &std::path::Path) -> Result<(), String> {
+    if let Ok(uri) = url::Url::from_file_path(path) {
+        let shown = std::process::Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--dest=org.freedesktop.FileManager1",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{}", uri))
+            .arg("string:")
+            .output()
+            .map(|out| out.status.success())
+            .unwrap_or(false);
+
+        if shown {
+            return Ok(());
+        }
+    }
+
+    let parent = path.parent().ok_or_else(|| "File has no parent directory".to_string())?;
+    open_with_default(&parent.to_string_lossy())
+}
+
+/// Shrink an image so neither side exceeds `max_dimension`, keeping the aspect
+/// ratio. Returns PNG bytes, or None when the image already fits or can't be decoded.
+pub fn downscale_image(bytes: &[u8], max_dimension: u32) -> Option<Vec<u8>> {
+    if max_dimension == 0 {
+        return None;
+    }
+
+    let img = image::load_from_memory(bytes).ok()?;
+    if img.width() <= max_dimension && img.height() <= max_dimension {
+        return None;
+    }
+
+    let resized = img.resize(max_dimension, max_dimension, image::imageops::FilterType::Triangle);
+    let mut out = std::io::Cursor::new(Vec::new());
+    resized.write_to(&mut out, image::ImageFormat::Png).ok()?;
+    Some(out.into_inner())
+}
+
+/// Re-encode an image as `png`, `jpeg` or `webp`. JPEG drops the alpha channel.
+pub fn convert_image(bytes: &[u8], target_format: &str) -> Result<Vec<u8>, String> {
+    let format = match target_format.to_lowercase().as_str() {
+        "png" => image::ImageFormat::Png,
+        "jpeg" | "jpg" => image::ImageFormat::Jpeg,
+        "webp" => image::ImageFormat::WebP,
+        other => return Err(format!("Unsupported image format: {}", other)),
+    };
+
+    let img = image::load_from_memory(bytes).map_err(|e| format!("Failed to decode image: {}", e))?;
+    let img = if format == image::ImageFormat::Jpeg {
+        image::DynamicImage::ImageRgb8(img.to_rgb8())
+    } else {
+        img
+    };
+
+    let mut out = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut out, format)
+        .map_err(|e| format!("Failed to encode image as {}: {}", target_format, e))?;
+    Ok(out.into_inner())
+}
+
+/// Shown for images whose dominant color can't be worked out
+pub const NEUTRAL_COLOR: &str = "#808080";
+
+/// Most common color of an image as `#rrggbb`: pixels of a 64px thumbnail are
+/// bucketed at 4 bits per channel and the fullest bucket is averaged.
+/// Mostly transparent pixels are ignored.
+pub fn dominant_color(bytes: &[u8]) -> Option<String> {
+    let img = image::load_from_memory(bytes).ok()?.thumbnail(64, 64).to_rgba8();
+
+    let mut buckets: std::collections::HashMap<u16, (u64, [u64; 3])> = std::collections::HashMap::new();
+    for pixel in img.pixels() {
+        let [r, g, b, a] = pixel.0;
+        if a < 128 {
+            continue;
+        }
+        let key = (u16::from(r >> 4) << 8) | (u16::from(g >> 4) << 4) | u16::from(b >> 4);
+        let (count, sums) = buckets.entry(key).or_insert((0, [0; 3]));
+        *count += 1;
+        sums[0] += u64::from(r);
+        sums[1] += u64::from(g);
+        sums[2] += u64::from(b);
+    }
+
+    let (count, sums) = buckets.into_values().max_by_key(|(count, _)| *count)?;
+    Some(format!(
+        "#{:02x}{:02x}{:02x}",
+        sums[0] / count,
+        sums[1] / count,
+        sums[2] / count
+    ))
+}
+
+// Bytes of text inspected by `detect_text_subtype`
+const SUBTYPE_SAMPLE_BYTES: usize = 4096;
+// Larger text is never parsed as JSON
+const SUBTYPE_MAX_JSON_BYTES: usize = 1024 * 1024;
+
+/// Best-effort code/markup detection for highlighting, e.g. `text/json`,
+/// `text/html` or `application/x-sh`. Only a prefix is scanned, except for
+/// JSON which must parse in full.
+pub fn detect_text_subtype(text: &str) -> Option<String> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let mut end = trimmed.len().min(SUBTYPE_SAMPLE_BYTES);
+    while !trimmed.is_char_boundary(end) {
+        end -= 1;
+    }
+    let sample = &trimmed[..end];
+
+    if let Some(shebang) = sample.strip_prefix("#!") {
+        let interpreter = shebang.lines().next().unwrap_or("");
+        return if interpreter.contains("python") {
+            Some("text/x-python".to_string())
+        } else if interpreter.contains("node") {
+            Some("text/javascript".to_string())
+        } else if ["sh", "bash", "zsh", "dash", "fish"]
+            .iter()
+            .any(|shell| interpreter.split(['/', ' ']).any(|part| part == *shell))
+        {
+            Some("application/x-sh".to_string())
+        } else {
+            None
+        };
+    }
+
+    if (sample.starts_with('{') || sample.starts_with('['))
+        && trimmed.len() <= SUBTYPE_MAX_JSON_BYTES
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return Some("text/json".to_string());
+    }
+
+    let lower = sample.to_lowercase();
+    if lower.starts_with("<!doctype html")
+        || lower.starts_with("<html")
+        || (lower.starts_with('<') && ["</div>", "</p>", "</span>", "</a>", "</body>"].iter().any(|t| lower.contains(t)))
+    {
+        return Some("text/html".to_string());
+    }
+
+    // Weak signals only count when several show up together
+    let score = |signals: &[&str]| signals.iter().filter(|s| sample.contains(*s)).count();
+    let languages: [(&str, &[&str]); 3] = [
+        ("text/x-rust", &["fn ", "let mut ", "impl ", "pub fn ", "use std::", "-> ", "::new("]),
+        ("text/x-python", &["def ", "import ", "self.", "elif ", "__init__", "):\n"]),
+        ("text/javascript", &["function ", "const ", "=> ", "console.log", "let ", "require("]),
+    ];
+
+    languages
+        .iter()
+        .map(|(subtype, signals)| (*subtype, score(signals)))
+        .filter(|(_, hits)| *hits >= 3)
+        .max_by_key(|(_, hits)| *hits)
+        .map(|(subtype, _)| subtype.to_string())
+}
+
+/// Whether a history content type holds text: plain `text` or one of the
+/// subtypes `detect_text_subtype` refines it to
+pub fn is_text_content_type(content_type: &str) -> bool {
+    content_type == "text" || content_type.starts_with("text/") || content_type == "application/x-sh"
+}
+
+/// File extension for a history content type
+pub fn extension_for_content_type(content_type: &str) -> &'static str {
+    match content_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        "text/uri-list" | "x-special/gnome-copied-files" => "uri",
+        "text/json" => "json",
+        "text/html" => "html",
+        "text/x-rust" => "rs",
+        "text/x-python" => "py",
+        "text/javascript" => "js",
+        "application/x-sh" => "sh",
+        t if is_text_content_type(t) => "txt",
+        _ => "bin",
+    }
+}
+
+/// Temp files handed out for drag-out, removed on exit
+static MATERIALIZED: Lazy<Mutex<Vec<std::path::PathBuf>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Private directory for drag-out files: `$XDG_RUNTIME_DIR/claw`, or else a
+/// fresh randomly named 0700 directory in the system temp dir, since entries
+/// can hold passwords and `/tmp` is shared with other users
+static MATERIALIZE_DIR: Lazy<Mutex<Option<std::path::PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+fn materialize_dir() -> std::io::Result<std::path::PathBuf> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    let mut dir = lock_recover(&MATERIALIZE_DIR);
+    if let Some(path) = dir.as_ref() {
+        return Ok(path.clone());
+    }
+
+    let path = match dirs::runtime_dir() {
+        Some(runtime) => {
+            let path = runtime.join("claw");
+            std::fs::DirBuilder::new().recursive(true).mode(0o700).create(&path)?;
+            path
+        }
+        None => {
+            // Not recursive: fails rather than reuse a directory someone else made
+            let path = std::env::temp_dir().join(format!("claw-{}", uuid::Uuid::new_v4()));
+            std::fs::DirBuilder::new().mode(0o700).create(&path)?;
+            path
+        }
+    };
+    *dir = Some(path.clone());
+    Ok(path)
+}
+
+/// Write entry content to a temp file named after the entry, readable only by
+/// this user, and return its path
+pub fn materialize_entry_file(id: &str, content_type: &str, content: &[u8]) -> std::io::Result<std::path::PathBuf> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let path = materialize_dir()?.join(format!("claw-{}.{}", id, extension_for_content_type(content_type)));
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)?;
+    // `mode` only applies to new files
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(content)?;
+
+    let mut files = lock_recover(&MATERIALIZED);
+    if !files.contains(&path) {
+        files.push(path.clone());
+    }
+    Ok(path)
+}
+
+pub fn cleanup_materialized_files() {
+    let mut files = lock_recover(&MATERIALIZED);
+    for path in files.drain(..) {
+        let _ = std::fs::remove_file(path);
+    }
+    if let Some(dir) = lock_recover(&MATERIALIZE_DIR).take() {
+        // Left in place if anything else is still in it
+        let _ = std::fs::remove_dir(dir);
+    }
+}
+
+pub const DEFAULT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Render a timestamp with a strftime format in `"local"`, `"utc"` or a fixed
+/// offset such as `"+02:00"`. Invalid formats fall back to the default and
+/// unknown zones to local time.
+pub fn format_timestamp(ts: &chrono::DateTime<chrono::Utc>, fmt: &str, tz: &str) -> String {
+    use chrono::format::{Item, StrftimeItems};
+
+    let fmt = if fmt.is_empty() || StrftimeItems::new(fmt).any(|item| matches!(item, Item::Error)) {
+        DEFAULT_TIMESTAMP_FORMAT
+    } else {
+        fmt
+    };
+
+    match tz.trim().to_lowercase().as_str() {
+        "utc" => ts.format(fmt).to_string(),
+        "local" | "" => ts.with_timezone(&chrono::Local).format(fmt).to_string(),
+        offset => match offset.parse::<chrono::FixedOffset>() {
+            Ok(fixed) => ts.with_timezone(&fixed).format(fmt).to_string(),
+            Err(_) => ts.with_timezone(&chrono::Local).format(fmt).to_string(),
+        },
+    }
+}
+
+// Stop counting GIF frames here; "animated" only needs more than one
+const MAX_GIF_FRAMES_COUNTED: usize = 1000;
+
+/// Number of frames (image descriptor blocks) in a GIF, walking the block
+/// structure rather than the pixel data. Returns None for non-GIF bytes.
+/// Counting stops at `MAX_GIF_FRAMES_COUNTED` to bound work on huge files.
+pub fn gif_frame_count(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() < 13 || !(bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a")) {
+        return None;
+    }
+
+    // Skip sub-blocks (length-prefixed, terminated by a zero length)
+    fn skip_sub_blocks(bytes: &[u8], mut pos: usize) -> Option<usize> {
+        loop {
+            let len = *bytes.get(pos)? as usize;
+            pos += 1;
+            if len == 0 {
+                return Some(pos);
+            }
+            pos += len;
+        }
+    }
+
+    let color_table_len = |packed: u8| -> usize {
+        if packed & 0x80 != 0 {
+            3 * (1 << ((packed & 0x07) + 1))
+        } else {
+            0
+        }
+    };
+
+    let mut pos = 13 + color_table_len(bytes[10]);
+    let mut frames = 0;
+
+    while frames < MAX_GIF_FRAMES_COUNTED {
+        match bytes.get(pos) {
+            // Extension: introducer, label, then sub-blocks
+            Some(0x21) => match skip_sub_blocks(bytes, pos + 2) {
+                Some(next) => pos = next,
+                None => break,
+            },
+            // Image descriptor: 10 bytes, optional local color table,
+            // LZW code size byte, then image data sub-blocks
+            Some(0x2C) => {
+                frames += 1;
+                let Some(&packed) = bytes.get(pos + 9) else {
+                    break;
+                };
+                match skip_sub_blocks(bytes, pos + 10 + color_table_len(packed) + 1) {
+                    Some(next) => pos = next,
+                    None => break,
+                }
+            }
+            // Trailer, or anything unexpected/truncated
+            _ => break,
+        }
+    }
+
+    Some(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_recover_returns_data_of_poisoned_lock() {
+        static DATA: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+        *DATA.lock().unwrap() = Some(b"kept".to_vec());
+        let result = std::thread::spawn(|| {
+            let _held = DATA.lock().unwrap();
+            panic!("poisoning the lock on purpose");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(DATA.is_poisoned());
+
+        assert_eq!(lock_recover(&DATA).as_deref(), Some(b"kept".as_slice()));
+        // Writes keep working too
+        *lock_recover(&DATA) = Some(b"next".to_vec());
+        assert_eq!(lock_recover(&DATA).as_deref(), Some(b"next".as_slice()));
+    }
+
+    #[test]
+    fn collapse_whitespace_joins_runs_with_one_space() {
+        assert_eq!(collapse_whitespace("hello   world"), "hello world");
+        assert_eq!(collapse_whitespace("a\tb\nc\r\nd"), "a b c d");
+    }
+
+    #[test]
+    fn collapse_whitespace_drops_leading_and_trailing_whitespace() {
+        assert_eq!(collapse_whitespace("hello "), "hello");
+        assert_eq!(collapse_whitespace("hello\n"), "hello");
+        assert_eq!(collapse_whitespace("  \thello"), "hello");
+        assert_eq!(collapse_whitespace("hello "), collapse_whitespace("hello\n"));
+    }
+
+    #[test]
+    fn collapse_whitespace_of_blank_text_is_empty() {
+        assert_eq!(collapse_whitespace(""), "");
+        assert_eq!(collapse_whitespace(" \t\r\n "), "");
+    }
+
+    #[test]
+    fn collapse_whitespace_keeps_words_apart() {
+        assert_ne!(collapse_whitespace("a b"), collapse_whitespace("ab"));
+    }
+
+    #[test]
+    fn trim_blank_lines_handles_crlf() {
+        assert_eq!(trim_blank_lines("\r\n  code\r\n\r\n"), "  code");
+        // Interior blank lines and line endings are kept
+        assert_eq!(trim_blank_lines("\r\na\r\n\r\nb\r\n\r\n"), "a\r\n\r\nb");
+    }
+
+    #[test]
+    fn trim_blank_lines_treats_tab_only_lines_as_blank() {
+        assert_eq!(trim_blank_lines("\t\n\t \n    fn main() {}\n\t\n"), "    fn main() {}");
+        assert_eq!(trim_blank_lines("a\n\t\nb"), "a\n\t\nb");
+        // Leading indentation of the first kept line survives
+        assert_eq!(trim_blank_lines("\n\tindented\n"), "\tindented");
+    }
+
+    #[test]
+    fn trim_blank_lines_of_all_blank_input_is_empty() {
+        assert_eq!(trim_blank_lines(""), "");
+        assert_eq!(trim_blank_lines("\n\n"), "");
+        assert_eq!(trim_blank_lines(" \n\t\n\r\n"), "");
+    }
+
+    #[test]
+    fn strip_ansi_removes_sgr_colours() {
+        assert_eq!(strip_ansi("\x1b[1;31merror\x1b[0m: failed"), "error: failed");
+        assert_eq!(strip_ansi("\x1b[38;5;208mo\x1b[38;2;10;20;30mk\x1b[m"), "ok");
+    }
+
+    #[test]
+    fn strip_ansi_removes_cursor_movement() {
+        assert_eq!(strip_ansi("\x1b[2Aup\x1b[10;5Hhere\x1b[K"), "uphere");
+        assert_eq!(strip_ansi("\x1b[?25lhidden\x1b[?25h"), "hidden");
+    }
+
+    #[test]
+    fn strip_ansi_removes_osc_sequences() {
+        // Window title ended by BEL
+        assert_eq!(strip_ansi("\x1b]0;~/src\x07$ ls"), "$ ls");
+        // Hyperlink ended by ESC \
+        assert_eq!(strip_ansi("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"), "link");
+    }
+
+    #[test]
+    fn strip_ansi_leaves_plain_text_alone() {
+        assert_eq!(strip_ansi("plain [text] ~ 100%\n"), "plain [text] ~ 100%\n");
+    }
+
+    #[test]
+    fn trim_trailing_newline_strips_lf_and_crlf() {
+        assert_eq!(trim_trailing_newline("line\n"), "line");
+        assert_eq!(trim_trailing_newline("line\r\n"), "line");
+        assert_eq!(trim_trailing_newline("line"), "line");
+    }
+
+    #[test]
+    fn trim_trailing_newline_strips_only_one() {
+        assert_eq!(trim_trailing_newline("line\n\n"), "line\n");
+        assert_eq!(trim_trailing_newline("line\r\n\r\n"), "line\r\n");
+        assert_eq!(trim_trailing_newline("a\nb\n"), "a\nb");
+    }
+
+    #[test]
+    fn trim_trailing_newline_of_lone_newline_is_empty() {
+        assert_eq!(trim_trailing_newline("\n"), "");
+        assert_eq!(trim_trailing_newline("\r\n"), "");
+    }
+}
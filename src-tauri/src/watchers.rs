@@ -1,134 +1,781 @@
 // Author: Dustin Pilgrim
 // License: MIT
 
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 use tokio::sync::RwLock;
 
 use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
 
 use crate::{
     config,
-    utils::{detect_content_type, normalize_clipboard_bytes},
+    history::EntrySource,
+    utils::{
+        canonical_clipboard_bytes, clipboard_hash, detect_content_type, downscale_image, is_file_copy,
+        lock_recover, read_recover, strip_ansi, trim_blank_lines, trim_trailing_newline, write_recover,
+    },
     ConfigUpdate,
 };
 
+/// Times the clipboard watcher has been restarted after stopping or panicking
+static WATCHER_RESTARTS: AtomicU64 = AtomicU64::new(0);
+
+pub fn watcher_restart_count() -> u64 {
+    WATCHER_RESTARTS.load(Ordering::Relaxed)
+}
+
+/// Outcome of the watcher's most recent clipboard read: None before the first
+/// one, then the error message if it failed
+static LAST_READ: Mutex<Option<Result<(), String>>> = Mutex::new(None);
+
+/// Whether the last watcher read succeeded, and its error if it didn't
+pub fn last_read_status() -> (bool, Option<String>) {
+    match &*lock_recover(&LAST_READ) {
+        Some(Ok(())) => (true, None),
+        Some(Err(e)) => (false, Some(e.clone())),
+        None => (false, None),
+    }
+}
+
+fn record_read_result<T>(result: &Result<T, String>) {
+    *lock_recover(&LAST_READ) = Some(result.as_ref().map(|_| ()).map_err(String::clone));
+}
+
+/// Capture latency figures for tuning `poll_interval_ms`
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct WatcherMetrics {
+    /// Estimated delay between the clipboard changing and the watcher noticing,
+    /// for the most recent capture: half the gap since the previous poll plus read time
+    pub last_latency_ms: Option<f64>,
+    pub average_latency_ms: Option<f64>,
+    pub captures: u64,
+    /// Interval the watcher is currently sleeping between polls
+    pub poll_interval_ms: u64,
+}
+
+static METRICS: Mutex<WatcherMetrics> = Mutex::new(WatcherMetrics {
+    last_latency_ms: None,
+    average_latency_ms: None,
+    captures: 0,
+    poll_interval_ms: 0,
+});
+
+pub fn watcher_metrics() -> WatcherMetrics {
+    *lock_recover(&METRICS)
+}
+
+fn record_capture_latency(latency: Duration) {
+    let ms = latency.as_secs_f64() * 1000.0;
+    let mut metrics = lock_recover(&METRICS);
+    let total = metrics.average_latency_ms.unwrap_or(0.0) * metrics.captures as f64 + ms;
+    metrics.captures += 1;
+    metrics.last_latency_ms = Some(ms);
+    metrics.average_latency_ms = Some(total / metrics.captures as f64);
+}
+
+/// Entries recorded by the watcher since claw started, for `get_session_stats`
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct SessionStats {
+    pub captured_this_session: u64,
+    pub bytes_this_session: u64,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+static SESSION_STARTED: Lazy<chrono::DateTime<chrono::Utc>> = Lazy::new(chrono::Utc::now);
+static SESSION_CAPTURES: AtomicU64 = AtomicU64::new(0);
+static SESSION_BYTES: AtomicU64 = AtomicU64::new(0);
+
+pub fn session_stats() -> SessionStats {
+    SessionStats {
+        captured_this_session: SESSION_CAPTURES.load(Ordering::Relaxed),
+        bytes_this_session: SESSION_BYTES.load(Ordering::Relaxed),
+        started_at: *SESSION_STARTED,
+    }
+}
+
+fn record_session_capture(size: usize) {
+    SESSION_CAPTURES.fetch_add(1, Ordering::Relaxed);
+    SESSION_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+}
+
+/// `clipboard.only_record_patterns`, compiled; empty records everything
+static ONLY_RECORD_PATTERNS: Lazy<std::sync::RwLock<Vec<regex::Regex>>> =
+    Lazy::new(|| std::sync::RwLock::new(Vec::new()));
+
+/// Patterns are validated at config load, so any that still fail are skipped
+pub fn set_only_record_patterns(patterns: &[String]) {
+    let compiled = patterns.iter().filter_map(|p| regex::Regex::new(p).ok()).collect();
+    *write_recover(&ONLY_RECORD_PATTERNS) = compiled;
+}
+
+/// Whether the record whitelist lets this content into history. Text must match
+/// a pattern; images pass unless `only_record_excludes_images` is set.
+fn allowed_by_whitelist(content_type: &str, bytes: &[u8], exclude_images: bool) -> bool {
+    let patterns = read_recover(&ONLY_RECORD_PATTERNS);
+    if patterns.is_empty() {
+        return true;
+    }
+    if content_type == "text" {
+        let text = String::from_utf8_lossy(bytes);
+        patterns.iter().any(|re| re.is_match(&text))
+    } else {
+        !(exclude_images && content_type.starts_with("image/"))
+    }
+}
+
+/// Run the clipboard watcher under a supervisor that restarts it with backoff
+/// if the task ever ends (it only does so by panicking).
 pub fn spawn_clipboard_watcher(
     app_handle: AppHandle,
     claw_config: Arc<RwLock<(config::ClipboardConfig, crate::theme::Theme)>>,
 ) {
+    Lazy::force(&SESSION_STARTED);
     tauri::async_runtime::spawn(async move {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        let mut poll_interval_ms = 250u64;
-        let mut last_seen_hash: Option<u64> = None;
-        let mut last_reinject_time = std::time::Instant::now();
-        let mut consecutive_empty_reads = 0u32;
+        let mut backoff_ms = 1000u64;
 
         loop {
-            tokio::time::sleep(tokio::time::Duration::from_millis(poll_interval_ms)).await;
+            let started = std::time::Instant::now();
+            let task = tauri::async_runtime::spawn(run_clipboard_watcher(
+                app_handle.clone(),
+                claw_config.clone(),
+            ));
+
+            match task.await {
+                Ok(()) => eprintln!("Clipboard watcher stopped unexpectedly"),
+                Err(e) => eprintln!("Clipboard watcher crashed: {}", e),
+            }
+
+            // A panic while holding a clipboard lock poisons it; the data is a
+            // plain cache, so it's safe to keep using it.
+            crate::clipboard::clear_poisoned_locks();
+
+            // Reset the backoff if the last run was healthy for a while
+            if started.elapsed().as_secs() > 60 {
+                backoff_ms = 1000;
+            }
+
+            let restarts = WATCHER_RESTARTS.fetch_add(1, Ordering::Relaxed) + 1;
+            eprintln!("Restarting clipboard watcher in {}ms (restart #{})", backoff_ms, restarts);
+            tokio::time::sleep(tokio::time::Duration::from_millis(backoff_ms)).await;
+            backoff_ms = (backoff_ms * 2).min(30_000);
+        }
+    });
+}
+
+/// How a clipboard read relates to what the watcher saw and recorded before
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClipboardChange {
+    /// Same content as the previous read; only keep-alive applies
+    Unchanged,
+    /// Something re-set the clipboard to the content recorded last (ownership
+    /// changed, content didn't), or to content recorded within
+    /// `recapture_cooldown_secs`; no new entry
+    OwnerChanged,
+    /// Content that hasn't been recorded yet
+    ContentChanged,
+}
+
+/// Decide what a read with `content_hash` means, given the hash of the
+/// previous read and of the last content added to history. `in_cooldown` is
+/// whether the same content was recorded less than `recapture_cooldown_secs` ago.
+fn classify_change(
+    content_hash: u64,
+    last_seen: Option<u64>,
+    last_recorded: Option<u64>,
+    in_cooldown: bool,
+) -> ClipboardChange {
+    if last_seen == Some(content_hash) {
+        ClipboardChange::Unchanged
+    } else if last_recorded == Some(content_hash) || in_cooldown {
+        ClipboardChange::OwnerChanged
+    } else {
+        ClipboardChange::ContentChanged
+    }
+}
+
+/// Whether `content_hash` was recorded less than `cooldown` ago
+fn in_recapture_cooldown(recent_captures: &HashMap<u64, Instant>, content_hash: u64, cooldown: Duration) -> bool {
+    recent_captures
+        .get(&content_hash)
+        .is_some_and(|recorded_at| recorded_at.elapsed() < cooldown)
+}
+
+/// Poll interval while the clipboard is unchanged. Stays at the active 250ms
+/// until it has been idle for `after_secs`, then grows by `percent` per poll up
+/// to `max_ms`; the next change resets it. `after_secs = 0` disables back-off.
+fn idle_poll_interval(current_ms: u64, idle: Duration, (after_secs, max_ms, percent): (u64, u64, u64)) -> u64 {
+    const ACTIVE_POLL_MS: u64 = 250;
+
+    if after_secs == 0 || idle < Duration::from_secs(after_secs) {
+        return ACTIVE_POLL_MS;
+    }
+    let current = current_ms.max(ACTIVE_POLL_MS);
+    (current + current * percent / 100).min(max_ms.max(ACTIVE_POLL_MS))
+}
 
-            let Ok(content_bytes) = crate::clipboard::get_clipboard() else {
+async fn run_clipboard_watcher(
+    app_handle: AppHandle,
+    claw_config: Arc<RwLock<(config::ClipboardConfig, crate::theme::Theme)>>,
+) {
+    let mut poll_interval_ms = 250u64;
+    let mut last_seen_hash: Option<u64> = None;
+    let mut last_recorded_hash: Option<u64> = None;
+    // When each recently recorded hash was recorded, for `recapture_cooldown_secs`
+    let mut recent_captures: HashMap<u64, Instant> = HashMap::new();
+    let mut last_reinject_time = std::time::Instant::now();
+    let mut consecutive_empty_reads = 0u32;
+    let mut last_poll = Instant::now();
+    let mut last_notified: Option<Instant> = None;
+    let mut last_change = Instant::now();
+
+    loop {
+        lock_recover(&METRICS).poll_interval_ms = poll_interval_ms;
+        tokio::time::sleep(tokio::time::Duration::from_millis(poll_interval_ms)).await;
+
+        let poll_started = Instant::now();
+        let since_last_poll = poll_started.duration_since(last_poll);
+        last_poll = poll_started;
+
+        let read = crate::clipboard::get_clipboard();
+        record_read_result(&read);
+        let (content_bytes, selection) = match read {
+            Ok(read) => read,
+            Err(e) => {
+                crate::utils::record_error(format!("Failed to read clipboard: {}", e));
                 poll_interval_ms = 1000;
                 continue;
-            };
+            }
+        };
 
-            // Check if clipboard is empty/invalid
-            if crate::clipboard::should_ignore_bytes(&content_bytes) {
-                consecutive_empty_reads += 1;
+        // Check if clipboard is empty/invalid
+        if crate::clipboard::should_ignore_bytes(&content_bytes) {
+            consecutive_empty_reads += 1;
 
-                // Reset last_seen_hash if persistent memory is cleared
-                if crate::clipboard::get_persistent_clipboard().is_none() {
-                    if last_seen_hash.is_some() {
-                        last_seen_hash = None;
-                    }
+            // Reset last_seen_hash if persistent memory is cleared
+            if crate::clipboard::get_persistent_clipboard().is_none() {
+                if last_seen_hash.is_some() {
+                    last_seen_hash = None;
                 }
+                last_recorded_hash = None;
+            }
 
-                if consecutive_empty_reads >= 3 {
-                    if let Some(persistent_data) = crate::clipboard::get_persistent_clipboard() {
-                        if !crate::clipboard::should_ignore_bytes(&persistent_data) {
-                            let _ = crate::clipboard::set_clipboard_no_hash(&persistent_data);
-
-                            let normalized = normalize_clipboard_bytes(&persistent_data);
-                            let mut hasher = DefaultHasher::new();
-                            normalized.hash(&mut hasher);
-                            last_seen_hash = Some(hasher.finish());
-                            last_reinject_time = std::time::Instant::now();
-                            consecutive_empty_reads = 0;
-                        }
+            if consecutive_empty_reads >= 3 {
+                if let Some(persistent_data) = crate::clipboard::get_persistent_clipboard() {
+                    if !crate::clipboard::should_ignore_bytes(&persistent_data) {
+                        let _ = crate::clipboard::set_clipboard_no_hash(&persistent_data);
+
+                        last_seen_hash = Some(clipboard_hash(&persistent_data));
+                        last_reinject_time = std::time::Instant::now();
+                        consecutive_empty_reads = 0;
                     }
                 }
-
-                drop(content_bytes);
-                poll_interval_ms = 1000;
-                continue;
             }
 
-            consecutive_empty_reads = 0;
+            drop(content_bytes);
+            poll_interval_ms = 1000;
+            continue;
+        }
+
+        consecutive_empty_reads = 0;
+
+        // Normalize text conservatively (utils.rs preserves line formats);
+        // images are hashed and stored exactly as read
+        let mut normalized = canonical_clipboard_bytes(&content_bytes);
+        let content_hash = clipboard_hash(&content_bytes);
 
-            // Normalize conservatively (utils.rs now preserves line formats)
-            let normalized = normalize_clipboard_bytes(&content_bytes);
-            let mut hasher = DefaultHasher::new();
-            normalized.hash(&mut hasher);
-            let content_hash = hasher.finish();
+        let in_cooldown = recent_captures.contains_key(&content_hash) && {
+            let cooldown = claw_config.read().await.0.recapture_cooldown_secs;
+            in_recapture_cooldown(&recent_captures, content_hash, Duration::from_secs(cooldown))
+        };
 
+        match classify_change(content_hash, last_seen_hash, last_recorded_hash, in_cooldown) {
             // Same content as before - just maintain it
-            if Some(content_hash) == last_seen_hash {
+            ClipboardChange::Unchanged => {
                 let elapsed = last_reinject_time.elapsed();
-                if elapsed.as_secs() >= 2 {
+                if elapsed.as_secs() >= 2 && selection.keeps_alive() {
                     let _ = crate::clipboard::set_clipboard_no_hash(&content_bytes);
                     last_reinject_time = std::time::Instant::now();
                 }
                 drop(content_bytes);
                 drop(normalized);
+                let backoff = {
+                    let cfg = claw_config.read().await;
+                    (cfg.0.idle_after_secs, cfg.0.idle_poll_max_ms, cfg.0.idle_backoff_percent)
+                };
+                poll_interval_ms = idle_poll_interval(poll_interval_ms, last_change.elapsed(), backoff);
+                continue;
+            }
+            // Re-asserted by its owner: refresh timing, but don't record it again
+            ClipboardChange::OwnerChanged => {
+                last_change = Instant::now();
+                last_seen_hash = Some(content_hash);
+                last_reinject_time = std::time::Instant::now();
+                if selection.keeps_alive() {
+                    crate::clipboard::cache_clipboard_data(&content_bytes);
+                }
+                drop(content_bytes);
+                drop(normalized);
                 poll_interval_ms = 250;
                 continue;
             }
+            ClipboardChange::ContentChanged => {}
+        }
 
-            // New content detected
-            last_seen_hash = Some(content_hash);
-            last_reinject_time = std::time::Instant::now();
+        // New content detected
+        last_change = Instant::now();
+        last_seen_hash = Some(content_hash);
+        last_reinject_time = std::time::Instant::now();
 
+        if selection.keeps_alive() {
             crate::clipboard::cache_clipboard_data(&content_bytes);
+        }
 
-            {
-                let last = crate::LAST_WRITTEN_CLIPBOARD.lock().unwrap();
-                if Some(content_hash) == *last {
-                    drop(content_bytes);
-                    drop(normalized);
-                    poll_interval_ms = 250;
-                    continue;
-                }
+        {
+            let last = lock_recover(&crate::LAST_WRITTEN_CLIPBOARD);
+            if Some(content_hash) == *last {
+                drop(content_bytes);
+                drop(normalized);
+                poll_interval_ms = 250;
+                continue;
             }
+        }
 
-            poll_interval_ms = 250;
+        poll_interval_ms = 250;
+        // Counted once the entry is actually recorded
+        let capture_latency = since_last_poll / 2 + poll_started.elapsed();
 
-            drop(content_bytes);
+        drop(content_bytes);
+
+        // The picker is open: selections made there must not be re-recorded.
+        // The content is still cached above so keep-alive maintains it.
+        if crate::window::is_main_window_visible() {
+            drop(normalized);
+            continue;
+        }
+
+        if normalized.is_empty() || crate::clipboard::should_ignore_bytes(&normalized) {
+            drop(normalized);
+            continue;
+        }
+
+        // Everything the owner offers is in `ignore_mimes`
+        if crate::clipboard::only_ignored_mimes_offered() {
+            drop(normalized);
+            continue;
+        }
 
-            if normalized.is_empty() || crate::clipboard::should_ignore_bytes(&normalized) {
+        let settings = claw_config.read().await.0.clone();
+
+        // Manual mode: keep the selection alive but only record on request
+        if settings.capture_mode == "manual" {
+            drop(normalized);
+            continue;
+        }
+
+        // Locked history: keep the selection alive, never record
+        if crate::history::history_locked() {
+            drop(normalized);
+            continue;
+        }
+
+        let history_limit = settings.max_entries();
+        let content_type = detect_content_type(&normalized);
+
+        // Images stay on the clipboard but are not recorded when disabled
+        if !settings.record_images && content_type.starts_with("image/") {
+            drop(normalized);
+            continue;
+        }
+
+        // File copies stay on the clipboard but are not recorded when disabled
+        if !settings.record_files && is_file_copy(&normalized) {
+            drop(normalized);
+            continue;
+        }
+
+        // Stored and deduped in stripped form, so toggling this doesn't create duplicates
+        if content_type == "text" && settings.strip_ansi {
+            let stripped = strip_ansi(&String::from_utf8_lossy(&normalized));
+            if stripped.trim().is_empty() {
+                drop(normalized);
+                continue;
+            }
+            normalized = stripped.into_bytes();
+        }
+
+        if content_type == "text" && settings.trim_blank_lines {
+            let trimmed = trim_blank_lines(&String::from_utf8_lossy(&normalized));
+            if trimmed.is_empty() {
+                drop(normalized);
+                continue;
+            }
+            normalized = trimmed.into_bytes();
+        }
+
+        // Stored (and so deduped) without it
+        if content_type == "text" && settings.trim_trailing_newline {
+            let trimmed = trim_trailing_newline(&String::from_utf8_lossy(&normalized)).to_string();
+            if trimmed.is_empty() {
                 drop(normalized);
                 continue;
             }
+            normalized = trimmed.into_bytes();
+        }
 
-            let history_limit = claw_config.read().await.0.history_limit as usize;
-            let content_type = detect_content_type(&normalized);
+        // Skip trivially short text copies (images and file lists are never affected)
+        if content_type == "text" && settings.min_text_length > 0 {
+            let trimmed_len = String::from_utf8_lossy(&normalized).trim().chars().count();
+            if trimmed_len < settings.min_text_length {
+                drop(normalized);
+                continue;
+            }
+        }
+
+        // Whitelist comes last, so every ignore rule above wins over it
+        if !allowed_by_whitelist(&content_type, &normalized, settings.only_record_excludes_images) {
+            drop(normalized);
+            continue;
+        }
 
-            if let Err(e) = crate::history::add_to_history(
+        // Oversized images are stored shrunk; the clipboard keeps the original
+        let downscaled = if content_type.starts_with("image/") {
+            downscale_image(&normalized, settings.max_image_dimension)
+        } else {
+            None
+        };
+
+        let source_app = crate::detect::current_focused_app();
+        let result = match downscaled {
+            Some(small) => crate::history::add_downscaled_image_to_history(
+                &app_handle,
+                &small,
+                detect_content_type(&small),
+                history_limit,
+                source_app,
+                settings.dedup_images(),
+            ),
+            None => crate::history::add_to_history(
                 &app_handle,
                 &normalized,
                 content_type,
                 history_limit,
-                None,
-            ) {
-                eprintln!("Failed to add to history: {}", e);
-            } else {
+                EntrySource {
+                    path: None,
+                    app: source_app,
+                    encoding: crate::clipboard::last_read_encoding().map(str::to_string),
+                    mimes: crate::clipboard::offered_mimes(),
+                },
+                settings.dedup_ignore_whitespace,
+                settings.dedup_images(),
+            ),
+        };
+
+        if let Err(e) = result {
+            crate::utils::record_error(format!("Failed to add to history: {}", e));
+        } else {
+            record_capture_latency(capture_latency);
+            last_recorded_hash = Some(content_hash);
+            if settings.recapture_cooldown_secs > 0 {
+                let cooldown = Duration::from_secs(settings.recapture_cooldown_secs);
+                recent_captures.retain(|_, at| at.elapsed() < cooldown);
+                recent_captures.insert(content_hash, Instant::now());
+            }
+            record_session_capture(normalized.len());
+            let _ = app_handle.emit("history-updated", "");
+
+            let due = last_notified.is_none_or(|at| at.elapsed() >= CAPTURE_NOTIFY_INTERVAL);
+            if settings.notify_on_capture && due {
+                notify_capture(&app_handle, &normalized);
+                last_notified = Some(Instant::now());
+            }
+        }
+
+        drop(normalized);
+    }
+}
+
+/// Config reloads are skipped until this instant (set by `pause_config_watch`)
+static CONFIG_WATCH_PAUSED_UNTIL: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Set when a config change was skipped during a pause, so it is reloaded
+/// once the pause lifts
+static CONFIG_RELOAD_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// How often the config watcher checks whether a pause has timed out
+const CONFIG_PAUSE_POLL: Duration = Duration::from_secs(1);
+
+/// Pause config reloads for at most `timeout` so a half-written file isn't loaded.
+/// The pause lifts by itself once the timeout passes, reloading any edits made
+/// meanwhile.
+pub fn pause_config_watch(timeout: Duration) {
+    *lock_recover(&CONFIG_WATCH_PAUSED_UNTIL) = Some(Instant::now() + timeout);
+}
+
+/// Lift a pause. The caller reloads, so skipped edits are no longer pending.
+pub fn resume_config_watch() {
+    *lock_recover(&CONFIG_WATCH_PAUSED_UNTIL) = None;
+    CONFIG_RELOAD_PENDING.store(false, Ordering::Relaxed);
+}
+
+fn config_watch_paused() -> bool {
+    let mut paused = lock_recover(&CONFIG_WATCH_PAUSED_UNTIL);
+    match *paused {
+        Some(until) if Instant::now() < until => true,
+        Some(_) => {
+            *paused = None;
+            false
+        }
+        None => false,
+    }
+}
+
+/// Rapid copies get one capture notification per interval, not one each
+const CAPTURE_NOTIFY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Desktop notification for `notify_on_capture`, e.g. "📝 Copied" / "text: the quick…"
+fn notify_capture(app_handle: &AppHandle, content: &[u8]) {
+    use tauri_plugin_notification::NotificationExt;
+
+    let content_type = crate::history::refine_content_type(content, detect_content_type(content));
+    let result = app_handle
+        .notification()
+        .builder()
+        .title(format!("{} Copied", crate::tray::content_type_icon(&content_type)))
+        .body(crate::preview::capture_summary(content, &content_type))
+        .show();
+    if let Err(e) = result {
+        eprintln!("Failed to show capture notification: {}", e);
+    }
+}
+
+/// What the watcher would make of the current clipboard, for diagnostics
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ClipboardExplain {
+    pub content_type: String,
+    pub size: usize,
+    pub ignored: bool,
+    /// Which rule skips the content, when `ignored`
+    pub ignore_reason: Option<String>,
+}
+
+/// Run the current clipboard through the watcher's filters without recording it.
+/// Checks are in the watcher's order, so the reason is the first rule that hits.
+pub fn explain_clipboard(settings: &config::ClipboardConfig) -> Result<ClipboardExplain, String> {
+    let (content, _) = crate::clipboard::get_clipboard()?;
+    let normalized = canonical_clipboard_bytes(&content);
+    let content_type = detect_content_type(&normalized);
+
+    let self_written =
+        *lock_recover(&crate::LAST_WRITTEN_CLIPBOARD) == Some(clipboard_hash(&content));
+    let text = || String::from_utf8_lossy(&normalized).to_string();
+    let reason = if let Some(reason) = crate::clipboard::ignore_reason(&normalized) {
+        Some(format!("Ignored content: {}", reason))
+    } else if self_written {
+        Some("Written by claw itself".to_string())
+    } else if crate::clipboard::only_ignored_mimes_offered() {
+        Some("Every offered type matches clipboard.ignore_mimes".to_string())
+    } else if crate::history::history_locked() {
+        Some("lock_history is on".to_string())
+    } else if settings.capture_mode == "manual" {
+        Some("capture_mode is \"manual\"".to_string())
+    } else if !settings.record_images && content_type.starts_with("image/") {
+        Some("record_images is off".to_string())
+    } else if !settings.record_files && is_file_copy(&normalized) {
+        Some("record_files is off".to_string())
+    } else if content_type == "text" && settings.strip_ansi && strip_ansi(&text()).trim().is_empty() {
+        Some("Nothing left after strip_ansi".to_string())
+    } else if content_type == "text" && settings.trim_blank_lines && trim_blank_lines(&text()).is_empty() {
+        Some("Nothing left after trim_blank_lines".to_string())
+    } else if content_type == "text"
+        && settings.trim_trailing_newline
+        && trim_trailing_newline(&text()).is_empty()
+    {
+        Some("Nothing left after trim_trailing_newline".to_string())
+    } else if content_type == "text"
+        && settings.min_text_length > 0
+        && text().trim().chars().count() < settings.min_text_length
+    {
+        Some(format!("Shorter than min_text_length ({})", settings.min_text_length))
+    } else if !allowed_by_whitelist(&content_type, &normalized, settings.only_record_excludes_images) {
+        Some("Not matched by clipboard.only_record_patterns".to_string())
+    } else {
+        None
+    };
+
+    Ok(ClipboardExplain {
+        content_type,
+        size: content.len(),
+        ignored: reason.is_some(),
+        ignore_reason: reason,
+    })
+}
+
+/// Record what is on the clipboard right now. This is the only way entries
+/// are added from outside claw in `capture_mode = "manual"`.
+pub fn capture_now(app_handle: &AppHandle, settings: &config::ClipboardConfig) -> Result<(), String> {
+    if crate::history::history_locked() {
+        return Err("History is locked (clipboard.lock_history)".to_string());
+    }
+    let (content, _) = crate::clipboard::get_clipboard()?;
+    let normalized = canonical_clipboard_bytes(&content);
+    if normalized.is_empty() || crate::clipboard::should_ignore_bytes(&normalized) {
+        return Err("Clipboard is empty".to_string());
+    }
+
+    let history_limit = settings.max_entries();
+    let content_type = detect_content_type(&normalized);
+    let downscaled = if content_type.starts_with("image/") {
+        downscale_image(&normalized, settings.max_image_dimension)
+    } else {
+        None
+    };
+
+    let source_app = crate::detect::current_focused_app();
+    match downscaled {
+        Some(small) => crate::history::add_downscaled_image_to_history(
+            app_handle,
+            &small,
+            detect_content_type(&small),
+            history_limit,
+            source_app,
+            settings.dedup_images(),
+        ),
+        None => crate::history::add_to_history(
+            app_handle,
+            &normalized,
+            content_type,
+            history_limit,
+            EntrySource {
+                path: None,
+                app: source_app,
+                encoding: crate::clipboard::last_read_encoding().map(str::to_string),
+                mimes: crate::clipboard::offered_mimes(),
+            },
+            settings.dedup_ignore_whitespace,
+            settings.dedup_images(),
+        ),
+    }?;
+
+    record_session_capture(normalized.len());
+    let _ = app_handle.emit("history-updated", "");
+    Ok(())
+}
+
+/// Reload config from disk, apply it and notify the frontend
+pub async fn reload_config(
+    app_handle: &AppHandle,
+    claw_config: &Arc<RwLock<(config::ClipboardConfig, crate::theme::Theme)>>,
+) -> Result<(), String> {
+    let path = config::find_config().ok_or_else(|| "No claw.rune config found".to_string())?;
+    let new_config = config::load_config(&path.to_string_lossy()).map_err(|e| {
+        crate::utils::record_error(format!("Failed to reload config: {}", e));
+        e.to_string()
+    })?;
+
+    let (new_config, profile_changed) = {
+        let mut cfg = claw_config.write().await;
+        // The session limit outlives reloads, it only ends with the app
+        let session_history_limit = cfg.0.session_history_limit;
+        let profile_changed = cfg.0.profile != new_config.0.profile;
+        *cfg = new_config;
+        cfg.0.session_history_limit = session_history_limit;
+        (cfg.clone(), profile_changed)
+    };
+
+    // Only an edited `profile` switches; a profile picked at runtime survives other edits
+    if profile_changed {
+        match crate::history::set_active_profile(&new_config.0.profile) {
+            Ok(()) => {
                 let _ = app_handle.emit("history-updated", "");
             }
+            Err(e) => crate::utils::record_error(e),
+        }
+    }
+
+    crate::window::apply_window_config(app_handle, &new_config.0);
+    crate::clipboard::set_max_read_bytes(new_config.0.max_read_bytes);
+    crate::history::set_binary_store(new_config.0.store_format == "bincode");
+    crate::history::set_oversize_policy(&new_config.0.on_oversize);
+    crate::history::set_low_memory(new_config.0.low_memory);
+    crate::clipboard::set_ignore_mimes(new_config.0.ignore_mimes.clone());
+    crate::clipboard::set_x11_fallback_to_primary(new_config.0.x11_fallback_to_primary);
+    crate::preview::set_redact_patterns(&new_config.0.redact_patterns);
+    set_only_record_patterns(&new_config.0.only_record_patterns);
+    crate::history::set_type_limits(new_config.0.max_text_entries, new_config.0.max_image_entries);
+    crate::history::set_auto_pin_recent(new_config.0.auto_pin_recent);
+    crate::history::set_history_locked(new_config.0.lock_history);
+    // Tray options and capture mode show up in the tray menu
+    let _ = crate::tray::update_tray_menu(app_handle, crate::tray::TRAY_ID);
+
+    let update = ConfigUpdate {
+        enable_titlebar: new_config.0.enable_titlebar,
+        force_dark_mode: new_config.0.force_dark_mode,
+        theme: new_config.1.clone(),
+    };
+
+    let _ = app_handle.emit("config-reloaded", update);
+    Ok(())
+}
 
-            drop(normalized);
+/// The config watcher and the files it watches, shared so `rescan_gather_files`
+/// can update them without waiting for the main config to change
+struct ConfigWatch {
+    watcher: RecommendedWatcher,
+    main_config_path: PathBuf,
+    local_config_path: Option<PathBuf>,
+    paths: HashSet<PathBuf>,
+}
+
+static CONFIG_WATCH: Mutex<Option<ConfigWatch>> = Mutex::new(None);
+
+/// Files the config watcher is currently watching, for `get_watched_paths`
+pub fn watched_paths() -> Vec<String> {
+    let guard = lock_recover(&CONFIG_WATCH);
+    let mut list: Vec<String> = guard
+        .iter()
+        .flat_map(|watch| watch.paths.iter())
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    list.sort();
+    list
+}
+
+/// Recompute the gather files from the main config and watch new ones (and
+/// stop watching dropped ones) right away. Returns the watched files.
+pub fn rescan_watched_paths() -> Result<Vec<String>, String> {
+    {
+        let mut guard = lock_recover(&CONFIG_WATCH);
+        let watch = guard
+            .as_mut()
+            .ok_or_else(|| "Config watcher is not running".to_string())?;
+
+        let new_paths: HashSet<PathBuf> = gather_paths(&watch.main_config_path)
+            .into_iter()
+            .chain(Some(watch.main_config_path.clone()))
+            .chain(watch.local_config_path.clone())
+            .collect();
+        for path in new_paths.difference(&watch.paths) {
+            watch.watcher.watch(path, RecursiveMode::NonRecursive).ok();
         }
-    });
+        for path in watch.paths.difference(&new_paths) {
+            watch.watcher.unwatch(path).ok();
+        }
+        watch.paths = new_paths;
+    }
+    Ok(watched_paths())
+}
+
+fn gather_paths(main_config_path: &Path) -> Vec<PathBuf> {
+    config::gather_statements(main_config_path)
+        .into_iter()
+        .map(|(path, _)| path)
+        .filter(|path| path.exists())
+        .collect()
 }
 
 pub fn spawn_config_watcher(
@@ -137,80 +784,141 @@ pub fn spawn_config_watcher(
 ) {
     tauri::async_runtime::spawn(async move {
         use notify::Config;
-        use std::collections::HashSet;
-        use std::path::PathBuf;
-        use std::sync::mpsc::channel;
+        use std::sync::mpsc::{channel, RecvTimeoutError};
 
         let main_config_path: PathBuf = config::find_config().expect("No claw.rune config found");
 
-        let mut watched_paths = HashSet::new();
-        watched_paths.insert(main_config_path.clone());
-
-        let gather_paths = || -> Vec<PathBuf> {
-            let content = std::fs::read_to_string(&main_config_path).unwrap_or_default();
-            let gather_regex = regex::Regex::new(r#"gather\s+"([^"]+)"(?:\s+as\s+(\w+))?"#).unwrap();
-            gather_regex
-                .captures_iter(&content)
-                .filter_map(|cap| {
-                    let path_str = &cap[1];
-                    let expanded_path = if path_str.starts_with("~/") {
-                        dirs::home_dir().map(|h| h.join(&path_str[2..]))
-                    } else {
-                        Some(PathBuf::from(path_str))
-                    }?;
-                    if expanded_path.exists() {
-                        Some(expanded_path)
-                    } else {
-                        None
-                    }
-                })
-                .collect()
-        };
+        let mut paths = HashSet::new();
+        paths.insert(main_config_path.clone());
 
-        for path in gather_paths() {
-            watched_paths.insert(path);
+        // The local overlay is reloaded together with the main config
+        let local_config_path = config::find_local_config();
+        if let Some(path) = &local_config_path {
+            paths.insert(path.clone());
+        }
+
+        for path in gather_paths(&main_config_path) {
+            paths.insert(path);
         }
 
         let (tx, rx) = channel();
         let mut watcher: RecommendedWatcher =
             Watcher::new(tx, Config::default()).expect("Failed to create file watcher");
 
-        for path in &watched_paths {
+        for path in &paths {
             watcher.watch(path, RecursiveMode::NonRecursive).expect("Failed to watch file");
         }
+        *lock_recover(&CONFIG_WATCH) = Some(ConfigWatch {
+            watcher,
+            main_config_path,
+            local_config_path,
+            paths,
+        });
 
         loop {
-            match rx.recv() {
+            match rx.recv_timeout(CONFIG_PAUSE_POLL) {
                 Ok(event) => {
                     if let Ok(ev) = event {
                         if let EventKind::Modify(_) = ev.kind {
-                            if let Ok(new_config) = config::load_config(&main_config_path.to_string_lossy()) {
-                                *claw_config.write().await = new_config.clone();
-
-                                let update = ConfigUpdate {
-                                    enable_titlebar: new_config.0.enable_titlebar,
-                                    force_dark_mode: new_config.0.force_dark_mode,
-                                    theme: new_config.1.clone(),
-                                };
-
-                                let _ = app_handle.emit("config-reloaded", update);
-
-                                let new_paths: HashSet<_> = gather_paths().into_iter().collect();
-                                for path in new_paths.difference(&watched_paths) {
-                                    watcher.watch(path, RecursiveMode::NonRecursive).ok();
-                                }
-                                watched_paths = new_paths
-                                    .union(&[main_config_path.clone()].into_iter().collect())
-                                    .cloned()
-                                    .collect();
-                            } else {
-                                eprintln!("Failed to reload config");
+                            // UI-driven edits pause reloads; they reload once on resume
+                            if config_watch_paused() {
+                                CONFIG_RELOAD_PENDING.store(true, Ordering::Relaxed);
+                                continue;
+                            }
+
+                            if reload_config(&app_handle, &claw_config).await.is_ok() {
+                                let _ = rescan_watched_paths();
                             }
                         }
                     }
                 }
+                // A pause that timed out without a resume still owes a reload
+                Err(RecvTimeoutError::Timeout) => {
+                    if !config_watch_paused()
+                        && CONFIG_RELOAD_PENDING.swap(false, Ordering::Relaxed)
+                        && reload_config(&app_handle, &claw_config).await.is_ok()
+                    {
+                        let _ = rescan_watched_paths();
+                    }
+                }
                 Err(e) => eprintln!("Watch error: {:?}", e),
             }
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: u64 = 0xA;
+    const B: u64 = 0xB;
+
+    #[test]
+    fn classify_change_first_read_is_new_content() {
+        assert_eq!(classify_change(A, None, None, false), ClipboardChange::ContentChanged);
+    }
+
+    #[test]
+    fn classify_change_same_read_is_unchanged() {
+        assert_eq!(classify_change(A, Some(A), Some(A), false), ClipboardChange::Unchanged);
+        // Content that was seen but never recorded (e.g. ignored) stays unchanged too
+        assert_eq!(classify_change(A, Some(A), None, false), ClipboardChange::Unchanged);
+    }
+
+    #[test]
+    fn classify_change_reasserted_content_is_owner_change() {
+        // An app re-set the clipboard to what was recorded last after a read
+        // that differed (e.g. an empty or ignored read in between)
+        assert_eq!(classify_change(A, Some(B), Some(A), false), ClipboardChange::OwnerChanged);
+        assert_eq!(classify_change(A, None, Some(A), false), ClipboardChange::OwnerChanged);
+    }
+
+    #[test]
+    fn classify_change_different_content_is_new_content() {
+        assert_eq!(classify_change(B, Some(A), Some(A), false), ClipboardChange::ContentChanged);
+        assert_eq!(classify_change(B, Some(A), None, false), ClipboardChange::ContentChanged);
+    }
+
+    #[test]
+    fn classify_change_recapture_within_cooldown_is_owner_change() {
+        // A copied, then B, then A again while A's cooldown is running
+        let mut recent = HashMap::new();
+        recent.insert(A, Instant::now());
+        let in_cooldown = in_recapture_cooldown(&recent, A, Duration::from_secs(30));
+        assert!(in_cooldown);
+        assert_eq!(classify_change(A, Some(B), Some(B), in_cooldown), ClipboardChange::OwnerChanged);
+    }
+
+    #[test]
+    fn classify_change_recapture_after_cooldown_is_new_content() {
+        let mut recent = HashMap::new();
+        recent.insert(A, Instant::now() - Duration::from_secs(31));
+        let in_cooldown = in_recapture_cooldown(&recent, A, Duration::from_secs(30));
+        assert!(!in_cooldown);
+        assert_eq!(classify_change(A, Some(B), Some(B), in_cooldown), ClipboardChange::ContentChanged);
+    }
+
+    #[test]
+    fn classify_change_cooldown_off_never_suppresses() {
+        let mut recent = HashMap::new();
+        recent.insert(A, Instant::now());
+        let in_cooldown = in_recapture_cooldown(&recent, A, Duration::ZERO);
+        assert!(!in_cooldown);
+        assert_eq!(classify_change(A, Some(B), Some(B), in_cooldown), ClipboardChange::ContentChanged);
+    }
+
+    #[test]
+    fn classify_change_cooldown_only_covers_recorded_content() {
+        let mut recent = HashMap::new();
+        recent.insert(A, Instant::now());
+        let in_cooldown = in_recapture_cooldown(&recent, B, Duration::from_secs(30));
+        assert!(!in_cooldown);
+        assert_eq!(classify_change(B, Some(A), Some(A), in_cooldown), ClipboardChange::ContentChanged);
+    }
+
+    #[test]
+    fn classify_change_unchanged_wins_over_cooldown() {
+        assert_eq!(classify_change(A, Some(A), Some(A), true), ClipboardChange::Unchanged);
+    }
+}
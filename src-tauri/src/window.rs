@@ -1,12 +1,33 @@
-use tauri::{AppHandle, Manager};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, LogicalSize, Manager, PhysicalPosition, Theme, WebviewWindow};
+
+use crate::config::{current_clipboard_config, ClipboardConfig};
+
+const MIN_WINDOW_WIDTH: u32 = 300;
+const MIN_WINDOW_HEIGHT: u32 = 200;
+
+/// Whether the picker is on screen; the clipboard watcher pauses capture while it is
+static MAIN_WINDOW_VISIBLE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_main_window_visible() -> bool {
+    MAIN_WINDOW_VISIBLE.load(Ordering::Relaxed)
+}
+
+pub fn set_main_window_visible(visible: bool) {
+    MAIN_WINDOW_VISIBLE.store(visible, Ordering::Relaxed);
+}
 
 pub fn toggle_main_window(app: &AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         if window.is_visible().unwrap_or(false) {
             let _ = window.hide();
+            set_main_window_visible(false);
         } else {
             let _ = window.show();
+            ensure_on_screen(&window);
             let _ = window.set_focus();
+            set_main_window_visible(true);
         }
     }
 }
@@ -14,25 +35,199 @@ pub fn toggle_main_window(app: &AppHandle) {
 pub fn show_main_window(app: &AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         let _ = window.show();
+        ensure_on_screen(&window);
         let _ = window.set_focus();
+        set_main_window_visible(true);
     }
 }
 
 pub fn hide_main_window(app: &AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         let _ = window.hide();
+        set_main_window_visible(false);
     }
 }
 
+/// Apply size, always-on-top and dark mode settings from the config
+pub fn apply_window_config(app: &AppHandle, config: &ClipboardConfig) {
+    if let Some(window) = app.get_webview_window("main") {
+        let width = config.window_width.max(MIN_WINDOW_WIDTH);
+        let height = config.window_height.max(MIN_WINDOW_HEIGHT);
+        let _ = window.set_size(LogicalSize::new(width, height));
+    }
+    let _ = apply_always_on_top(app, effective_always_on_top(config.always_on_top));
+    let _ = apply_dark_mode(app, config.force_dark_mode);
+}
+
+/// Always-on-top toggled at runtime, with the config value it was toggled
+/// against. It holds across reloads until `clipboard.always_on_top` changes.
+static ALWAYS_ON_TOP_OVERRIDE: Mutex<Option<(bool, bool)>> = Mutex::new(None);
+
+/// Whether the window is currently kept above others
+static ALWAYS_ON_TOP: AtomicBool = AtomicBool::new(false);
+
+pub fn is_always_on_top() -> bool {
+    ALWAYS_ON_TOP.load(Ordering::Relaxed)
+}
+
+/// Runtime toggle if it is still current for `configured`, else the config value
+fn effective_always_on_top(configured: bool) -> bool {
+    let mut toggled = crate::utils::lock_recover(&ALWAYS_ON_TOP_OVERRIDE);
+    match *toggled {
+        Some((enabled, toggled_against)) if toggled_against == configured => enabled,
+        _ => {
+            *toggled = None;
+            configured
+        }
+    }
+}
+
+/// Keep the window above others (or stop) until `clipboard.always_on_top`
+/// changes from `configured`
+pub fn set_always_on_top_override(app: &AppHandle, enabled: bool, configured: bool) -> tauri::Result<()> {
+    *crate::utils::lock_recover(&ALWAYS_ON_TOP_OVERRIDE) = (enabled != configured).then_some((enabled, configured));
+    apply_always_on_top(app, enabled)
+}
+
+fn apply_always_on_top(app: &AppHandle, enabled: bool) -> tauri::Result<()> {
+    if let Some(window) = app.get_webview_window("main") {
+        window.set_always_on_top(enabled)?;
+    }
+    ALWAYS_ON_TOP.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Force the window into the dark theme, or hand it back to the system theme
+pub fn apply_dark_mode(app: &AppHandle, force_dark: bool) -> tauri::Result<()> {
+    match app.get_webview_window("main") {
+        Some(window) => window.set_theme(force_dark.then_some(Theme::Dark)),
+        None => Ok(()),
+    }
+}
+
+/// The monitor the picker is on, for `get_window_monitor`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WindowMonitor {
+    pub name: Option<String>,
+    pub position: PhysicalPosition<i32>,
+    pub size: tauri::PhysicalSize<u32>,
+    pub scale_factor: f64,
+}
+
+pub fn window_monitor(app: &AppHandle) -> Result<WindowMonitor, String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or_else(|| "Main window not found".to_string())?;
+    let monitor = window
+        .current_monitor()
+        .map_err(|e| format!("Failed to query monitor: {}", e))?
+        .ok_or_else(|| "The window is not on any monitor".to_string())?;
+
+    Ok(WindowMonitor {
+        name: monitor.name().cloned(),
+        position: *monitor.position(),
+        size: *monitor.size(),
+        scale_factor: monitor.scale_factor(),
+    })
+}
+
+/// How often the monitor layout is re-checked while the window is shown
+const DISPLAY_CHECK_INTERVAL_MS: u64 = 2000;
+
+/// Move the window onto a monitor if its center isn't on any of them
+/// (e.g. it was last placed on a display that has since been unplugged)
+fn ensure_on_screen(window: &WebviewWindow) {
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.outer_size()) else {
+        return;
+    };
+    let Ok(monitors) = window.available_monitors() else {
+        return;
+    };
+    if monitors.is_empty() {
+        return;
+    }
+
+    let center_x = position.x + size.width as i32 / 2;
+    let center_y = position.y + size.height as i32 / 2;
+    let visible = monitors.iter().any(|m| {
+        let (origin, area) = (m.position(), m.size());
+        (origin.x..origin.x + area.width as i32).contains(&center_x)
+            && (origin.y..origin.y + area.height as i32).contains(&center_y)
+    });
+    if visible {
+        return;
+    }
+
+    let target = window
+        .primary_monitor()
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| monitors[0].clone());
+    let (origin, area) = (target.position(), target.size());
+    let x = origin.x + (area.width as i32 - size.width as i32).max(0) / 2;
+    let y = origin.y + (area.height as i32 - size.height as i32).max(0) / 2;
+    let _ = window.set_position(PhysicalPosition::new(x, y));
+}
+
+/// Periodically pull the shown window back on screen after docking,
+/// undocking or other monitor layout changes. Tauri has no display-change event.
+pub fn spawn_display_watcher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_millis(DISPLAY_CHECK_INTERVAL_MS)).await;
+            if !is_main_window_visible() {
+                continue;
+            }
+            if let Some(window) = app.get_webview_window("main") {
+                ensure_on_screen(&window);
+            }
+        }
+    });
+}
+
+/// Bumped on every focus gain; a pending blur-hide only fires if the
+/// generation it started with is still current
+static FOCUS_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Hide after `hide_on_blur_delay_ms` unless the window regains focus first
+fn schedule_hide_on_blur(app: &AppHandle) {
+    let Some(cfg) = current_clipboard_config(app) else {
+        return;
+    };
+    if !cfg.hide_on_blur {
+        return;
+    }
+
+    let generation = FOCUS_GENERATION.load(Ordering::SeqCst);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(cfg.hide_on_blur_delay_ms)).await;
+        if FOCUS_GENERATION.load(Ordering::SeqCst) == generation && is_main_window_visible() {
+            hide_main_window(&app);
+        }
+    });
+}
+
 pub fn setup_window_close_handler(app_handle: AppHandle) {
     if let Some(main_window) = app_handle.get_webview_window("main") {
         main_window.on_window_event({
             let app_handle = app_handle.clone();
-            move |event| {
-                if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+            move |event| match event {
+                tauri::WindowEvent::CloseRequested { api, .. } => {
                     api.prevent_close();
                     hide_main_window(&app_handle);
                 }
+                tauri::WindowEvent::Focused(true) => {
+                    FOCUS_GENERATION.fetch_add(1, Ordering::SeqCst);
+                }
+                tauri::WindowEvent::Focused(false) => schedule_hide_on_blur(&app_handle),
+                // Scale changes accompany most monitor layout changes
+                tauri::WindowEvent::ScaleFactorChanged { .. } => {
+                    if let Some(window) = app_handle.get_webview_window("main") {
+                        ensure_on_screen(&window);
+                    }
+                }
+                _ => {}
             }
         });
     }
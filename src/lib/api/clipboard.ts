@@ -7,6 +7,108 @@ export interface ClipboardEntry {
     content?: number[];  // Changed from string to byte array
     timestamp: string;
     content_type: string;
+    pinned?: boolean;
+    downscaled?: boolean;
+    expires_at?: string | null;
+    frame_count?: number | null;
+    use_count?: number;
+    recovered?: boolean;
+    source_app?: string | null;
+    source_encoding?: string | null;
+    pin_order?: number | null;
+    label?: string | null;
+    dimensions?: [number, number] | null;
+    last_used?: string | null;
+    truncated?: boolean;
+    mimes?: string[];
+}
+
+export interface EntryPreview {
+    id: string;
+    content_type: string;
+    timestamp: string;
+    preview: string;
+}
+
+export interface GatherResult {
+    path: string;
+    alias: string | null;
+    status: 'loaded' | 'missing' | 'parse_error';
+    message?: string;
+}
+
+export interface AppInfo {
+    version: string;
+    tauri_version: string;
+    desktop_env: string;
+    backend: string;
+}
+
+export interface HistoryHealth {
+    history_dir: string;
+    dir_writable: boolean;
+    store_path: string | null;
+    store_exists: boolean;
+    store_parses: boolean;
+    round_trip_ok: boolean;
+    errors: string[];
+}
+
+export interface ClipboardExplain {
+    content_type: string;
+    size: number;
+    ignored: boolean;
+    ignore_reason: string | null;
+}
+
+export interface EffectiveKeybind {
+    action: string;
+    key: string;
+    source: 'config' | 'default';
+}
+
+// new_id is null with no error when the result repeats the latest entry
+export interface TransformResult {
+    id: string;
+    new_id: string | null;
+    error: string | null;
+}
+
+export interface TemplateVariable {
+    name: string;
+    description: string;
+}
+
+export interface DeletionRecord {
+    id: string;
+    content_type: string;
+    content_size: number;
+    removed_at: string;
+    reason: 'deleted' | 'trashed' | 'purged' | 'evicted' | 'expired' | 'deduped' | 'cleared';
+}
+
+export interface QueryParams {
+    offset?: number;
+    limit?: number;
+    content_type_filter?: string;
+    search?: string;
+    fuzzy?: boolean;
+    order?: 'display' | 'newest' | 'oldest' | 'largest' | 'smallest';
+    date_from?: string;
+    date_to?: string;
+    pinned_only?: boolean;
+}
+
+export interface QueryResult {
+    entries: ClipboardEntry[];
+    total: number;
+}
+
+export interface ImageInfo {
+    width: number;
+    height: number;
+    format: string;
+    size_bytes: number;
 }
 
 export interface ClipboardData {
@@ -19,6 +121,15 @@ export class ClipboardService {
         return await invoke('set_system_clipboard', { text });
     }
 
+    // Copies an image file and records it with the file as its source
+    static async importImageFile(path: string): Promise<void> {
+        return await invoke('import_image_file', { path });
+    }
+
+    static async addHistoryEntry(content: number[], contentType?: string, sourcePath?: string): Promise<void> {
+        return await invoke('add_history_entry', { content, contentType, sourcePath });
+    }
+
     static async getClipboard(): Promise<ClipboardData> {
         return await invoke('get_system_clipboard');
     }
@@ -27,21 +138,402 @@ export class ClipboardService {
         return await invoke('get_clipboard_entry_content', { entryId });
     }
 
+    static async getEntriesInRange(from: string, to: string): Promise<ClipboardEntry[]> {
+        return await invoke('get_entries_in_range', { from, to });
+    }
+
+    // Biggest first; pass null to leave a bound open
+    static async getEntriesBySize(minBytes: number | null, maxBytes: number | null): Promise<ClipboardEntry[]> {
+        return await invoke('get_entries_by_size', { minBytes, maxBytes });
+    }
+
+    // Latest entry at or before `ts` (ISO 8601), or null
+    static async getEntryAtTime(ts: string): Promise<ClipboardEntry | null> {
+        return await invoke('get_entry_at_time', { ts });
+    }
+
     static async getHistory(limit?: number): Promise<ClipboardEntry[]> {
         return await invoke('get_clipboard_history', { limit });
     }
 
+    static async getAdjacentEntry(entryId: string, direction: 'up' | 'down'): Promise<ClipboardEntry | null> {
+        return await invoke('get_adjacent_entry', { entryId, direction });
+    }
+
+    static async queryHistory(params: QueryParams): Promise<QueryResult> {
+        return await invoke('query_history', { params });
+    }
+
+    static async getRecentPreviews(n: number, previewBytes: number): Promise<EntryPreview[]> {
+        return await invoke('get_recent_previews', { n, previewBytes });
+    }
+
     static async clearHistory(): Promise<void> {
         return await invoke('clear_clipboard_history');
     }
 
+    static async clearUnpinnedHistory(): Promise<number> {
+        return await invoke('clear_unpinned_history');
+    }
+
+    static async pinMatching(query: string, fuzzy: boolean): Promise<number> {
+        return await invoke('pin_matching', { query, fuzzy });
+    }
+
+    static async setEntryExpiry(entryId: string, ttlSecs: number | null): Promise<boolean> {
+        return await invoke('set_entry_expiry', { entryId, ttlSecs });
+    }
+
+    // contentType must be 'text', 'binary' or 'image/<type>'
+    static async setEntryContentType(entryId: string, contentType: string): Promise<boolean> {
+        return await invoke('set_entry_content_type', { entryId, contentType });
+    }
+
+    static async dedupeHistory(): Promise<number> {
+        return await invoke('dedupe_history');
+    }
+
+    static async setEntryPinned(entryId: string, pinned: boolean): Promise<boolean> {
+        return await invoke('set_entry_pinned', { entryId, pinned });
+    }
+
     static async removeEntry(entryId: string): Promise<boolean> {
         return await invoke('remove_clipboard_entry', { entryId });
     }
 
+    static async removeEntries(entryIds: string[]): Promise<number> {
+        return await invoke('remove_clipboard_entries', { entryIds });
+    }
+
+    // Groups of ids with identical content, newest first; nothing is removed
+    static async findDuplicates(): Promise<string[][]> {
+        return await invoke('find_duplicates');
+    }
+
+    static async quickPaste(index: number): Promise<string> {
+        return await invoke('quick_paste', { index });
+    }
+
+    static async updateEntryContent(entryId: string, content: number[]): Promise<void> {
+        return await invoke('update_entry_content', { entryId, content });
+    }
+
     static async setFromHistory(entryId: string): Promise<void> {
         return await invoke('set_clipboard_from_history', { entryId });
     }
+
+    // Fills {{date}}, {{time}}, {{datetime}}, {{clipboard}} and {{cursor}} before copying
+    static async applySnippet(entryId: string): Promise<void> {
+        return await invoke('apply_snippet', { entryId });
+    }
+
+    static async getTemplateVariables(): Promise<TemplateVariable[]> {
+        return await invoke('get_template_variables');
+    }
+
+    // Saves the current clipboard as a pinned, labelled snippet; returns its id
+    static async snippetFromClipboard(label: string): Promise<string> {
+        return await invoke('snippet_from_clipboard', { label });
+    }
+
+    static async concatEntries(ids: string[], separator: string, record: boolean): Promise<string> {
+        return await invoke('concat_entries', { ids, separator, record });
+    }
+
+    // Appends a text entry to the current clipboard text and returns the result
+    static async appendToClipboard(entryId: string, separator: string, record: boolean): Promise<string> {
+        return await invoke('append_to_clipboard', { entryId, separator, record });
+    }
+
+    static async openEntryUrl(entryId: string): Promise<void> {
+        return await invoke('open_entry_url', { entryId });
+    }
+
+    // Session only: reverts when claw restarts; 0 clears it
+    static async setSessionHistoryLimit(limit: number): Promise<void> {
+        await invoke('set_session_history_limit', { limit });
+    }
+
+    static async setTrayHistoryEnabled(enabled: boolean): Promise<void> {
+        return await invoke('set_tray_history_enabled', { enabled });
+    }
+
+    static async setPersistHistory(enabled: boolean): Promise<void> {
+        return await invoke('set_persist_history', { enabled });
+    }
+
+    static async setForceDarkMode(enabled: boolean): Promise<void> {
+        return await invoke('set_force_dark_mode', { enabled });
+    }
+
+    static async exportSettings(): Promise<string> {
+        return await invoke('export_settings');
+    }
+
+    static async importSettings(json: string): Promise<number> {
+        return await invoke('import_settings', { json });
+    }
+
+    static async setAlwaysOnTop(enabled: boolean, persist = false): Promise<boolean> {
+        return await invoke('set_always_on_top', { enabled, persist });
+    }
+
+    static async getAlwaysOnTop(): Promise<boolean> {
+        return await invoke('get_always_on_top');
+    }
+
+    static async getWindowMonitor(): Promise<{
+        name: string | null;
+        position: { x: number; y: number };
+        size: { width: number; height: number };
+        scale_factor: number;
+    }> {
+        return await invoke('get_window_monitor');
+    }
+
+    static async getConfigValue(key: string): Promise<string | null> {
+        return await invoke('get_config_value', { key });
+    }
+
+    static async getEffectiveKeybinds(): Promise<EffectiveKeybind[]> {
+        return await invoke('get_effective_keybinds');
+    }
+
+    static async setConfigValue(key: string, value: string): Promise<void> {
+        return await invoke('set_config_value', { key, value });
+    }
+
+    static async pauseConfigWatch(): Promise<void> {
+        return await invoke('pause_config_watch');
+    }
+
+    static async resumeConfigWatch(): Promise<void> {
+        return await invoke('resume_config_watch');
+    }
+
+    static async getConfigPath(): Promise<string | null> {
+        return await invoke('get_config_path');
+    }
+
+    static async openConfigFile(): Promise<void> {
+        return await invoke('open_config_file');
+    }
+
+    // The new theme is also broadcast through `config-reloaded`
+    static async setActiveThemeAlias(alias: string, persist = false): Promise<void> {
+        await invoke('set_active_theme_alias', { alias, persist });
+    }
+
+    static async getTopPinned(limit: number): Promise<ClipboardEntry[]> {
+        return await invoke('get_top_pinned', { limit });
+    }
+
+    static async resetUsageStats(): Promise<void> {
+        await invoke('reset_usage_stats');
+    }
+
+    static async listClipboardMimes(): Promise<string[]> {
+        return await invoke('list_clipboard_mimes');
+    }
+
+    // Empty when the clipboard doesn't offer `mime`
+    static async getClipboardMime(mime: string): Promise<number[]> {
+        return await invoke('get_clipboard_mime', { mime });
+    }
+
+    static async getCurrentEntryId(): Promise<string | null> {
+        return await invoke('get_current_entry_id');
+    }
+
+    static async setClipboardFromHistoryAs(entryId: string, targetFormat: 'png' | 'jpeg' | 'webp'): Promise<void> {
+        await invoke('set_clipboard_from_history_as', { entryId, targetFormat });
+    }
+
+    // Returns how many entries were recovered
+    static async rebuildIndexFromDisk(): Promise<number> {
+        return await invoke('rebuild_index_from_disk');
+    }
+
+    // Migrates the history store first if it is on an older schema
+    static async getSchemaVersion(): Promise<{ stored: number | null; current: number; migrated: boolean }> {
+        return await invoke('get_schema_version');
+    }
+
+    static async getLastError(): Promise<string | null> {
+        return await invoke('get_last_error');
+    }
+
+    static async clearLastError(): Promise<void> {
+        await invoke('clear_last_error');
+    }
+
+    // Temp file path for dragging the entry out; removed when claw exits
+    static async materializeEntry(entryId: string): Promise<string> {
+        return await invoke('materialize_entry', { entryId });
+    }
+
+    // Record the current clipboard (the way to add entries in manual capture mode)
+    static async captureNow(): Promise<void> {
+        await invoke('capture_now');
+    }
+
+    static async getWatchedPaths(): Promise<string[]> {
+        return await invoke('get_watched_paths');
+    }
+
+    static async explainCurrentClipboard(): Promise<ClipboardExplain> {
+        return await invoke('explain_current_clipboard');
+    }
+
+    // Reloads the config and watches newly gathered files; returns the watched files
+    static async rescanGatherFiles(): Promise<string[]> {
+        return await invoke('rescan_gather_files');
+    }
+
+    static async getLastSelected(): Promise<string | null> {
+        return await invoke('get_last_selected');
+    }
+
+    // App name -> entry ids; entries without a known app are under "unknown"
+    static async getHistoryByApp(): Promise<Record<string, string[]>> {
+        return await invoke('get_history_by_app');
+    }
+
+    // Middle-click paste buffer only; the regular clipboard is unchanged
+    static async setPrimaryFromHistory(entryId: string): Promise<void> {
+        await invoke('set_primary_from_history', { entryId });
+    }
+
+    // Invalid UTF-8 shows as replacement characters instead of nothing
+    static async getEntryTextLossy(entryId: string): Promise<string> {
+        return await invoke('get_entry_text_lossy', { entryId });
+    }
+
+    // Ops: lowercase, uppercase, trim, collapse_whitespace, trim_blank_lines,
+    // strip_ansi, sort_lines, dedupe_lines. Returns one result per id.
+    static async transformEntries(ids: string[], ops: string[]): Promise<TransformResult[]> {
+        return await invoke('transform_entries', { ids, ops });
+    }
+
+    // Only works for entries deleted with soft_delete on, within 10 minutes
+    static async restoreEntry(entryId: string): Promise<void> {
+        return await invoke('restore_entry', { entryId });
+    }
+
+    static async getDeletionLog(): Promise<DeletionRecord[]> {
+        return await invoke('get_deletion_log');
+    }
+
+    static async exportHistoryText(destPath: string, format: 'txt' | 'md'): Promise<number> {
+        return await invoke('export_history_text', { destPath, format });
+    }
+
+    // "#rrggbb"; undecodable images give a neutral grey
+    static async getEntryDominantColor(entryId: string): Promise<string> {
+        return await invoke('get_entry_dominant_color', { entryId });
+    }
+
+    // Fields are null when history is too short to extrapolate from
+    static async getGrowthEstimate(): Promise<{
+        daily_avg_bytes: number | null;
+        daily_avg_entries: number | null;
+        projected_days_to_limit: number | null;
+    }> {
+        return await invoke('get_growth_estimate');
+    }
+
+    // Bounded full text for the peek popup (the backend caps maxBytes at 1MB)
+    static async getEntryFullText(entryId: string, maxBytes: number): Promise<{ text: string; truncated: boolean }> {
+        return await invoke('get_entry_full_text', { entryId, maxBytes });
+    }
+
+    // Hex SHA-256 of the stored content
+    static async getEntryChecksum(entryId: string): Promise<string> {
+        return await invoke('get_entry_checksum', { entryId });
+    }
+
+    static async getImageInfo(entryId: string): Promise<ImageInfo> {
+        return await invoke('get_image_info', { entryId });
+    }
+
+    // All ids must be pinned; listed pins are shown first in this order
+    static async reorderPins(orderedIds: string[]): Promise<void> {
+        return await invoke('reorder_pins', { orderedIds });
+    }
+
+    // False when there was nothing cached to put back
+    static async reinjectClipboard(): Promise<boolean> {
+        return await invoke('reinject_clipboard');
+    }
+
+    static async getActiveProfile(): Promise<string> {
+        return await invoke('get_active_profile');
+    }
+
+    // Lasts for this session; set `profile` in the config to keep it
+    static async setActiveProfile(name: string): Promise<void> {
+        return await invoke('set_active_profile', { name });
+    }
+
+    static async listProfiles(): Promise<string[]> {
+        return await invoke('list_profiles');
+    }
+
+    static async createProfile(name: string): Promise<void> {
+        return await invoke('create_profile', { name });
+    }
+
+    static async deleteProfile(name: string): Promise<void> {
+        return await invoke('delete_profile', { name });
+    }
+
+    static async getGatherResults(): Promise<GatherResult[]> {
+        return await invoke('get_gather_results');
+    }
+
+    static async historyHealthCheck(): Promise<HistoryHealth> {
+        return await invoke('history_health_check');
+    }
+
+    static async getAppInfo(): Promise<AppInfo> {
+        return await invoke('get_app_info');
+    }
+
+    static async getWatcherDiagnostics(): Promise<{ restarts: number; memory_only: boolean }> {
+        return await invoke('get_watcher_diagnostics');
+    }
+
+    static async getBackendStatus(): Promise<{
+        detected_env: string;
+        active_backend: string;
+        override_source: string | null;
+        last_read_ok: boolean;
+        last_read_error: string | null;
+    }> {
+        return await invoke('get_backend_status');
+    }
+
+    // Estimated capture latency, for tuning the poll interval
+    static async getWatcherMetrics(): Promise<{
+        last_latency_ms: number | null;
+        average_latency_ms: number | null;
+        captures: number;
+        poll_interval_ms: number;
+    }> {
+        return await invoke('get_watcher_metrics');
+    }
+
+    static async getSessionStats(): Promise<{
+        captured_this_session: number;
+        bytes_this_session: number;
+        started_at: string;
+    }> {
+        return await invoke('get_session_stats');
+    }
+
+    static async revealInFileManager(entryId: string): Promise<void> {
+        return await invoke('reveal_in_file_manager', { entryId });
+    }
 }
 
 // Helper to decode bytes to text
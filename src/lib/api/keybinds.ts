@@ -7,6 +7,7 @@ export type Keybinds = {
     useEntry: string;
     removeEntry: string;
     deleteAll: string;
+    peek: string;
 };
 
 // Default keybinds
@@ -15,7 +16,8 @@ export const keybinds = writable<Keybinds>({
     historyDown: 'ArrowDown',
     useEntry: 'Enter',
     removeEntry: 'x',
-    deleteAll: '' // empty by default
+    deleteAll: '', // empty by default
+    peek: 'p'
 });
 
 // Normalize config key strings
@@ -53,6 +55,7 @@ export function setKeybindsFromConfig(config: {
         select: string;
         delete: string;
         delete_all: string;
+        peek: string;
     };
 }) {
     keybinds.set({
@@ -60,7 +63,8 @@ export function setKeybindsFromConfig(config: {
         historyDown: normalizeKey(config.keybinds.down || 'ArrowDown'),
         useEntry: normalizeKey(config.keybinds.select || 'Enter'),
         removeEntry: normalizeKey(config.keybinds.delete || 'x'),
-        deleteAll: normalizeKey(config.keybinds.delete_all || '')
+        deleteAll: normalizeKey(config.keybinds.delete_all || ''),
+        peek: normalizeKey(config.keybinds.peek || 'p')
     });
     console.log('Keybinds set:', get(keybinds));
 }